@@ -1,4 +1,6 @@
 use logflow::prelude::*;
+use logflow::FormatterType;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -6,13 +8,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Custom formatter function
     println!("--- Custom Formatter ---");
-    let _custom_logger = LogFlow::new()
-        .with_colors(true)
-        .with_timestamps(true)
+    let seq = AtomicUsize::new(0);
+    let custom_logger = LogFlow::new()
+        .with_formatter(FormatterType::custom(
+            move |level: &LogLevel, message: &str, _: &LogContext, _: &LogConfig| {
+                let n = seq.fetch_add(1, Ordering::Relaxed);
+                format!("#{:03} [{}] {}", n, level, message)
+            },
+        ))
         .build()?;
 
-    // Note: For this example, we'll use the built-in formatters
-    // but show how different configurations create different outputs
+    custom_logger.info("Formatted by a closure")?;
+    custom_logger.warn("With its own counter")?;
 
     // Development mode with all details
     println!("\n--- Development Mode (All Details) ---");
@@ -25,7 +25,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Simulate some API requests
     {
         let server_scope = logger.begin_scope("http_server");
-        server_scope.info_with_subtitle("LISTENING", "Server listening on http://localhost:8080")?;
+        server_scope
+            .info_with_subtitle("LISTENING", "Server listening on http://localhost:8080")?;
 
         // Simulate incoming requests
         simulate_api_request(&server_scope, "POST", "/api/users", 201, 45)?;
@@ -72,7 +73,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .with_subtitle("METRICS")
             .info("Cache performance metrics")?;
 
-        cache_scope.error_with_subtitle("REDIS", "Redis connection lost, falling back to in-memory cache")?;
+        cache_scope.error_with_subtitle(
+            "REDIS",
+            "Redis connection lost, falling back to in-memory cache",
+        )?;
         cache_scope.info_with_subtitle("RETRY", "Attempting to reconnect to Redis...")?;
         cache_scope.info_with_subtitle("SUCCESS", "Redis connection restored")?;
     }
@@ -86,7 +90,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_subtitle("HEALTH")
         .info("Application health metrics")?;
 
-    logger.info_with_subtitle("STATUS", "Application running smoothly - all systems operational")?;
+    logger.info_with_subtitle(
+        "STATUS",
+        "Application running smoothly - all systems operational",
+    )?;
     logger.debug_with_subtitle("BACKUP", "Next scheduled backup in 2 hours")?;
 
     Ok(())
@@ -124,4 +131,4 @@ fn simulate_api_request(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
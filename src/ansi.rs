@@ -0,0 +1,220 @@
+// Measuring and cutting colored text by what the terminal shows. Escape sequences
+// take no columns and are never split; wide CJK characters and emoji take two.
+
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthChar;
+
+const RESET: &str = "\x1b[0m";
+const LINK_END: &str = "\x1b]8;;\x1b\\";
+
+#[derive(Clone, Copy)]
+pub(crate) enum Piece<'a> {
+    Escape(&'a str),
+    Char(char),
+}
+
+pub(crate) struct Pieces<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = Piece<'a>;
+
+    fn next(&mut self) -> Option<Piece<'a>> {
+        let rest = &self.text[self.pos..];
+        let mut chars = rest.char_indices();
+        let (_, c) = chars.next()?;
+        if c != '\x1b' {
+            self.pos += c.len_utf8();
+            return Some(Piece::Char(c));
+        }
+        let len = match chars.next() {
+            // CSI: parameters up to a final byte in @..~
+            Some((_, '[')) => chars
+                .find(|(_, c)| ('@'..='~').contains(c))
+                .map_or(rest.len(), |(i, c)| i + c.len_utf8()),
+            // OSC: up to BEL or ESC \
+            Some((_, ']')) => match rest[2..].find(['\x07', '\x1b']) {
+                Some(i) if rest[2 + i..].starts_with("\x1b\\") => 2 + i + 2,
+                Some(i) if rest[2 + i..].starts_with('\x07') => 2 + i + 1,
+                Some(i) => 2 + i,
+                None => rest.len(),
+            },
+            Some((i, c)) => i + c.len_utf8(),
+            None => 1,
+        };
+        self.pos += len;
+        Some(Piece::Escape(&rest[..len]))
+    }
+}
+
+// `text` split into escape sequences and the chars between them
+pub(crate) fn pieces(text: &str) -> Pieces<'_> {
+    Pieces { text, pos: 0 }
+}
+
+// Control chars, newlines included, take no columns
+fn columns(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+fn piece_columns(piece: &Piece) -> usize {
+    match piece {
+        Piece::Char(c) => columns(*c),
+        Piece::Escape(_) => 0,
+    }
+}
+
+/// Columns `text` takes on a terminal, leaving out escape sequences.
+pub(crate) fn visible_width(text: &str) -> usize {
+    pieces(text).map(|piece| piece_columns(&piece)).sum()
+}
+
+// Some(true) for an OSC 8 sequence opening a hyperlink, Some(false) for one closing it
+fn link(escape: &str) -> Option<bool> {
+    let params = escape.strip_prefix("\x1b]8;")?;
+    let url = params.split_once(';').map_or("", |(_, rest)| rest);
+    Some(!url.trim_end_matches(['\x07', '\x1b', '\\']).is_empty())
+}
+
+/// Cuts `text` to `width` columns ending in `...`, closing any color or link left open.
+pub(crate) fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if visible_width(text) <= width {
+        return Cow::Borrowed(text);
+    }
+
+    let ellipsis = &"..."[..width.min(3)];
+    let mut keep = width - ellipsis.len();
+    let mut cut = String::with_capacity(text.len().min(width * 4));
+    let mut styled = false;
+    let mut linked = false;
+    for piece in pieces(text) {
+        match piece {
+            Piece::Escape(escape) => {
+                styled = true;
+                linked = link(escape).unwrap_or(linked);
+                cut.push_str(escape);
+            }
+            // A wide char that would straddle the cut is left out
+            Piece::Char(c) if columns(c) > keep => break,
+            Piece::Char(c) => {
+                keep -= columns(c);
+                cut.push(c);
+            }
+        }
+    }
+    cut.push_str(ellipsis);
+    if linked {
+        cut.push_str(LINK_END);
+    }
+    if styled {
+        cut.push_str(RESET);
+    }
+    Cow::Owned(cut)
+}
+
+/// Breaks `text` into lines of at most `width` columns, at the last space where there
+/// is one. Lines after the first start with `continuation`, and colors and links open
+/// at a break are closed before it and reopened after.
+pub(crate) fn wrap(text: &str, width: usize, continuation: &str) -> String {
+    if width == 0 || text.lines().all(|line| visible_width(line) <= width) {
+        return text.to_string();
+    }
+
+    let continued_width = width.saturating_sub(visible_width(continuation)).max(1);
+    let mut wrapped = String::with_capacity(text.len() + text.len() / width * 8);
+    let mut active = Active::default();
+    let mut line: Vec<Piece> = Vec::new();
+    let mut width_used = 0;
+    let mut limit = width;
+
+    for piece in pieces(text) {
+        if let Piece::Char('\n') = piece {
+            flush(&mut wrapped, &mut active, line.drain(..));
+            wrapped.push('\n');
+            width_used = 0;
+            limit = width;
+            continue;
+        }
+
+        // A word carried to the next line can still be too long for it, so this
+        // repeats until the piece fits or the line is empty
+        let needed = piece_columns(&piece);
+        let mut broke = false;
+        while width_used > 0 && width_used + needed > limit {
+            let split = line
+                .iter()
+                .rposition(|piece| matches!(piece, Piece::Char(' ')))
+                .filter(|&i| line[..i].iter().any(|p| matches!(p, Piece::Char(_))));
+            let carried: Vec<Piece> = match split {
+                Some(i) => {
+                    let carried = line.split_off(i + 1);
+                    line.pop();
+                    carried
+                }
+                None => Vec::new(),
+            };
+            flush(&mut wrapped, &mut active, line.drain(..));
+            if active.link.is_some() {
+                wrapped.push_str(LINK_END);
+            }
+            if !active.sgr.is_empty() {
+                wrapped.push_str(RESET);
+            }
+            wrapped.push('\n');
+            wrapped.push_str(continuation);
+            wrapped.push_str(&active.sgr);
+            wrapped.push_str(active.link.unwrap_or_default());
+            width_used = carried.iter().map(piece_columns).sum();
+            line = carried;
+            limit = continued_width;
+            broke = true;
+        }
+        if broke && matches!(piece, Piece::Char(' ')) && line.is_empty() {
+            continue;
+        }
+        width_used += needed;
+        line.push(piece);
+    }
+    flush(&mut wrapped, &mut active, line.drain(..));
+    wrapped
+}
+
+#[derive(Default)]
+struct Active<'a> {
+    // SGR sequences in effect since the last reset
+    sgr: String,
+    link: Option<&'a str>,
+}
+
+fn flush<'a>(out: &mut String, active: &mut Active<'a>, line: impl Iterator<Item = Piece<'a>>) {
+    for piece in line {
+        match piece {
+            Piece::Char(c) => out.push(c),
+            Piece::Escape(escape) => {
+                if escape == RESET || escape == "\x1b[m" {
+                    active.sgr.clear();
+                } else if escape.starts_with("\x1b[") && escape.ends_with('m') {
+                    active.sgr.push_str(escape);
+                } else if let Some(open) = link(escape) {
+                    active.link = open.then_some(escape);
+                }
+                out.push_str(escape);
+            }
+        }
+    }
+}
+
+/// Width of the terminal: `COLUMNS` when set, otherwise what the terminal reports.
+pub(crate) fn terminal_width() -> Option<usize> {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .or_else(|| {
+            crossterm::terminal::size()
+                .ok()
+                .map(|(columns, _)| columns.into())
+        })
+        .filter(|&columns| columns > 0)
+}
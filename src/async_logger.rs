@@ -1,16 +1,42 @@
+use crate::budget::{BudgetState, BudgetTracker};
+#[cfg(feature = "async")]
+use crate::color::{ColorDepth, ColorMode};
 #[cfg(feature = "async")]
 use crate::config::LogConfig;
 #[cfg(feature = "async")]
-use crate::context::LogContext;
+use crate::context::{typed_field_methods, FieldValue, LogContext};
+#[cfg(feature = "async")]
+use crate::dedup::{Deduplicator, Repeated};
+#[cfg(feature = "async")]
+use crate::facade::Log;
+#[cfg(feature = "async")]
+use crate::formatter::{
+    FieldOrder, FieldRenderer, Formatter, FormatterType, IndentStyle, JsonFields, Overflow, Style,
+    Theme, TimestampFormat, Timezone,
+};
+#[cfg(feature = "async")]
+use crate::level::{BoostState, LevelBoost, LevelCell, LogLevel};
+#[cfg(feature = "async")]
+use crate::memory::RecordQuery;
+#[cfg(feature = "async")]
+use crate::notify::{self, Notification};
+#[cfg(feature = "async")]
+use crate::output::{BrokenPipePolicy, Output, OutputId, OutputType, Record};
 #[cfg(feature = "async")]
-use crate::formatter::Formatter;
+use crate::pipeline::RecordHooks;
 #[cfg(feature = "async")]
-use crate::level::LogLevel;
+use crate::ratelimit::{suppressed_notice, RateDecision, RateLimiter};
 #[cfg(feature = "async")]
-use crate::output::{Output, OutputType};
+use crate::sampling;
+#[cfg(feature = "async")]
+use crate::syslog::Facility;
 #[cfg(feature = "async")]
 use crate::LogFlowError;
 #[cfg(feature = "async")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "async")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "async")]
 use std::sync::Arc;
 #[cfg(feature = "async")]
 use tokio::sync::{Mutex, RwLock};
@@ -20,38 +46,138 @@ use tokio::time::{Duration, Instant};
 #[cfg(feature = "async")]
 type Result<T> = std::result::Result<T, LogFlowError>;
 
+// Plain outputs get one contiguous byte buffer; structured ones, and outputs with
+// extra per-level outputs, keep whole records
+#[cfg(feature = "async")]
+struct PendingWrites {
+    structured: bool,
+    bytes: Vec<u8>,
+    records: Vec<(Record, bool)>,
+    len: usize,
+}
+
+#[cfg(feature = "async")]
+impl PendingWrites {
+    fn new(structured: bool) -> Self {
+        Self {
+            structured,
+            bytes: Vec::new(),
+            records: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, record: Record, primary: bool) {
+        if self.structured {
+            self.records.push((record, primary));
+        } else if primary {
+            self.bytes.extend_from_slice(record.line.as_bytes());
+            self.bytes.push(b'\n');
+        }
+        self.len += 1;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn take(&mut self) -> Self {
+        let taken = Self {
+            structured: self.structured,
+            bytes: std::mem::take(&mut self.bytes),
+            records: std::mem::take(&mut self.records),
+            len: self.len,
+        };
+        self.len = 0;
+        taken
+    }
+
+    fn write_to(&self, output: &mut Output) -> std::io::Result<()> {
+        if self.structured {
+            output.write_records_to(&self.records)
+        } else {
+            output.write(&self.bytes)?;
+            output.flush()
+        }
+    }
+}
+
 #[cfg(feature = "async")]
 pub struct AsyncLogFlow {
     config: LogConfig,
     formatter: Formatter,
     output: Arc<Mutex<Output>>,
     context_stack: Arc<RwLock<Vec<LogContext>>>,
-    buffer: Arc<Mutex<Vec<String>>>,
+    buffer: Arc<Mutex<PendingWrites>>,
     buffer_size: usize,
     flush_interval: Duration,
     last_flush: Arc<Mutex<Instant>>,
+    level: Arc<LevelCell>,
+    boost: Arc<LevelBoost>,
+    budget: Arc<BudgetTracker>,
+    rate_limiter: Arc<RateLimiter>,
+    dedup: Arc<Deduplicator>,
+    hooks: Arc<RecordHooks>,
+    direct: Arc<AtomicBool>,
 }
 
 #[cfg(feature = "async")]
 impl AsyncLogFlow {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> AsyncLogFlowBuilder {
         AsyncLogFlowBuilder::new()
     }
 
+    /// Builds a logger from the `LOGFLOW_*` environment variables, see
+    /// [`LogConfig::apply_env`].
+    pub async fn from_env() -> Result<Self> {
+        Self::with_config(LogConfig::from_env()?).await
+    }
+
     pub async fn with_config(config: LogConfig) -> Result<Self> {
+        // Tasks move between threads, so there is no thread to keep the scopes on
+        if config.thread_local_scopes {
+            return Err(LogFlowError::Config(
+                "Thread-local scopes are not supported by the async logger".to_string(),
+            ));
+        }
+
         let formatter = Formatter::new(config.clone());
-        let output = Output::new(config.output.clone())?;
+        let output = Output::from_config(&config)?;
+
+        if config.check_outputs_on_build {
+            output.health_check()?;
+        }
 
-        Ok(Self {
+        let buffer = PendingWrites::new(output.is_structured() || output.has_extra_outputs());
+
+        let logger = Self {
             formatter,
             output: Arc::new(Mutex::new(output)),
+            level: Arc::new(LevelCell::new(config.level)),
+            budget: Arc::new(BudgetTracker::new(config.budget)),
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limit)),
+            dedup: Arc::new(Deduplicator::new(config.dedup_window)),
+            hooks: Arc::new(RecordHooks::default()),
             config,
             context_stack: Arc::new(RwLock::new(Vec::new())),
-            buffer: Arc::new(Mutex::new(Vec::new())),
+            buffer: Arc::new(Mutex::new(buffer)),
             buffer_size: 100,
             flush_interval: Duration::from_millis(100),
             last_flush: Arc::new(Mutex::new(Instant::now())),
-        })
+            boost: Arc::new(LevelBoost::new()),
+            direct: Arc::new(AtomicBool::new(false)),
+        };
+
+        if logger.config.dump_config {
+            logger.write_config_dump().await;
+        }
+
+        Ok(logger)
     }
 
     pub async fn log(&self, level: LogLevel, message: &str) -> Result<()> {
@@ -64,38 +190,318 @@ impl AsyncLogFlow {
         message: &str,
         extra_context: Option<LogContext>,
     ) -> Result<()> {
-        let target = std::module_path!().to_string();
+        self.log_rendered(level, message, extra_context, |formatter, record| {
+            formatter.render(record.level, &record.message, &record.context)
+        })
+        .await
+    }
+
+    // Routes, processes and renders a record with `render`, then writes it out
+    async fn log_rendered(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+        render: impl FnOnce(&Formatter, &Record) -> String,
+    ) -> Result<()> {
+        let target = std::module_path!();
+
+        let primary = match self.route(level, extra_context.as_ref(), target).await {
+            Some(primary) => primary,
+            None => return Ok(()),
+        };
 
-        if !self.config.should_log(level, &target) {
+        let Some(mut record) = self.processed_record(level, message, extra_context).await else {
             return Ok(());
+        };
+        record.line = render(&self.formatter, &record);
+
+        self.emit(record, primary).await?;
+        self.try_flush().await?;
+
+        Ok(())
+    }
+
+    // None when no output takes the record, otherwise whether the primary output does;
+    // extra outputs filter on their own levels
+    async fn route(
+        &self,
+        level: LogLevel,
+        context: Option<&LogContext>,
+        target: &str,
+    ) -> Option<bool> {
+        let current = match context {
+            Some(_) => None,
+            None => self.context_stack.read().await.last().cloned(),
+        };
+        let scope = context.or(current.as_ref());
+        // Records in a scope are filtered by the scope's target
+        let target = scope.map_or(target, |scope| scope.target.as_str());
+
+        let threshold = self.threshold(scope, target).await;
+        let floor = self.config.output_floor(threshold).max(self.budget.floor());
+        if !self.config.should_log_at(level, floor, target) {
+            return None;
+        }
+
+        // Sampled out records are dropped before any formatting work
+        if let Some(rate) = self.config.sample_rate(level) {
+            if !sampling::keep(rate) {
+                return None;
+            }
+        }
+        Some(level >= threshold)
+    }
+
+    pub async fn effective_level(&self) -> LogLevel {
+        let current = self.context_stack.read().await.last().cloned();
+        let target = current
+            .as_ref()
+            .map_or(std::module_path!(), |scope| scope.target.as_str());
+        self.threshold(current.as_ref(), target).await
+    }
+
+    // A scope level replaces the global or env filter level; an active boost still
+    // lowers it further
+    async fn threshold(&self, scope: Option<&LogContext>, target: &str) -> LogLevel {
+        let base = scope
+            .and_then(|scope| scope.min_level)
+            .or_else(|| self.config.filter_level(target))
+            .unwrap_or_else(|| self.level.get());
+
+        let threshold = match self.boost.check() {
+            BoostState::Active(boosted) => boosted.min(base),
+            BoostState::Expired(boosted) => {
+                self.write_notice(&format!(
+                    "Log level boost to {} expired, restored {}",
+                    boosted,
+                    self.level.get()
+                ))
+                .await;
+                base
+            }
+            BoostState::Inactive => base,
+        };
+
+        // A spent budget overrides scopes and boosts alike
+        match self.budget.check() {
+            BudgetState::Degraded(level) => threshold.max(level),
+            BudgetState::Renewed => {
+                self.write_notice("Log budget renewed, full logging restored")
+                    .await;
+                threshold
+            }
+            BudgetState::Open => threshold,
+        }
+    }
+
+    /// Changes the global level of the running logger; scope levels, env filter
+    /// directives and boosts still apply on top of it.
+    pub async fn set_level(&self, level: LogLevel) {
+        let previous = self.level.set(level);
+        if previous != level {
+            self.write_notice(&format!("Log level changed from {} to {}", previous, level))
+                .await;
+        }
+    }
+
+    pub async fn boost_level(&self, level: LogLevel, duration: std::time::Duration) {
+        self.boost.set(level, duration);
+        self.write_notice(&format!(
+            "Log level boosted to {} for {:?}",
+            level, duration
+        ))
+        .await;
+    }
+
+    pub async fn cancel_boost(&self) {
+        if let Some(boosted) = self.boost.clear() {
+            self.write_notice(&format!(
+                "Log level boost to {} cancelled, restored {}",
+                boosted,
+                self.level.get()
+            ))
+            .await;
         }
+    }
 
+    // Level changes are always recorded, regardless of the current threshold
+    async fn write_notice(&self, message: &str) {
+        let record = self.render_record(LogLevel::Info, message, None).await;
+        let _ = self.buffer_log(record, true).await;
+    }
+
+    // Written regardless of level or filters, since it explains what they are
+    async fn write_config_dump(&self) {
+        let context = self.config.summary().into_iter().fold(
+            self.config.new_context(std::module_path!().to_string()),
+            |context, (key, value)| context.with_field(key, value),
+        );
+        let record = self
+            .render_record(LogLevel::Info, "Logger configuration", Some(context))
+            .await;
+        let _ = self.output.lock().await.write_record(&record);
+    }
+
+    async fn build_context(
+        &self,
+        level: LogLevel,
+        extra_context: Option<LogContext>,
+    ) -> LogContext {
         let context = if let Some(ctx) = extra_context {
             ctx
         } else {
+            let target = std::module_path!().to_string();
             let stack = self.context_stack.read().await;
             if let Some(current_ctx) = stack.last() {
-                current_ctx.child(target)
+                self.config.child_context(current_ctx, target)
             } else {
-                LogContext::new(target)
+                self.config.new_context(target)
             }
         };
 
-        let formatted = self.formatter.format(level, message, &context);
+        let context = self.config.apply_dynamic_fields(level, context);
+        let context = self.config.limit_fields(context);
+        #[cfg(feature = "opentelemetry")]
+        let context = crate::otel::attach_trace_ids(context);
+        self.config.attach_backtrace(level, context)
+    }
+
+    pub async fn format_only(&self, level: LogLevel, message: &str) -> String {
+        self.format_only_with_context(level, message, None).await
+    }
 
-        self.buffer_log(formatted).await?;
-        self.try_flush().await?;
+    pub async fn format_only_with_context(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> String {
+        self.render_record(level, message, extra_context).await.line
+    }
 
-        Ok(())
+    // Unformatted record after the processors; None when one of them drops it
+    async fn processed_record(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> Option<Record> {
+        let context = self.build_context(level, extra_context).await;
+        let mut record = Record::new(level, message, context, String::new());
+        self.config.process(&mut record).then_some(record)
+    }
+
+    async fn render_record(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> Record {
+        let context = self.build_context(level, extra_context).await;
+        let line = self.formatter.render(level, message, &context);
+        Record::new(level, message, context, line)
+    }
+
+    pub fn set_colors(&self, enabled: bool) {
+        self.formatter.toggles().set_colors(enabled);
+    }
+
+    pub fn set_show_timestamps(&self, enabled: bool) {
+        self.formatter.toggles().set_timestamps(enabled);
+    }
+
+    pub fn set_show_date(&self, enabled: bool) {
+        self.formatter.toggles().set_show_date(enabled);
+    }
+
+    pub fn set_show_target(&self, enabled: bool) {
+        self.formatter.toggles().set_show_target(enabled);
+    }
+
+    pub fn set_show_module(&self, enabled: bool) {
+        self.formatter.toggles().set_show_module(enabled);
+    }
+
+    pub fn set_show_file_line(&self, enabled: bool) {
+        self.formatter.toggles().set_show_file_line(enabled);
+    }
+
+    pub fn formatter(&self) -> &Formatter {
+        &self.formatter
+    }
+
+    async fn emit(&self, record: Record, primary: bool) -> Result<()> {
+        let (fresh, repeated) = self.dedup.check(&record, primary);
+        if let Some(repeated) = repeated {
+            let (summary, summary_primary) = self.repeated_record(repeated).await;
+            self.buffer_log(summary, summary_primary).await?;
+        }
+        if !fresh {
+            return Ok(());
+        }
+
+        match self.rate_limiter.check(&record) {
+            RateDecision::Allow => {}
+            RateDecision::Resume { key, suppressed } => {
+                self.write_notice(&suppressed_notice(&key, suppressed))
+                    .await;
+            }
+            RateDecision::Suppress => return Ok(()),
+        }
+
+        self.buffer_log(record, primary).await
+    }
+
+    async fn repeated_record(&self, repeated: Repeated) -> (Record, bool) {
+        let record = self
+            .render_record(repeated.level, &repeated.message(), None)
+            .await;
+        (record, repeated.primary)
+    }
+
+    // Every record is charged against the budget, notices included, as in
+    // `LogFlow::deliver`; the exhaustion notice goes out ahead of the record that spent it
+    async fn buffer_log(&self, record: Record, primary: bool) -> Result<()> {
+        if self.budget.charge(record.line.len() + 1) {
+            if let Some(notice) = self.budget_notice().await {
+                self.budget.charge(notice.line.len() + 1);
+                self.dispatch(notice, true).await?;
+            }
+        }
+        self.dispatch(record, primary).await
     }
 
-    async fn buffer_log(&self, formatted: String) -> Result<()> {
+    async fn budget_notice(&self) -> Option<Record> {
+        let budget = self.budget.budget()?;
+        let message = format!(
+            "Log budget exhausted, only {} and above until the {:?} period ends",
+            budget.degraded_level, budget.period
+        );
+        Some(self.render_record(LogLevel::Info, &message, None).await)
+    }
+
+    async fn dispatch(&self, record: Record, primary: bool) -> Result<()> {
+        self.hooks.notify(&record);
+        if primary {
+            notify::dispatch(&self.config.notifications, record.level, &record.message);
+        }
+        self.push_record(record, primary).await
+    }
+
+    async fn push_record(&self, record: Record, primary: bool) -> Result<()> {
+        if self.direct.load(Ordering::SeqCst) {
+            let mut output = self.output.lock().await;
+            output.write_record_to(&record, primary)?;
+            return Ok(());
+        }
+
         let mut buffer = self.buffer.lock().await;
-        buffer.push(formatted);
+        buffer.push(record, primary);
 
         if buffer.len() >= self.buffer_size {
             drop(buffer);
-            self.flush().await?;
+            self.write_pending().await?;
         }
 
         Ok(())
@@ -105,32 +511,81 @@ impl AsyncLogFlow {
         let last_flush = self.last_flush.lock().await;
         if last_flush.elapsed() >= self.flush_interval {
             drop(last_flush);
-            self.flush().await?;
+            self.write_pending().await?;
         }
         Ok(())
     }
 
+    /// Writes out pending records and clears the status line.
     pub async fn flush(&self) -> Result<()> {
+        self.write_pending().await?;
+        self.output.lock().await.clear_status()?;
+        Ok(())
+    }
+
+    // See `LogFlow::set_status`
+    pub async fn set_status(&self, status: &str) -> Result<()> {
+        self.output.lock().await.set_status(status)?;
+        Ok(())
+    }
+
+    pub async fn clear_status(&self) -> Result<()> {
+        self.output.lock().await.clear_status()?;
+        Ok(())
+    }
+
+    async fn write_pending(&self) -> Result<()> {
+        let repeated = match self.dedup.take_pending() {
+            Some(repeated) => Some(self.repeated_record(repeated).await),
+            None => None,
+        };
+
         let mut buffer = self.buffer.lock().await;
+        if let Some((record, primary)) = repeated {
+            self.hooks.notify(&record);
+            buffer.push(record, primary);
+        }
         if buffer.is_empty() {
             return Ok(());
         }
 
-        let messages = buffer.drain(..).collect::<Vec<_>>();
+        let pending = buffer.take();
         drop(buffer);
 
-        let mut output = self.output.lock().await;
-        for message in messages {
-            output.write_line(&message)?;
-        }
-        output.flush()?;
-
-        let mut last_flush = self.last_flush.lock().await;
-        *last_flush = Instant::now();
+        // The output lock is released before `last_flush` is taken, so the two are
+        // never held together
+        pending.write_to(&mut *self.output.lock().await)?;
+        *self.last_flush.lock().await = Instant::now();
 
         Ok(())
     }
 
+    pub async fn critical_section<F, Fut, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = R>,
+    {
+        self.write_pending().await?;
+        let previous = self.direct.swap(true, Ordering::SeqCst);
+
+        let result = f().await;
+
+        self.direct.store(previous, Ordering::SeqCst);
+        self.write_pending().await?;
+        Ok(result)
+    }
+
+    /// See [`LogFlow::check_outputs`](crate::LogFlow::check_outputs).
+    pub async fn check_outputs(&self) -> Vec<(OutputId, Result<()>)> {
+        self.output
+            .lock()
+            .await
+            .health_checks()
+            .into_iter()
+            .map(|(id, result)| (id, result.map_err(LogFlowError::from)))
+            .collect()
+    }
+
     pub async fn trace(&self, message: &str) -> Result<()> {
         self.log(LogLevel::Trace, message).await
     }
@@ -155,45 +610,329 @@ impl AsyncLogFlow {
         self.log(LogLevel::Fatal, message).await
     }
 
-    pub async fn begin_scope(&self, name: &str) -> AsyncLogScope {
-        let target = format!("{}::{}", std::module_path!(), name);
+    pub async fn log_with_subtitle(
+        &self,
+        level: LogLevel,
+        subtitle: &str,
+        message: &str,
+    ) -> Result<()> {
         let context = {
+            let target = std::module_path!().to_string();
             let stack = self.context_stack.read().await;
-            if let Some(current) = stack.last() {
-                current.child(target)
+            if let Some(current_ctx) = stack.last() {
+                self.config.child_context(current_ctx, target)
             } else {
-                LogContext::new(target)
+                self.config.new_context(target)
             }
         };
 
+        self.log_with_context(level, message, Some(context.with_subtitle(subtitle)))
+            .await
+    }
+
+    /// Logs `message` at Fatal, drains the buffer and every output, and exits the
+    /// process with `code` once that is done.
+    pub async fn fatal_exit(&self, code: i32, message: &str) -> ! {
+        let _ = self.fatal(message).await;
+        let _ = self.flush().await;
+        let _ = self.output.lock().await.close();
+        std::process::exit(code)
+    }
+
+    pub async fn trace_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Trace, subtitle, message)
+            .await
+    }
+
+    pub async fn debug_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Debug, subtitle, message)
+            .await
+    }
+
+    pub async fn info_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Info, subtitle, message)
+            .await
+    }
+
+    pub async fn warn_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Warn, subtitle, message)
+            .await
+    }
+
+    pub async fn error_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Error, subtitle, message)
+            .await
+    }
+
+    pub async fn fatal_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.log_with_subtitle(LogLevel::Fatal, subtitle, message)
+            .await
+    }
+
+    pub async fn list<I>(&self, header: &str, items: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: std::fmt::Display,
+    {
+        self.list_at(LogLevel::Info, header, items).await
+    }
+
+    pub async fn list_at<I>(&self, level: LogLevel, header: &str, items: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: std::fmt::Display,
+    {
+        self.log_rendered(level, header, None, |formatter, record| {
+            let items: Vec<String> = items.into_iter().map(|item| item.to_string()).collect();
+            formatter.format_list(record.level, &record.message, &items, &record.context)
+        })
+        .await
+    }
+
+    pub async fn diff(&self, header: &str, old: &str, new: &str) -> Result<()> {
+        self.diff_at(LogLevel::Info, header, old, new).await
+    }
+
+    pub async fn diff_at(&self, level: LogLevel, header: &str, old: &str, new: &str) -> Result<()> {
+        self.log_rendered(level, header, None, |formatter, record| {
+            formatter.format_diff(record.level, &record.message, old, new, &record.context)
+        })
+        .await
+    }
+
+    pub async fn banner(&self, message: &str) -> Result<()> {
+        self.banner_at(LogLevel::Info, message).await
+    }
+
+    pub async fn banner_at(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.log_rendered(level, message, None, |formatter, record| {
+            formatter.format_banner(record.level, &record.message, &record.context)
+        })
+        .await
+    }
+
+    pub async fn panel(&self, title: &str, body: &str) -> Result<()> {
+        self.panel_at(LogLevel::Info, title, body).await
+    }
+
+    pub async fn panel_at(&self, level: LogLevel, title: &str, body: &str) -> Result<()> {
+        self.log_rendered(level, title, None, |formatter, record| {
+            formatter.format_panel(record.level, &record.message, body, &record.context)
+        })
+        .await
+    }
+
+    pub async fn begin_scope(&self, name: &str) -> AsyncLogScope<'_> {
+        self.push_scope(name, None, None).await
+    }
+
+    pub async fn begin_scope_with_level(&self, name: &str, level: LogLevel) -> AsyncLogScope<'_> {
+        self.push_scope(name, Some(level), None).await
+    }
+
+    pub async fn begin_request_scope(&self) -> AsyncLogScope<'_> {
+        let id = self.config.id_gen.next_id();
+        self.begin_request_scope_with_id(&id).await
+    }
+
+    pub async fn begin_request_scope_with_id(&self, id: &str) -> AsyncLogScope<'_> {
+        self.push_scope("request", None, Some(id)).await
+    }
+
+    async fn push_scope(
+        &self,
+        name: &str,
+        min_level: Option<LogLevel>,
+        correlation_id: Option<&str>,
+    ) -> AsyncLogScope<'_> {
+        let context = {
+            let stack = self.context_stack.read().await;
+            self.scope_context(&stack, name, min_level, correlation_id)
+        };
+
         {
             let mut stack = self.context_stack.write().await;
             stack.push(context.clone());
         }
+        let start = self.formatter.format_scope_start(name, &context);
+        self.write_scope_marker(start, name, &context).await;
+
+        self.scope(name, context)
+    }
+
+    fn scope_context(
+        &self,
+        stack: &[LogContext],
+        name: &str,
+        min_level: Option<LogLevel>,
+        correlation_id: Option<&str>,
+    ) -> LogContext {
+        let target = format!("{}::{}", std::module_path!(), name);
+        let mut context = if let Some(current) = stack.last() {
+            self.config.child_context(current, target)
+        } else {
+            self.config.new_context(target)
+        }
+        .with_scope(name);
 
+        if let Some(level) = min_level {
+            context = context.with_min_level(level);
+        }
+        if let Some(id) = correlation_id {
+            context = context.with_correlation_id(id);
+        }
+        context
+    }
+
+    fn scope(&self, name: &str, context: LogContext) -> AsyncLogScope<'_> {
         AsyncLogScope {
             logger: self,
             context,
             name: name.to_string(),
+            started: Instant::now(),
         }
     }
 
     pub async fn end_scope(&self) {
         let mut stack = self.context_stack.write().await;
-        stack.pop();
+        let Some(context) = stack.pop() else {
+            return;
+        };
+        drop(stack);
+        if let Some(name) = context.scope.as_deref() {
+            let end = self.formatter.format_scope_end(name, &context);
+            self.write_scope_marker(end, name, &context).await;
+        }
     }
 
-    pub async fn with_field<T>(&self, key: &str, value: T) -> AsyncFieldLogger
-    where
-        T: serde::Serialize,
+    // Group, section or block markers of the CI formats, written past filters and limits
+    // so every opened scope gets closed
+    async fn write_scope_marker(&self, marker: Option<String>, name: &str, context: &LogContext) {
+        if let Some(line) = marker {
+            let record = Record::new(LogLevel::Info, name, context.clone(), line);
+            let _ = self.push_record(record, true).await;
+        }
+    }
+
+    /// Another handle to this logger, sharing its outputs, buffer, scopes, level,
+    /// display toggles and limits; see [`LogFlow::child`](crate::LogFlow::child).
+    pub fn child(&self) -> AsyncLogFlow {
+        Self {
+            config: self.config.clone(),
+            formatter: self.formatter.with_shared_toggles(self.config.clone()),
+            output: Arc::clone(&self.output),
+            context_stack: Arc::clone(&self.context_stack),
+            buffer: Arc::clone(&self.buffer),
+            buffer_size: self.buffer_size,
+            flush_interval: self.flush_interval,
+            last_flush: Arc::clone(&self.last_flush),
+            level: Arc::clone(&self.level),
+            boost: Arc::clone(&self.boost),
+            budget: Arc::clone(&self.budget),
+            rate_limiter: Arc::clone(&self.rate_limiter),
+            dedup: Arc::clone(&self.dedup),
+            hooks: Arc::clone(&self.hooks),
+            direct: Arc::clone(&self.direct),
+        }
+    }
+
+    // Written records only, so pending ones are flushed first; see `LogFlow::recent`
+    pub async fn recent(&self, query: &RecordQuery) -> Vec<Record> {
+        let _ = self.write_pending().await;
+        self.output.lock().await.recent(query)
+    }
+
+    // See `LogFlow::on_record`
+    pub fn on_record<F>(&self, hook: F)
+    where
+        F: Fn(&Record) + Send + Sync + 'static,
+    {
+        self.hooks.add(Box::new(hook));
+    }
+
+    pub fn bind_field<T>(mut self, key: &str, value: T) -> Self
+    where
+        T: serde::Serialize,
+    {
+        self.config = self.config.with_global_field(key, value);
+        self
+    }
+
+    pub fn try_bind_field<T>(mut self, key: &str, value: T) -> Result<Self>
+    where
+        T: serde::Serialize,
+    {
+        self.config = self.config.try_with_global_field(key, value)?;
+        Ok(self)
+    }
+
+    pub async fn with_field<T>(&self, key: &str, value: T) -> AsyncFieldLogger<'_>
+    where
+        T: serde::Serialize,
+    {
+        let context = {
+            let stack = self.context_stack.read().await;
+            let context = stack
+                .last()
+                .cloned()
+                .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+            context.with_field_or(key, value, self.config.serialization_placeholder)
+        };
+
+        AsyncFieldLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    /// Like [`Self::with_field`], but fails if `value` does not serialize.
+    pub async fn try_with_field<T>(&self, key: &str, value: T) -> Result<AsyncFieldLogger<'_>>
+    where
+        T: serde::Serialize,
+    {
+        let context = {
+            let stack = self.context_stack.read().await;
+            stack
+                .last()
+                .cloned()
+                .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()))
+        };
+
+        Ok(AsyncFieldLogger {
+            logger: self,
+            context: context.try_with_field(key, value)?,
+        })
+    }
+
+    // One-off emphasis for a record, without changing its level
+    pub async fn styled(&self, style: Style) -> AsyncFieldLogger<'_> {
+        let context = {
+            let stack = self.context_stack.read().await;
+            let context = stack
+                .last()
+                .cloned()
+                .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+            context.with_style(style)
+        };
+
+        AsyncFieldLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    // Records carry `timestamp` as their event time instead of the time they were logged
+    pub async fn with_timestamp<T>(&self, timestamp: T) -> AsyncFieldLogger<'_>
+    where
+        T: Into<DateTime<Utc>>,
     {
         let context = {
             let stack = self.context_stack.read().await;
             let context = stack
                 .last()
                 .cloned()
-                .unwrap_or_else(|| LogContext::new(std::module_path!().to_string()));
-            context.with_field(key, value)
+                .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+            context.with_timestamp(timestamp)
         };
 
         AsyncFieldLogger {
@@ -207,99 +946,386 @@ impl AsyncLogFlow {
         stack.len()
     }
 
-    pub fn start_background_flush(&self) -> tokio::task::JoinHandle<()> {
-        let buffer = Arc::clone(&self.buffer);
-        let output = Arc::clone(&self.output);
-        let last_flush = Arc::clone(&self.last_flush);
-        let flush_interval = self.flush_interval;
+    pub fn start_background_flush(&self) -> tokio::task::JoinHandle<()> {
+        let buffer = Arc::clone(&self.buffer);
+        let output = Arc::clone(&self.output);
+        let last_flush = Arc::clone(&self.last_flush);
+        let flush_interval = self.flush_interval;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+                interval.tick().await;
+
+                let should_flush = {
+                    let last_flush = last_flush.lock().await;
+                    last_flush.elapsed() >= flush_interval
+                };
+
+                if should_flush {
+                    let mut buffer = buffer.lock().await;
+                    if !buffer.is_empty() {
+                        let pending = buffer.take();
+                        drop(buffer);
+
+                        let _ = pending.write_to(&mut *output.lock().await);
+                        *last_flush.lock().await = Instant::now();
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl Default for AsyncLogFlow {
+    fn default() -> Self {
+        block_on(AsyncLogFlow::with_config(LogConfig::default())).unwrap()
+    }
+}
+
+#[cfg(feature = "async")]
+pub struct AsyncLogFlowBuilder {
+    config: LogConfig,
+    buffer_size: usize,
+    flush_interval: Duration,
+}
+
+#[cfg(feature = "async")]
+impl AsyncLogFlowBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: LogConfig::default(),
+            buffer_size: 100,
+            flush_interval: Duration::from_millis(100),
+        }
+    }
+
+    pub fn with_level(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_level(level);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: crate::filter::EnvFilter) -> Self {
+        self.config = self.config.with_filter(filter);
+        self
+    }
+
+    pub fn with_sampling(mut self, level: LogLevel, rate: f64) -> Self {
+        self.config = self.config.with_sampling(level, rate);
+        self
+    }
+
+    pub fn with_env_filter(mut self, var: &str) -> Self {
+        self.config = self.config.with_env_filter(var);
+        self
+    }
+
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_colors(enabled);
+        self
+    }
+
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.config = self.config.with_color_mode(mode);
+        self
+    }
+
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_timestamps(enabled);
+        self
+    }
+
+    pub fn with_date(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_date(enabled);
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.config = self.config.with_timezone(timezone);
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.config = self.config.with_timestamp_format(format);
+        self
+    }
+
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.config = self.config.with_field_order(order);
+        self
+    }
+
+    pub fn with_json_fields(mut self, json_fields: JsonFields) -> Self {
+        self.config = self.config.with_json_fields(json_fields);
+        self
+    }
+
+    pub fn with_json_key(mut self, key: &str, name: &str) -> Self {
+        self.config = self.config.with_json_key(key, name);
+        self
+    }
+
+    pub fn with_serialization_placeholder(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_serialization_placeholder(enabled);
+        self
+    }
+
+    pub fn with_max_field_len(mut self, len: usize) -> Self {
+        self.config = self.config.with_max_field_len(len);
+        self
+    }
+
+    pub fn with_max_fields_len(mut self, len: usize) -> Self {
+        self.config = self.config.with_max_fields_len(len);
+        self
+    }
+
+    pub fn with_output(mut self, output: OutputType) -> Self {
+        self.config = self.config.with_output(output);
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: FormatterType) -> Self {
+        self.config = self.config.with_formatter(formatter);
+        self
+    }
+
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    pub fn with_output_at_level(mut self, output: OutputType, level: LogLevel) -> Self {
+        self.config = self.config.with_output_at_level(output, level);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.config = self.config.with_theme(theme);
+        self
+    }
+
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.config = self.config.with_color_depth(depth);
+        self
+    }
+
+    pub fn with_unicode(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_unicode(enabled);
+        self
+    }
+
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_hyperlinks(enabled);
+        self
+    }
+
+    pub fn with_link_format(mut self, format: &str) -> Self {
+        self.config = self.config.with_link_format(format);
+        self
+    }
+
+    pub fn with_ci_groups(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_ci_groups(enabled);
+        self
+    }
+
+    pub fn with_max_width(mut self, width: usize) -> Self {
+        self.config = self.config.with_max_width(width);
+        self
+    }
+
+    pub fn with_terminal_width(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_terminal_width(enabled);
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.config = self.config.with_overflow(overflow);
+        self
+    }
+
+    pub fn with_continuation_marker(mut self, marker: impl Into<String>) -> Self {
+        self.config = self.config.with_continuation_marker(marker);
+        self
+    }
+
+    pub fn with_field_lines(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_field_lines(enabled);
+        self
+    }
+
+    pub fn with_aligned_columns(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_aligned_columns(enabled);
+        self
+    }
+
+    pub fn with_target_width(mut self, width: usize) -> Self {
+        self.config = self.config.with_target_width(width);
+        self
+    }
+
+    pub fn with_indent_size(mut self, size: usize) -> Self {
+        self.config = self.config.with_indent_size(size);
+        self
+    }
+
+    pub fn with_indent_style(mut self, style: IndentStyle) -> Self {
+        self.config = self.config.with_indent_style(style);
+        self
+    }
+
+    pub fn with_ascii_indent(mut self) -> Self {
+        self.config = self.config.with_ascii_indent();
+        self
+    }
+
+    pub fn with_scope_names(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_scope_names(enabled);
+        self
+    }
+
+    pub fn with_output_check(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_output_check(enabled);
+        self
+    }
+
+    pub fn with_field_renderer(mut self, key: &str, renderer: FieldRenderer) -> Self {
+        self.config = self.config.with_field_renderer(key, renderer);
+        self
+    }
+
+    pub fn with_syslog_facility(mut self, facility: Facility) -> Self {
+        self.config = self.config.with_syslog_facility(facility);
+        self
+    }
+
+    pub fn with_app_name(mut self, name: &str) -> Self {
+        self.config = self.config.with_app_name(name);
+        self
+    }
+
+    pub fn with_broken_pipe_policy(mut self, policy: BrokenPipePolicy) -> Self {
+        self.config = self.config.with_broken_pipe_policy(policy);
+        self
+    }
+
+    pub fn with_spool<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_spool(path);
+        self
+    }
+
+    pub fn with_compression(mut self, compression: crate::compression::Compression) -> Self {
+        self.config = self.config.with_compression(compression);
+        self
+    }
 
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(flush_interval);
-            loop {
-                interval.tick().await;
+    pub fn with_idle_flush(mut self, idle_flush: crate::output::IdleFlush) -> Self {
+        self.config = self.config.with_idle_flush(idle_flush);
+        self
+    }
 
-                let should_flush = {
-                    let last_flush = last_flush.lock().await;
-                    last_flush.elapsed() >= flush_interval
-                };
+    pub fn with_file_buffer(mut self, file_buffer: crate::output::FileBuffer) -> Self {
+        self.config = self.config.with_file_buffer(file_buffer);
+        self
+    }
 
-                if should_flush {
-                    let mut buffer = buffer.lock().await;
-                    if !buffer.is_empty() {
-                        let messages = buffer.drain(..).collect::<Vec<_>>();
-                        drop(buffer);
+    pub fn with_min_free_space(mut self, bytes: u64) -> Self {
+        self.config = self.config.with_min_free_space(bytes);
+        self
+    }
 
-                        let mut output = output.lock().await;
-                        for message in messages {
-                            let _ = output.write_line(&message);
-                        }
-                        let _ = output.flush();
+    pub fn with_budget(mut self, budget: crate::budget::LogBudget) -> Self {
+        self.config = self.config.with_budget(budget);
+        self
+    }
 
-                        let mut last_flush = last_flush.lock().await;
-                        *last_flush = Instant::now();
-                    }
-                }
-            }
-        })
+    pub fn with_rate_limit(mut self, limit: crate::ratelimit::RateLimit) -> Self {
+        self.config = self.config.with_rate_limit(limit);
+        self
     }
-}
 
-#[cfg(feature = "async")]
-impl Default for AsyncLogFlow {
-    fn default() -> Self {
-        futures::executor::block_on(AsyncLogFlow::with_config(LogConfig::default())).unwrap()
+    pub fn with_dedup(mut self, window: Duration) -> Self {
+        self.config = self.config.with_dedup(window);
+        self
     }
-}
 
-#[cfg(feature = "async")]
-pub struct AsyncLogFlowBuilder {
-    config: LogConfig,
-    buffer_size: usize,
-    flush_interval: Duration,
-}
+    pub fn with_backtraces(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_backtraces(level);
+        self
+    }
 
-#[cfg(feature = "async")]
-impl AsyncLogFlowBuilder {
-    pub fn new() -> Self {
-        Self {
-            config: LogConfig::default(),
-            buffer_size: 100,
-            flush_interval: Duration::from_millis(100),
-        }
+    pub fn with_scope_timing(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_scope_timing(level);
+        self
     }
 
-    pub fn with_level(mut self, level: LogLevel) -> Self {
-        self.config = self.config.with_level(level);
+    pub fn with_id_gen<G: crate::context::IdGen + 'static>(mut self, id_gen: G) -> Self {
+        self.config = self.config.with_id_gen(id_gen);
         self
     }
 
-    pub fn with_colors(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_colors(enabled);
+    pub fn with_config_dump(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_config_dump(enabled);
         self
     }
 
-    pub fn with_timestamps(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_timestamps(enabled);
+    pub fn with_processor<P: crate::pipeline::Processor + 'static>(mut self, processor: P) -> Self {
+        self.config = self.config.with_processor(processor);
         self
     }
 
-    pub fn with_date(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_date(enabled);
+    pub fn with_notification(mut self, notification: Notification) -> Self {
+        self.config = self.config.with_notification(notification);
         self
     }
 
-    pub fn with_output(mut self, output: OutputType) -> Self {
-        self.config = self.config.with_output(output);
+    pub fn with_bell(self, min_level: LogLevel) -> Self {
+        self.with_notification(Notification::bell(min_level))
+    }
+
+    pub fn with_global_field<T: serde::Serialize>(mut self, key: &str, value: T) -> Self {
+        self.config = self.config.with_global_field(key, value);
         self
     }
 
-    pub fn with_buffer_size(mut self, size: usize) -> Self {
-        self.buffer_size = size;
+    pub fn try_with_global_field<T: serde::Serialize>(
+        mut self,
+        key: &str,
+        value: T,
+    ) -> Result<Self> {
+        self.config = self.config.try_with_global_field(key, value)?;
+        Ok(self)
+    }
+
+    pub fn with_dynamic_field<F, T>(mut self, key: &str, provider: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: serde::Serialize,
+    {
+        self.config = self.config.with_dynamic_field(key, provider);
         self
     }
 
-    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
-        self.flush_interval = interval;
+    pub fn with_dynamic_field_at_level<F, T>(
+        mut self,
+        key: &str,
+        min_level: LogLevel,
+        provider: F,
+    ) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: serde::Serialize,
+    {
+        self.config = self
+            .config
+            .with_dynamic_field_at_level(key, min_level, provider);
         self
     }
 
@@ -318,6 +1344,21 @@ impl AsyncLogFlowBuilder {
         self
     }
 
+    pub fn logfmt(mut self) -> Self {
+        self.config = LogConfig::logfmt();
+        self
+    }
+
+    pub fn gelf(mut self) -> Self {
+        self.config = LogConfig::gelf();
+        self
+    }
+
+    pub fn ecs(mut self) -> Self {
+        self.config = LogConfig::ecs();
+        self
+    }
+
     pub fn dev(mut self) -> Self {
         self.config = LogConfig::dev();
         self
@@ -343,6 +1384,7 @@ pub struct AsyncLogScope<'a> {
     logger: &'a AsyncLogFlow,
     context: LogContext,
     name: String,
+    started: Instant,
 }
 
 #[cfg(feature = "async")]
@@ -383,24 +1425,157 @@ impl<'a> AsyncLogScope<'a> {
             .await
     }
 
-    pub async fn begin_scope(&self, name: &str) -> AsyncLogScope {
+    pub async fn trace_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Trace,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn debug_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Debug,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn info_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Info,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn warn_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Warn,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn error_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Error,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn fatal_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(
+                LogLevel::Fatal,
+                message,
+                Some(self.context.clone().with_subtitle(subtitle)),
+            )
+            .await
+    }
+
+    pub async fn begin_scope(&self, name: &str) -> AsyncLogScope<'_> {
         self.logger.begin_scope(name).await
     }
 
+    pub async fn begin_scope_with_level(&self, name: &str, level: LogLevel) -> AsyncLogScope<'_> {
+        self.logger.begin_scope_with_level(name, level).await
+    }
+
+    pub fn min_level(&self) -> Option<LogLevel> {
+        self.context.min_level
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn with_field<T>(&self, key: &str, value: T) -> AsyncFieldLogger
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.context.correlation_id()
+    }
+
+    pub fn with_field<T>(&self, key: &str, value: T) -> AsyncFieldLogger<'_>
+    where
+        T: serde::Serialize,
+    {
+        let placeholder = self.logger.config.serialization_placeholder;
+        let context = self.context.clone().with_field_or(key, value, placeholder);
+        AsyncFieldLogger {
+            logger: self.logger,
+            context,
+        }
+    }
+
+    pub fn try_with_field<T>(&self, key: &str, value: T) -> Result<AsyncFieldLogger<'_>>
     where
         T: serde::Serialize,
     {
-        let context = self.context.clone().with_field(key, value);
+        Ok(AsyncFieldLogger {
+            logger: self.logger,
+            context: self.context.clone().try_with_field(key, value)?,
+        })
+    }
+
+    pub fn with_value(&self, key: &str, value: impl Into<FieldValue>) -> AsyncFieldLogger<'_> {
+        AsyncFieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_value(key, value),
+        }
+    }
+
+    typed_field_methods!(borrowing AsyncFieldLogger<'_>);
+
+    pub fn with_timestamp<T>(&self, timestamp: T) -> AsyncFieldLogger<'_>
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        let context = self.context.clone().with_timestamp(timestamp);
         AsyncFieldLogger {
             logger: self.logger,
             context,
         }
     }
+
+    pub fn styled(&self, style: Style) -> AsyncFieldLogger<'_> {
+        AsyncFieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_style(style),
+        }
+    }
+
+    // Sets a field on every record logged through this scope from now on
+    pub(crate) fn set_field(&mut self, key: &str, value: FieldValue) {
+        self.context.fields.insert(key.to_string(), value);
+    }
+
+    // Drop can't log here, so timed async scopes are closed explicitly
+    pub async fn finish(self) -> Result<()> {
+        let level = self.logger.config.scope_timing.unwrap_or(LogLevel::Debug);
+        let context = self
+            .context
+            .clone()
+            .with_timestamp(Utc::now())
+            .with_field("scope", &self.name)
+            .with_field("duration_ms", self.started.elapsed().as_millis() as u64)
+            .closing_scope();
+        let result = self
+            .logger
+            .log_with_context(level, &format!("{} finished", self.name), Some(context))
+            .await;
+        self.logger.end_scope().await;
+        result
+    }
 }
 
 #[cfg(feature = "async")]
@@ -423,7 +1598,41 @@ impl<'a> AsyncFieldLogger<'a> {
     where
         T: serde::Serialize,
     {
-        self.context = self.context.with_field(key, value);
+        let placeholder = self.logger.config.serialization_placeholder;
+        self.context = self.context.with_field_or(key, value, placeholder);
+        self
+    }
+
+    pub fn try_with_field<T>(mut self, key: &str, value: T) -> Result<Self>
+    where
+        T: serde::Serialize,
+    {
+        self.context = self.context.try_with_field(key, value)?;
+        Ok(self)
+    }
+
+    pub fn with_value(mut self, key: &str, value: impl Into<FieldValue>) -> Self {
+        self.context = self.context.with_value(key, value);
+        self
+    }
+
+    typed_field_methods!(consuming);
+
+    pub fn with_subtitle(mut self, subtitle: &str) -> Self {
+        self.context = self.context.with_subtitle(subtitle);
+        self
+    }
+
+    pub fn with_timestamp<T>(mut self, timestamp: T) -> Self
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        self.context = self.context.with_timestamp(timestamp);
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.context = self.context.with_style(style);
         self
     }
 
@@ -463,3 +1672,196 @@ impl<'a> AsyncFieldLogger<'a> {
             .await
     }
 }
+
+// Runs `future` on the calling thread; on a multi-threaded tokio runtime the worker
+// hands its other tasks off first
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| futures::executor::block_on(future))
+        }
+        _ => futures::executor::block_on(future),
+    }
+}
+
+// A current-thread runtime cannot be blocked on: a task holding one of the logger's
+// locks only gets to release it once the caller yields
+#[cfg(feature = "async")]
+fn current_thread_runtime() -> Option<tokio::runtime::Handle> {
+    tokio::runtime::Handle::try_current()
+        .ok()
+        .filter(|handle| handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::CurrentThread)
+}
+
+// Blocking shim. On a current-thread runtime writes are queued as tasks on their own
+// handle to the logger, and run in order once the caller yields
+#[cfg(feature = "async")]
+impl AsyncLogFlow {
+    fn log_blocking(
+        &self,
+        level: LogLevel,
+        message: &str,
+        context: Option<LogContext>,
+    ) -> Result<()> {
+        let Some(runtime) = current_thread_runtime() else {
+            return block_on(self.log_with_context(level, message, context));
+        };
+        // The scope is resolved now, it may have ended by the time the task runs
+        let context = context.or_else(|| self.stack_in_place().last().cloned());
+        let logger = self.child();
+        let message = message.to_string();
+        runtime.spawn(async move {
+            let _ = logger.log_with_context(level, &message, context).await;
+        });
+        Ok(())
+    }
+
+    fn with_field_blocking(&self, key: &str, value: serde_json::Value) -> AsyncFieldLogger<'_> {
+        if current_thread_runtime().is_none() {
+            return block_on(self.with_field(key, value));
+        }
+        let context = self
+            .stack_in_place()
+            .last()
+            .cloned()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+        AsyncFieldLogger {
+            logger: self,
+            context: context.with_field_or(key, value, self.config.serialization_placeholder),
+        }
+    }
+
+    fn begin_scope_blocking(&self, name: &str) -> AsyncLogScope<'_> {
+        let Some(runtime) = current_thread_runtime() else {
+            return block_on(self.begin_scope(name));
+        };
+        let context = {
+            let mut stack = self.stack_in_place();
+            let context = self.scope_context(&stack, name, None, None);
+            stack.push(context.clone());
+            context
+        };
+        let start = self.formatter.format_scope_start(name, &context);
+        self.spawn_scope_marker(&runtime, start, name, &context);
+        self.scope(name, context)
+    }
+
+    fn end_scope_blocking(&self) {
+        let Some(runtime) = current_thread_runtime() else {
+            return block_on(self.end_scope());
+        };
+        let Some(context) = self.stack_in_place().pop() else {
+            return;
+        };
+        if let Some(name) = context.scope.as_deref() {
+            let end = self.formatter.format_scope_end(name, &context);
+            self.spawn_scope_marker(&runtime, end, name, &context);
+        }
+    }
+
+    fn spawn_scope_marker(
+        &self,
+        runtime: &tokio::runtime::Handle,
+        marker: Option<String>,
+        name: &str,
+        context: &LogContext,
+    ) {
+        if marker.is_none() {
+            return;
+        }
+        let logger = self.child();
+        let name = name.to_string();
+        let context = context.clone();
+        runtime.spawn(async move {
+            logger.write_scope_marker(marker, &name, &context).await;
+        });
+    }
+
+    // The scope stack is never locked across an await, so this waits at most for
+    // another thread's push or pop
+    fn stack_in_place(&self) -> tokio::sync::RwLockWriteGuard<'_, Vec<LogContext>> {
+        loop {
+            if let Ok(stack) = self.context_stack.try_write() {
+                return stack;
+            }
+            std::thread::yield_now();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Log for AsyncLogFlow {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.log_blocking(level, message, None)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(self.with_field_blocking(key, value))
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(BlockingScope(self.begin_scope_blocking(name)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Log for AsyncLogScope<'_> {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.logger
+            .log_blocking(level, message, Some(self.context.clone()))
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(AsyncLogScope::with_field(self, key, value))
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(BlockingScope(self.logger.begin_scope_blocking(name)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl Log for AsyncFieldLogger<'_> {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.logger
+            .log_blocking(level, message, Some(self.context.clone()))
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(AsyncFieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_field(key, value),
+        })
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(BlockingScope(self.logger.begin_scope_blocking(name)))
+    }
+}
+
+// Async scopes are ended manually; behind the facade the scope ends when it is dropped
+#[cfg(feature = "async")]
+struct BlockingScope<'a>(AsyncLogScope<'a>);
+
+#[cfg(feature = "async")]
+impl Log for BlockingScope<'_> {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        Log::log(&self.0, level, message)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Log::with_field(&self.0, key, value)
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Log::begin_scope(&self.0, name)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for BlockingScope<'_> {
+    fn drop(&mut self) {
+        self.0.logger.end_scope_blocking();
+    }
+}
@@ -0,0 +1,40 @@
+use std::backtrace::Backtrace;
+
+// Frames of the current call stack as `function (file:line:col)`, from the caller of
+// the logger down to `main`, like a short panic backtrace
+pub(crate) fn capture() -> Vec<String> {
+    let rendered = Backtrace::force_capture().to_string();
+
+    let mut frames: Vec<String> = Vec::new();
+    for line in rendered.lines().map(str::trim) {
+        if line.contains("__rust_begin_short_backtrace") {
+            // The shim calling `main`; spawned threads call their closure directly
+            if frames
+                .last()
+                .is_some_and(|frame| frame.starts_with("core::ops::function::FnOnce::call_once"))
+            {
+                frames.pop();
+            }
+            break;
+        }
+        if let Some(location) = line.strip_prefix("at ") {
+            if let Some(frame) = frames.last_mut() {
+                frame.push_str(&format!(" ({})", location));
+            }
+        } else if let Some((index, function)) = line.split_once(": ") {
+            if index.chars().all(|c| c.is_ascii_digit()) {
+                frames.push(function.to_string());
+            }
+        }
+    }
+
+    let internal = frames
+        .iter()
+        .take_while(|frame| {
+            frame.starts_with("std::backtrace")
+                || frame.starts_with("logflow::")
+                || frame.starts_with("<logflow::")
+        })
+        .count();
+    frames.split_off(internal)
+}
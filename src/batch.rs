@@ -0,0 +1,272 @@
+use crate::diagnostics;
+use crate::logger::LogFlowError;
+use crate::output::{OutputWriter, Record};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Destination that accepts whole batches of records, e.g. an HTTP push API.
+pub trait BatchSink: Send + Sync + 'static {
+    fn send(&self, records: &[Record]) -> io::Result<()>;
+
+    // Sinks that can accept part of a batch return the indices of records to retry
+    fn send_partial(&self, records: &[Record]) -> io::Result<Vec<usize>> {
+        self.send(records).map(|()| Vec::new())
+    }
+
+    // Whether the destination is reachable, for `LogFlow::check_outputs`
+    fn health_check(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Which records go when the queue of a [`BatchWriter`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QueueFull {
+    #[default]
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    pub max_records: usize,
+    pub max_delay: Duration,
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_queued: usize,
+    pub queue_full: QueueFull,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            max_records: 500,
+            max_delay: Duration::from_secs(1),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            max_queued: 10_000,
+            queue_full: QueueFull::DropOldest,
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn with_max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records.max(1);
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    // Records waiting to be sent, e.g. while the destination is down; past that,
+    // `queue_full` decides which are dropped
+    pub fn with_max_queued(mut self, max_queued: usize, queue_full: QueueFull) -> Self {
+        self.max_queued = max_queued.max(1);
+        self.queue_full = queue_full;
+        self
+    }
+}
+
+struct Queue {
+    records: Mutex<VecDeque<Record>>,
+    ready: Condvar,
+    stop: AtomicBool,
+    // Dropped for a full queue since the last report
+    dropped: AtomicUsize,
+}
+
+/// Collects records and hands them to a [`BatchSink`] from a background thread.
+///
+/// A batch is sent once `max_records` are queued or `max_delay` has passed. Failed
+/// sends are retried with exponential backoff; a batch that still fails after
+/// `max_retries` is dropped and reported through [`diagnostics::report`]. At most
+/// `max_queued` records wait to be sent; records dropped beyond that are reported too.
+pub struct BatchWriter {
+    queue: Arc<Queue>,
+    sink: Arc<dyn BatchSink>,
+    options: BatchOptions,
+    sender: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl BatchWriter {
+    pub fn new<S: BatchSink>(sink: S, options: BatchOptions) -> io::Result<Self> {
+        let queue = Arc::new(Queue {
+            records: Mutex::new(VecDeque::new()),
+            ready: Condvar::new(),
+            stop: AtomicBool::new(false),
+            dropped: AtomicUsize::new(0),
+        });
+
+        let sink = Arc::new(sink);
+        let sender = {
+            let queue = Arc::clone(&queue);
+            let sink = Arc::clone(&sink);
+            std::thread::Builder::new()
+                .name("logflow-batch".to_string())
+                .spawn(move || run(&queue, &*sink, options))?
+        };
+
+        Ok(Self {
+            queue,
+            sink,
+            options,
+            sender: Mutex::new(Some(sender)),
+        })
+    }
+}
+
+impl OutputWriter for BatchWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        let mut records = self
+            .queue
+            .records
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire batch lock"))?;
+        if records.len() >= self.options.max_queued {
+            self.queue.dropped.fetch_add(1, Ordering::Relaxed);
+            match self.options.queue_full {
+                QueueFull::DropOldest => {
+                    records.pop_front();
+                }
+                QueueFull::DropNewest => return Ok(()),
+            }
+        }
+        records.push_back(record.clone());
+        if records.len() >= self.options.max_records {
+            self.queue.ready.notify_one();
+        }
+        Ok(())
+    }
+
+    // Sending happens in the background; flushing never blocks on the network
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn close(&self) -> io::Result<()> {
+        self.stop();
+        Ok(())
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.sink.health_check()
+    }
+}
+
+impl BatchWriter {
+    // Sends what is still queued, retries included, then ends the background thread
+    fn stop(&self) {
+        self.queue.stop.store(true, Ordering::SeqCst);
+        self.queue.ready.notify_one();
+        let sender = self.sender.lock().ok().and_then(|mut sender| sender.take());
+        if let Some(sender) = sender {
+            let _ = sender.join();
+        }
+    }
+}
+
+impl Drop for BatchWriter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run<S: BatchSink>(queue: &Queue, sink: &S, options: BatchOptions) {
+    loop {
+        let batch = {
+            let Ok(mut records) = queue.records.lock() else {
+                return;
+            };
+            let deadline = Instant::now() + options.max_delay;
+            while records.len() < options.max_records && !queue.stop.load(Ordering::SeqCst) {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                records = match queue.ready.wait_timeout(records, deadline - now) {
+                    Ok((records, _)) => records,
+                    Err(_) => return,
+                };
+            }
+            let count = records.len().min(options.max_records);
+            records.drain(..count).collect::<Vec<_>>()
+        };
+
+        if !batch.is_empty() {
+            send_with_retry(sink, &batch, &options);
+        }
+
+        let dropped = queue.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            diagnostics::report(&LogFlowError::Output(io::Error::other(format!(
+                "Batch queue full, dropped {} records",
+                dropped
+            ))));
+        }
+
+        if queue.stop.load(Ordering::SeqCst)
+            && queue
+                .records
+                .lock()
+                .map_or(true, |records| records.is_empty())
+        {
+            return;
+        }
+    }
+}
+
+fn send_with_retry<S: BatchSink>(sink: &S, batch: &[Record], options: &BatchOptions) {
+    let mut pending = batch.to_vec();
+    let mut backoff = options.initial_backoff;
+    let mut attempt = 0;
+    loop {
+        let err = match sink.send_partial(&pending) {
+            Ok(retry) if retry.is_empty() => return,
+            Ok(retry) => {
+                let count = retry.len();
+                pending = retry
+                    .into_iter()
+                    .filter_map(|index| pending.get(index).cloned())
+                    .collect();
+                io::Error::other(format!("{} records were not accepted", count))
+            }
+            Err(err) => err,
+        };
+
+        if attempt >= options.max_retries {
+            diagnostics::report(&LogFlowError::Output(err));
+            return;
+        }
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(options.max_backoff);
+        attempt += 1;
+    }
+}
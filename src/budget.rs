@@ -0,0 +1,158 @@
+use crate::level::LogLevel;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Cap on how much is logged per period, e.g. 10 MB per hour.
+///
+/// Once a period's budget is spent, records below `degraded_level` (Warn by default)
+/// are dropped until the next period starts; both transitions are logged as notices.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::budget::LogBudget;
+/// use std::time::Duration;
+///
+/// let logger = LogFlow::new()
+///     .with_budget(LogBudget::bytes(10 * 1024 * 1024, Duration::from_secs(3600)))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogBudget {
+    pub max_bytes: Option<u64>,
+    pub max_records: Option<u64>,
+    pub period: Duration,
+    pub degraded_level: LogLevel,
+}
+
+impl LogBudget {
+    pub fn bytes(max_bytes: u64, period: Duration) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            max_records: None,
+            period,
+            degraded_level: LogLevel::Warn,
+        }
+    }
+
+    pub fn records(max_records: u64, period: Duration) -> Self {
+        Self {
+            max_bytes: None,
+            max_records: Some(max_records),
+            period,
+            degraded_level: LogLevel::Warn,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_max_records(mut self, max_records: u64) -> Self {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    pub fn with_degraded_level(mut self, level: LogLevel) -> Self {
+        self.degraded_level = level;
+        self
+    }
+
+    fn exceeded(&self, bytes: u64, records: u64) -> bool {
+        self.max_bytes.is_some_and(|max| bytes >= max)
+            || self.max_records.is_some_and(|max| records >= max)
+    }
+}
+
+struct Usage {
+    started: Instant,
+    bytes: u64,
+    records: u64,
+    exhausted: bool,
+}
+
+pub enum BudgetState {
+    Open,
+    Degraded(LogLevel),
+    Renewed,
+}
+
+/// Tracks what the current period of a [`LogBudget`] has used.
+pub struct BudgetTracker {
+    budget: Option<LogBudget>,
+    usage: Mutex<Usage>,
+}
+
+impl BudgetTracker {
+    pub fn new(budget: Option<LogBudget>) -> Self {
+        Self {
+            budget,
+            usage: Mutex::new(Usage {
+                started: Instant::now(),
+                bytes: 0,
+                records: 0,
+                exhausted: false,
+            }),
+        }
+    }
+
+    // Starts a new period once the current one is over; `Renewed` is reported once
+    pub fn check(&self) -> BudgetState {
+        let Some(budget) = self.budget else {
+            return BudgetState::Open;
+        };
+        let Ok(mut usage) = self.usage.lock() else {
+            return BudgetState::Open;
+        };
+
+        if usage.started.elapsed() >= budget.period {
+            let was_exhausted = usage.exhausted;
+            *usage = Usage {
+                started: Instant::now(),
+                bytes: 0,
+                records: 0,
+                exhausted: false,
+            };
+            if was_exhausted {
+                return BudgetState::Renewed;
+            }
+        }
+
+        if usage.exhausted {
+            BudgetState::Degraded(budget.degraded_level)
+        } else {
+            BudgetState::Open
+        }
+    }
+
+    // Lowest level any output may take right now
+    pub fn floor(&self) -> LogLevel {
+        match (self.budget, self.usage.lock()) {
+            (Some(budget), Ok(usage)) if usage.exhausted => budget.degraded_level,
+            _ => LogLevel::Trace,
+        }
+    }
+
+    // Returns true for the write that spends the budget
+    pub fn charge(&self, bytes: usize) -> bool {
+        let Some(budget) = self.budget else {
+            return false;
+        };
+        let Ok(mut usage) = self.usage.lock() else {
+            return false;
+        };
+
+        usage.bytes += bytes as u64;
+        usage.records += 1;
+        if usage.exhausted || !budget.exceeded(usage.bytes, usage.records) {
+            return false;
+        }
+        usage.exhausted = true;
+        true
+    }
+
+    pub fn budget(&self) -> Option<&LogBudget> {
+        self.budget.as_ref()
+    }
+}
@@ -0,0 +1,222 @@
+use crate::output::OutputType;
+use owo_colors::{AnsiColors, DynColors, XtermColors};
+use std::sync::OnceLock;
+
+/// Whether the Pretty and Compact formatters write ANSI colors.
+///
+/// `Auto`, the default, colors output only when it goes to a terminal, so
+/// `myapp | tee log.txt` stays plain. `NO_COLOR` turns colors off and `CLICOLOR_FORCE`
+/// on regardless of the terminal; `NO_COLOR` wins when both are set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled_for(&self, output: &OutputType) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if env_flag("NO_COLOR") {
+                    false
+                } else if env_flag("CLICOLOR_FORCE") {
+                    true
+                } else {
+                    std::env::var("CLICOLOR").as_deref() != Ok("0") && output.is_terminal()
+                }
+            }
+        }
+    }
+
+    // `always`, `never` or `auto`, as in `RUST_LOG_STYLE` and `LOGFLOW_COLOR`
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<ColorMode> {
+        match s.to_lowercase().as_str() {
+            "auto" => Some(ColorMode::Auto),
+            "always" | "true" | "1" | "on" | "yes" => Some(ColorMode::Always),
+            "never" | "false" | "0" | "off" | "no" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the terminal can show Unicode symbols: no on the Linux console, otherwise
+/// whatever the locale (`LC_ALL`, `LC_CTYPE`, `LANG`) says, and yes when none is set.
+pub fn supports_unicode() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        if std::env::var("TERM").as_deref() == Ok("linux") {
+            return false;
+        }
+        ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .is_none_or(|locale| {
+                let locale = locale.to_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            })
+    })
+}
+
+/// Whether the terminal turns OSC 8 escape sequences into clickable links, judged from
+/// the variables known terminals set; `FORCE_HYPERLINK` overrides the guess.
+pub fn supports_hyperlinks() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        if let Some(forced) = std::env::var_os("FORCE_HYPERLINK") {
+            return !forced.is_empty() && forced != "0";
+        }
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let program = var("TERM_PROGRAM");
+        let term = var("TERM");
+        matches!(
+            program.as_str(),
+            "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty"
+        ) || ["xterm-kitty", "alacritty", "foot", "xterm-ghostty"].contains(&term.as_str())
+            || var("VTE_VERSION")
+                .parse::<u32>()
+                .is_ok_and(|vte| vte >= 5000)
+            || env_flag("WT_SESSION")
+            || env_flag("KONSOLE_VERSION")
+    })
+}
+
+// Set to anything but an empty string or "0"
+fn env_flag(var: &str) -> bool {
+    std::env::var_os(var).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Colors a terminal can show, from the 16 basic ANSI colors up to 24-bit RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Depth advertised by the terminal through `COLORTERM` and `TERM`, read once per
+    /// process; terminals that advertise nothing get the basic colors.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<ColorDepth> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            Self::from_vars(
+                std::env::var("COLORTERM").ok().as_deref(),
+                std::env::var("TERM").ok().as_deref(),
+            )
+        })
+    }
+
+    fn from_vars(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let colorterm = colorterm.unwrap_or_default().to_lowercase();
+        let term = term.unwrap_or_default().to_lowercase();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit")
+            || term.ends_with("-direct")
+            || term.contains("truecolor")
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Basic
+        }
+    }
+}
+
+/// An RGB or 256-color palette color, shown as is where the terminal supports it and
+/// as the closest color it can show otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Rgb(u8, u8, u8),
+    Ansi256(u8),
+}
+
+// xterm's defaults for the 16 basic colors
+const BASIC: [(AnsiColors, (u8, u8, u8)); 16] = [
+    (AnsiColors::Black, (0, 0, 0)),
+    (AnsiColors::Red, (205, 0, 0)),
+    (AnsiColors::Green, (0, 205, 0)),
+    (AnsiColors::Yellow, (205, 205, 0)),
+    (AnsiColors::Blue, (0, 0, 238)),
+    (AnsiColors::Magenta, (205, 0, 205)),
+    (AnsiColors::Cyan, (0, 205, 205)),
+    (AnsiColors::White, (229, 229, 229)),
+    (AnsiColors::BrightBlack, (127, 127, 127)),
+    (AnsiColors::BrightRed, (255, 0, 0)),
+    (AnsiColors::BrightGreen, (0, 255, 0)),
+    (AnsiColors::BrightYellow, (255, 255, 0)),
+    (AnsiColors::BrightBlue, (92, 92, 255)),
+    (AnsiColors::BrightMagenta, (255, 0, 255)),
+    (AnsiColors::BrightCyan, (0, 255, 255)),
+    (AnsiColors::BrightWhite, (255, 255, 255)),
+];
+
+// Channel levels of the 6x6x6 cube in the 256-color palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Color {
+    pub(crate) fn resolve(self, depth: ColorDepth) -> DynColors {
+        match (self, depth) {
+            (Color::Rgb(r, g, b), ColorDepth::TrueColor) => DynColors::Rgb(r, g, b),
+            (Color::Rgb(r, g, b), ColorDepth::Ansi256) => {
+                DynColors::Xterm(XtermColors::from(palette_index(r, g, b)))
+            }
+            (Color::Ansi256(index), ColorDepth::TrueColor | ColorDepth::Ansi256) => {
+                DynColors::Xterm(XtermColors::from(index))
+            }
+            (_, ColorDepth::Basic) => DynColors::Ansi(nearest_basic(self.rgb())),
+        }
+    }
+
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Ansi256(index @ 0..=15) => BASIC[index as usize].1,
+            Color::Ansi256(index @ 16..=231) => {
+                let index = index - 16;
+                (
+                    CUBE[(index / 36) as usize],
+                    CUBE[(index / 6 % 6) as usize],
+                    CUBE[(index % 6) as usize],
+                )
+            }
+            Color::Ansi256(index) => {
+                let gray = 8 + (index - 232) * 10;
+                (gray, gray, gray)
+            }
+        }
+    }
+}
+
+// Closest 256-color palette entry: the gray ramp for grays, the color cube otherwise
+fn palette_index(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            249..=255 => 231,
+            gray => 232 + (gray - 8) / 10,
+        };
+    }
+    let level = |channel: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| CUBE[i].abs_diff(channel))
+            .unwrap_or(0) as u8
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+fn nearest_basic((r, g, b): (u8, u8, u8)) -> AnsiColors {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    BASIC
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or(AnsiColors::White, |(color, _)| *color)
+}
@@ -0,0 +1,70 @@
+use crate::color::{ColorDepth, ColorMode};
+use crate::config::LogConfig;
+use crate::context::LogContext;
+use crate::formatter::FormatterType;
+use crate::level::LogLevel;
+use crate::logger::{LogFlow, LogFlowBuilder, LogFlowError};
+use crate::macros::init_global_logger;
+use crate::output::OutputType;
+use chrono::SecondsFormat;
+
+type Result<T> = std::result::Result<T, LogFlowError>;
+
+pub const DEFAULT_FILTER_ENV: &str = "RUST_LOG";
+pub const DEFAULT_WRITE_STYLE_ENV: &str = "RUST_LOG_STYLE";
+
+// Mirrors env_logger's default `[2024-01-01T00:00:00Z INFO  my_crate] message` layout
+pub fn env_logger_format(
+    level: &LogLevel,
+    message: &str,
+    context: &LogContext,
+    config: &LogConfig,
+) -> String {
+    let mut level_str = format!("{:<5}", level.as_str());
+    if config.colors_enabled() {
+        let depth = config.color_depth.unwrap_or_else(ColorDepth::detect);
+        level_str = config.theme.paint_level(*level, &level_str, depth);
+    }
+    format!(
+        "[{} {} {}] {}",
+        context.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        level_str,
+        context.target,
+        message
+    )
+}
+
+// env_logger semantics: only the bare level directive applies globally, default is ERROR
+pub fn level_from_env(var: &str) -> LogLevel {
+    std::env::var(var)
+        .ok()
+        .and_then(|spec| {
+            spec.split(',')
+                .map(str::trim)
+                .filter(|directive| !directive.contains('='))
+                .find_map(LogLevel::from_str)
+        })
+        .unwrap_or(LogLevel::Error)
+}
+
+// Unset or unknown values mean `auto`, as in env_logger
+pub fn color_mode_from_env(var: &str) -> ColorMode {
+    std::env::var(var)
+        .ok()
+        .and_then(|style| ColorMode::from_str(&style))
+        .unwrap_or_default()
+}
+
+pub fn env_logger_builder() -> LogFlowBuilder {
+    LogFlow::new()
+        .with_level(level_from_env(DEFAULT_FILTER_ENV))
+        .with_env_filter(DEFAULT_FILTER_ENV)
+        .with_color_mode(color_mode_from_env(DEFAULT_WRITE_STYLE_ENV))
+        .with_output(OutputType::Stderr)
+        .with_formatter(FormatterType::custom(env_logger_format))
+}
+
+pub fn init_from_env_logger_format() -> Result<()> {
+    init_global_logger(env_logger_builder().build()?);
+    Ok(())
+}
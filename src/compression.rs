@@ -0,0 +1,143 @@
+use std::io::{self, Write};
+
+/// Compression applied to network sinks.
+///
+/// HTTP sinks compress each batch and set `Content-Encoding`; TCP syslog compresses
+/// the connection as one stream, flushed after every message. `Gzip` needs the `gzip`
+/// feature and `Zstd` the `zstd` feature; opening an output with an algorithm that
+/// wasn't compiled in fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    pub fn is_available(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    pub fn ensure_available(self) -> io::Result<()> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "{:?} compression requires the `{}` feature",
+                    self,
+                    self.feature()
+                ),
+            ))
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = self.stream(Vec::new())?;
+        encoder.write_all(data)?;
+        encoder.finish()
+    }
+
+    pub fn stream<W: Write + Send + 'static>(self, inner: W) -> io::Result<Encoder<W>> {
+        self.ensure_available()?;
+        Ok(match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                inner,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(inner, 0)?),
+            _ => Encoder::Plain(inner),
+        })
+    }
+
+    fn feature(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Writer that compresses into `W`; `flush` emits a decodable block so a receiver can
+/// read every message written so far.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn get_ref(&self) -> &W {
+        match self {
+            Encoder::Plain(inner) => inner,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.get_ref(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.get_ref(),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(inner) => Ok(inner),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Encoder<W> {
+    // Ends the compressed stream in place, for owners that can't give up the encoder
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(inner) => inner.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.try_finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.do_finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(inner) => inner.write(data),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(data),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(inner) => inner.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
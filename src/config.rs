@@ -1,45 +1,201 @@
-use crate::formatter::FormatterType;
+use crate::budget::LogBudget;
+use crate::color::{ColorDepth, ColorMode};
+use crate::compression::Compression;
+use crate::context::{FieldValue, IdGen, LogContext, UuidIdGen, SERIALIZATION_ERROR, TRUNCATED};
+use crate::diagnostics;
+use crate::filter::EnvFilter;
+use crate::formatter::{
+    FieldOrder, FieldRenderer, FormatterType, IndentStyle, JsonFields, Overflow, Theme,
+    TimestampFormat, Timezone,
+};
 use crate::level::LogLevel;
-use crate::output::OutputType;
+use crate::logger::LogFlowError;
+use crate::notify::Notification;
+use crate::output::{BrokenPipePolicy, FileBuffer, IdleFlush, OutputType, Record};
+use crate::pipeline::Processor;
+use crate::ratelimit::RateLimit;
+use crate::syslog::Facility;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const ENV_LEVEL: &str = "LOGFLOW_LEVEL";
+pub const ENV_FORMAT: &str = "LOGFLOW_FORMAT";
+pub const ENV_COLOR: &str = "LOGFLOW_COLOR";
+pub const ENV_OUTPUT: &str = "LOGFLOW_OUTPUT";
+pub const ENV_TIMESTAMPS: &str = "LOGFLOW_TIMESTAMPS";
+pub const ENV_TIMEZONE: &str = "LOGFLOW_TIMEZONE";
+pub const ENV_FILTER: &str = "LOGFLOW_FILTER";
+pub const ENV_APP_NAME: &str = "LOGFLOW_APP_NAME";
+
+type FieldProvider = Arc<dyn Fn() -> Option<serde_json::Value> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct DynamicField {
+    pub key: String,
+    pub min_level: LogLevel,
+    provider: FieldProvider,
+}
+
+impl DynamicField {
+    pub fn new<F, T>(key: &str, min_level: LogLevel, provider: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Serialize,
+    {
+        Self {
+            key: key.to_string(),
+            min_level,
+            provider: Arc::new(move || serde_json::to_value(provider()).ok()),
+        }
+    }
+
+    pub fn evaluate(&self) -> Option<serde_json::Value> {
+        (self.provider)()
+    }
+}
+
+impl std::fmt::Debug for DynamicField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DynamicField({:?}, {:?})", self.key, self.min_level)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct LogConfig {
     pub level: LogLevel,
-    pub colors_enabled: bool,
+    pub color_mode: ColorMode,
     pub timestamps: bool,
     pub show_date: bool,
+    pub timezone: Timezone,
+    pub timestamp_format: TimestampFormat,
+    pub field_order: FieldOrder,
+    pub json_fields: JsonFields,
+    pub json_keys: HashMap<String, String>,
+    pub serialization_placeholder: bool,
+    pub max_field_len: Option<usize>,
+    pub max_fields_len: Option<usize>,
     pub show_target: bool,
     pub show_module: bool,
     pub show_file_line: bool,
     pub bold_subtitles: bool,
+    pub theme: Theme,
+    pub color_depth: Option<ColorDepth>,
+    pub unicode: Option<bool>,
+    pub hyperlinks: Option<bool>,
+    pub link_format: String,
+    pub ci_groups: Option<bool>,
     pub formatter: FormatterType,
     pub output: OutputType,
+    pub extra_outputs: Vec<(OutputType, LogLevel)>,
     pub indent_size: usize,
+    pub indent_style: IndentStyle,
     pub max_width: Option<usize>,
+    pub terminal_width: bool,
+    pub overflow: Overflow,
+    pub continuation_marker: Option<String>,
+    pub field_lines: bool,
+    pub deferred_scopes: bool,
+    pub align_columns: bool,
+    pub target_width: Option<usize>,
     pub custom_fields: HashMap<String, String>,
     pub filter_targets: Vec<String>,
     pub exclude_targets: Vec<String>,
+    pub env_filter: Option<EnvFilter>,
+    pub sampling: Vec<(LogLevel, f64)>,
+    pub dynamic_fields: Vec<DynamicField>,
+    pub global_fields: IndexMap<String, FieldValue>,
+    pub processors: Vec<Arc<dyn Processor>>,
+    pub field_renderers: HashMap<String, FieldRenderer>,
+    pub notifications: Vec<Notification>,
+    pub check_outputs_on_build: bool,
+    pub syslog_facility: Facility,
+    pub app_name: Option<String>,
+    pub broken_pipe: BrokenPipePolicy,
+    pub spool: Option<PathBuf>,
+    pub compression: Compression,
+    pub dump_config: bool,
+    pub idle_flush: Option<IdleFlush>,
+    pub file_buffer: Option<FileBuffer>,
+    pub min_free_space: u64,
+    pub budget: Option<LogBudget>,
+    pub rate_limit: Option<RateLimit>,
+    pub dedup_window: Option<Duration>,
+    pub backtrace_level: Option<LogLevel>,
+    pub thread_local_scopes: bool,
+    pub scope_timing: Option<LogLevel>,
+    pub id_gen: Arc<dyn IdGen>,
 }
 
 impl Default for LogConfig {
     fn default() -> Self {
         Self {
             level: LogLevel::Info,
-            colors_enabled: true,
+            color_mode: ColorMode::Auto,
             timestamps: true,
             show_date: false,
+            timezone: Timezone::Utc,
+            timestamp_format: TimestampFormat::Rfc3339,
+            field_order: FieldOrder::Insertion,
+            json_fields: JsonFields::Nested,
+            json_keys: HashMap::new(),
+            serialization_placeholder: false,
+            max_field_len: None,
+            max_fields_len: None,
             show_target: false,
             show_module: false,
             show_file_line: false,
             bold_subtitles: true,
+            theme: Theme::default(),
+            color_depth: None,
+            unicode: None,
+            hyperlinks: None,
+            link_format: "file://{path}".to_string(),
+            ci_groups: None,
             formatter: FormatterType::Pretty,
             output: OutputType::Stdout,
+            extra_outputs: Vec::new(),
             indent_size: 2,
+            indent_style: IndentStyle::default(),
             max_width: None,
+            terminal_width: true,
+            overflow: Overflow::Truncate,
+            continuation_marker: None,
+            field_lines: false,
+            deferred_scopes: false,
+            align_columns: false,
+            target_width: None,
             custom_fields: HashMap::new(),
             filter_targets: Vec::new(),
             exclude_targets: Vec::new(),
+            env_filter: None,
+            sampling: Vec::new(),
+            dynamic_fields: Vec::new(),
+            global_fields: IndexMap::new(),
+            processors: Vec::new(),
+            field_renderers: HashMap::new(),
+            notifications: Vec::new(),
+            check_outputs_on_build: false,
+            syslog_facility: Facility::default(),
+            app_name: None,
+            broken_pipe: BrokenPipePolicy::default(),
+            spool: None,
+            compression: Compression::default(),
+            dump_config: false,
+            idle_flush: None,
+            file_buffer: None,
+            min_free_space: 1024 * 1024,
+            budget: None,
+            rate_limit: None,
+            dedup_window: None,
+            backtrace_level: None,
+            thread_local_scopes: false,
+            scope_timing: None,
+            id_gen: Arc::new(UuidIdGen),
         }
     }
 }
@@ -55,10 +211,24 @@ impl LogConfig {
     }
 
     pub fn with_colors(mut self, enabled: bool) -> Self {
-        self.colors_enabled = enabled;
+        self.color_mode = if enabled {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        };
+        self
+    }
+
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.color_mode = mode;
         self
     }
 
+    // Whether the formatters color output, with `ColorMode::Auto` decided for `output`
+    pub fn colors_enabled(&self) -> bool {
+        self.color_mode.enabled_for(&self.output)
+    }
+
     pub fn with_timestamps(mut self, enabled: bool) -> Self {
         self.timestamps = enabled;
         self
@@ -69,6 +239,56 @@ impl LogConfig {
         self
     }
 
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+
+    /// Writes fields in the order they were added (the default) or sorted by key.
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.field_order = order;
+        self
+    }
+
+    /// Nests JSON fields under `"fields"` (the default) or flattens them to top-level keys.
+    pub fn with_json_fields(mut self, json_fields: JsonFields) -> Self {
+        self.json_fields = json_fields;
+        self
+    }
+
+    /// Renames a standard JSON key, e.g. `with_json_key("message", "msg")` or
+    /// `with_json_key("timestamp", "@timestamp")`.
+    pub fn with_json_key(mut self, key: &str, name: &str) -> Self {
+        self.json_keys.insert(key.to_string(), name.to_string());
+        self
+    }
+
+    /// Keeps fields whose value fails to serialize as
+    /// [`SERIALIZATION_ERROR`](crate::SERIALIZATION_ERROR) instead of dropping them.
+    pub fn with_serialization_placeholder(mut self, enabled: bool) -> Self {
+        self.serialization_placeholder = enabled;
+        self
+    }
+
+    /// Cuts field values longer than `len` chars (JSON text for non-strings) to
+    /// `len` chars ending in `...`, and marks the record with `truncated=true`.
+    pub fn with_max_field_len(mut self, len: usize) -> Self {
+        self.max_field_len = Some(len);
+        self
+    }
+
+    /// Caps the combined length of a record's field values; fields past the cap are
+    /// cut, down to `...`, and the record is marked with `truncated=true`.
+    pub fn with_max_fields_len(mut self, len: usize) -> Self {
+        self.max_fields_len = Some(len);
+        self
+    }
+
     pub fn with_target(mut self, enabled: bool) -> Self {
         self.show_target = enabled;
         self
@@ -89,6 +309,46 @@ impl LogConfig {
         self
     }
 
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    // Overrides the depth detected from the terminal
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = Some(depth);
+        self
+    }
+
+    /// Unicode icons when true, their ASCII fallbacks when false; detected from the
+    /// terminal and locale by default.
+    pub fn with_unicode(mut self, enabled: bool) -> Self {
+        self.unicode = Some(enabled);
+        self
+    }
+
+    /// Makes `file:line` a clickable OSC 8 link; on by default where the terminal
+    /// is known to support them.
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.hyperlinks = Some(enabled);
+        self
+    }
+
+    /// Link target with `{path}` and `{line}` placeholders, e.g.
+    /// `vscode://file{path}:{line}`; `file://{path}` by default.
+    pub fn with_link_format(mut self, format: &str) -> Self {
+        self.link_format = format.to_string();
+        self
+    }
+
+    /// Folds scopes into collapsible groups in CI logs (GitHub Actions groups, GitLab
+    /// sections, TeamCity blocks); on by default for the CI formats, and for pretty and
+    /// compact output when a CI system is detected.
+    pub fn with_ci_groups(mut self, enabled: bool) -> Self {
+        self.ci_groups = Some(enabled);
+        self
+    }
+
     pub fn with_formatter(mut self, formatter: FormatterType) -> Self {
         self.formatter = formatter;
         self
@@ -99,16 +359,84 @@ impl LogConfig {
         self
     }
 
+    // An additional output with its own minimum level, e.g. a Debug file next to an Info console
+    pub fn with_output_at_level(mut self, output: OutputType, level: LogLevel) -> Self {
+        self.extra_outputs.push((output, level));
+        self
+    }
+
     pub fn with_indent_size(mut self, size: usize) -> Self {
         self.indent_size = size;
         self
     }
 
+    pub fn with_indent_style(mut self, style: IndentStyle) -> Self {
+        self.indent_style = style;
+        self
+    }
+
+    pub fn with_ascii_indent(mut self) -> Self {
+        self.indent_style = IndentStyle::ascii().with_scope_name(self.indent_style.show_scope_name);
+        self
+    }
+
+    pub fn with_scope_names(mut self, enabled: bool) -> Self {
+        self.indent_style.show_scope_name = enabled;
+        self
+    }
+
     pub fn with_max_width(mut self, width: usize) -> Self {
         self.max_width = Some(width);
         self
     }
 
+    /// Whether the terminal width is the default `max_width` when logging to a terminal.
+    pub fn with_terminal_width(mut self, enabled: bool) -> Self {
+        self.terminal_width = enabled;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Marker such as `↳` or `…` in front of each continuation line of a multi-line message.
+    pub fn with_continuation_marker(mut self, marker: impl Into<String>) -> Self {
+        self.continuation_marker = Some(marker.into());
+        self
+    }
+
+    /// Prints fields one per line under the message instead of inline as `{k=v, ...}`.
+    pub fn with_field_lines(mut self, enabled: bool) -> Self {
+        self.field_lines = enabled;
+        self
+    }
+
+    /// Holds back the records of a scope, nested scopes included, and writes them as
+    /// one tree when the outermost scope closes. Async scopes aren't deferred.
+    ///
+    /// Records are matched to their scope by context id, so building fails with an
+    /// id generator whose [`IdGen::unique`](crate::IdGen::unique) is false, such as
+    /// [`NoIdGen`](crate::NoIdGen).
+    pub fn with_deferred_scopes(mut self, enabled: bool) -> Self {
+        self.deferred_scopes = enabled;
+        self
+    }
+
+    /// Pads level tags and targets to one width so messages start in the same column;
+    /// targets grow to the widest seen so far unless `with_target_width` fixes it.
+    pub fn with_aligned_columns(mut self, enabled: bool) -> Self {
+        self.align_columns = enabled;
+        self
+    }
+
+    // Longer targets are cut to `width`
+    pub fn with_target_width(mut self, width: usize) -> Self {
+        self.target_width = Some(width);
+        self
+    }
+
     pub fn with_custom_field(mut self, key: String, value: String) -> Self {
         self.custom_fields.insert(key, value);
         self
@@ -124,9 +452,529 @@ impl LogConfig {
         self
     }
 
+    pub fn with_filter(mut self, filter: EnvFilter) -> Self {
+        self.env_filter = Some(filter);
+        self
+    }
+
+    /// Keeps only `rate` (0.0 to 1.0) of the records at `level` and below, chosen at
+    /// random before they are formatted. With several rules, the one for the lowest
+    /// level covering a record applies, e.g. `(Trace, 0.001)` and `(Debug, 0.01)`.
+    pub fn with_sampling(mut self, level: LogLevel, rate: f64) -> Self {
+        self.sampling.retain(|(existing, _)| *existing != level);
+        self.sampling.push((level, rate.clamp(0.0, 1.0)));
+        self.sampling.sort_by_key(|(level, _)| *level);
+        self
+    }
+
+    // Reads directives like `info,my_app::db=debug` from `var`; an invalid spec is
+    // reported and ignored
+    pub fn with_env_filter(mut self, var: &str) -> Self {
+        match EnvFilter::from_env(var) {
+            Ok(filter) => self.env_filter = Some(filter),
+            Err(err) => diagnostics::report(&err),
+        }
+        self
+    }
+
+    /// Attaches a fixed field such as the service name, version or pid to every record;
+    /// fields set on the record itself take precedence. A value that fails to serialize
+    /// is dropped, or kept as the placeholder if
+    /// [`with_serialization_placeholder`](Self::with_serialization_placeholder) was
+    /// enabled before this call.
+    pub fn with_global_field<T: Serialize>(mut self, key: &str, value: T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.global_fields.insert(key.to_string(), value.into());
+            }
+            Err(_) if self.serialization_placeholder => {
+                self.global_fields
+                    .insert(key.to_string(), SERIALIZATION_ERROR.into());
+            }
+            Err(_) => {}
+        }
+        self
+    }
+
+    /// Like [`with_global_field`](Self::with_global_field), but returns
+    /// [`LogFlowError::Field`] if `value` fails to serialize.
+    pub fn try_with_global_field<T: Serialize>(
+        mut self,
+        key: &str,
+        value: T,
+    ) -> Result<Self, LogFlowError> {
+        let value = serde_json::to_value(value).map_err(|source| LogFlowError::Field {
+            key: key.to_string(),
+            source,
+        })?;
+        self.global_fields.insert(key.to_string(), value.into());
+        Ok(self)
+    }
+
+    pub fn with_dynamic_field<F, T>(self, key: &str, provider: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Serialize,
+    {
+        self.with_dynamic_field_at_level(key, LogLevel::Trace, provider)
+    }
+
+    pub fn with_dynamic_field_at_level<F, T>(
+        mut self,
+        key: &str,
+        min_level: LogLevel,
+        provider: F,
+    ) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Serialize,
+    {
+        self.dynamic_fields
+            .push(DynamicField::new(key, min_level, provider));
+        self
+    }
+
+    pub fn with_field_renderer(mut self, key: &str, renderer: FieldRenderer) -> Self {
+        self.field_renderers.insert(key.to_string(), renderer);
+        self
+    }
+
+    /// Adds a step to the record pipeline, run after the ones added before it.
+    pub fn with_processor<P: Processor + 'static>(mut self, processor: P) -> Self {
+        self.processors.push(Arc::new(processor));
+        self
+    }
+
+    pub fn with_notification(mut self, notification: Notification) -> Self {
+        self.notifications.push(notification);
+        self
+    }
+
+    pub fn with_output_check(mut self, enabled: bool) -> Self {
+        self.check_outputs_on_build = enabled;
+        self
+    }
+
+    pub fn with_syslog_facility(mut self, facility: Facility) -> Self {
+        self.syslog_facility = facility;
+        self
+    }
+
+    pub fn with_app_name(mut self, name: &str) -> Self {
+        self.app_name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_broken_pipe_policy(mut self, policy: BrokenPipePolicy) -> Self {
+        self.broken_pipe = policy;
+        self
+    }
+
+    // Queues records for the network output (syslog, GELF, Loki, Elasticsearch, OTLP)
+    // in this file until it accepts them; only one network output may be configured
+    pub fn with_spool<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.spool = Some(path.into());
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_config_dump(mut self, enabled: bool) -> Self {
+        self.dump_config = enabled;
+        self
+    }
+
+    // Applies to File and RollingFile outputs
+    pub fn with_idle_flush(mut self, idle_flush: IdleFlush) -> Self {
+        self.idle_flush = Some(idle_flush);
+        self
+    }
+
+    // Applies to File and RollingFile outputs
+    pub fn with_file_buffer(mut self, file_buffer: FileBuffer) -> Self {
+        self.file_buffer = Some(file_buffer);
+        self
+    }
+
+    /// Free disk space below which the health check of File and RollingFile outputs
+    /// fails with `StorageFull`, 1 MiB by default and 0 to skip the check. Only
+    /// checked on Unix.
+    pub fn with_min_free_space(mut self, bytes: u64) -> Self {
+        self.min_free_space = bytes;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: LogBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Collapses consecutive identical records into a "Last message repeated N times"
+    /// line, written at the latest every `window` while the repeats continue.
+    pub fn with_dedup(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Captures a backtrace for records at `level` and above, e.g. `LogLevel::Error`;
+    /// shown as indented frames by Pretty and as an array by JSON.
+    pub fn with_backtraces(mut self, level: LogLevel) -> Self {
+        self.backtrace_level = Some(level);
+        self
+    }
+
+    /// Keeps the sync logger's scopes per thread, so a scope opened on one thread
+    /// doesn't nest records logged on another. The async logger rejects this.
+    pub fn with_thread_local_scopes(mut self, enabled: bool) -> Self {
+        self.thread_local_scopes = enabled;
+        self
+    }
+
+    /// Logs a closing record with the scope's `duration_ms` at `level` when a scope is
+    /// dropped, or finished in the async logger.
+    pub fn with_scope_timing(mut self, level: LogLevel) -> Self {
+        self.scope_timing = Some(level);
+        self
+    }
+
+    pub fn with_id_gen<G: IdGen + 'static>(mut self, id_gen: G) -> Self {
+        self.id_gen = Arc::new(id_gen);
+        self
+    }
+
+    // Contexts the loggers create take their ids from `id_gen`
+    pub fn new_context(&self, target: String) -> LogContext {
+        LogContext::new_with_id(target, self.id_gen.next_id())
+    }
+
+    pub fn child_context(&self, parent: &LogContext, target: String) -> LogContext {
+        parent.child_with_id(target, self.id_gen.next_id())
+    }
+
+    /// Fields of the record written at build time when `dump_config` is set, one per
+    /// config field and under the same name.
+    ///
+    /// ```
+    /// use logflow::LogConfig;
+    ///
+    /// let config = LogConfig::default();
+    /// let keys: Vec<_> = config.summary().into_iter().map(|(key, _)| key).collect();
+    /// for line in format!("{:#?}", config).lines() {
+    ///     let field = line.strip_prefix("    ").and_then(|line| line.split_once(':'));
+    ///     if let Some((field, _)) = field.filter(|(field, _)| !field.starts_with(' ')) {
+    ///         assert!(keys.contains(&field), "`{}` is missing from the summary", field);
+    ///     }
+    /// }
+    /// ```
+    pub fn summary(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let formatter = match self.formatter {
+            FormatterType::Pretty => "pretty",
+            FormatterType::Compact => "compact",
+            FormatterType::Json => "json",
+            FormatterType::Logfmt => "logfmt",
+            FormatterType::Gelf => "gelf",
+            FormatterType::Ecs => "ecs",
+            FormatterType::GitHub => "github",
+            FormatterType::GitLab => "gitlab",
+            FormatterType::TeamCity => "teamcity",
+            FormatterType::Custom(_) => "custom",
+        };
+        let dynamic_fields: Vec<&str> = self
+            .dynamic_fields
+            .iter()
+            .map(|field| field.key.as_str())
+            .collect();
+
+        vec![
+            ("level", serde_json::json!(self.level.as_str())),
+            ("formatter", serde_json::json!(formatter)),
+            ("timestamps", serde_json::json!(self.timestamps)),
+            ("show_date", serde_json::json!(self.show_date)),
+            (
+                "timestamp_format",
+                serde_json::json!(format!("{:?}", self.timestamp_format)),
+            ),
+            (
+                "field_order",
+                serde_json::json!(format!("{:?}", self.field_order).to_lowercase()),
+            ),
+            (
+                "json_fields",
+                serde_json::json!(format!("{:?}", self.json_fields).to_lowercase()),
+            ),
+            ("json_keys", serde_json::json!(self.json_keys)),
+            (
+                "serialization_placeholder",
+                serde_json::json!(self.serialization_placeholder),
+            ),
+            ("max_field_len", serde_json::json!(self.max_field_len)),
+            ("max_fields_len", serde_json::json!(self.max_fields_len)),
+            (
+                "color_mode",
+                serde_json::json!(format!("{:?}", self.color_mode)),
+            ),
+            ("show_target", serde_json::json!(self.show_target)),
+            ("show_module", serde_json::json!(self.show_module)),
+            ("show_file_line", serde_json::json!(self.show_file_line)),
+            ("bold_subtitles", serde_json::json!(self.bold_subtitles)),
+            ("theme", serde_json::json!(self.theme.name())),
+            (
+                "color_depth",
+                serde_json::json!(self
+                    .color_depth
+                    .map_or("detected".to_string(), |depth| format!("{:?}", depth))),
+            ),
+            (
+                "unicode",
+                serde_json::json!(self
+                    .unicode
+                    .map_or("detected".to_string(), |unicode| unicode.to_string())),
+            ),
+            (
+                "hyperlinks",
+                serde_json::json!(self
+                    .hyperlinks
+                    .map_or("detected".to_string(), |enabled| enabled.to_string())),
+            ),
+            ("link_format", serde_json::json!(self.link_format)),
+            (
+                "ci_groups",
+                serde_json::json!(self
+                    .ci_groups
+                    .map_or("detected".to_string(), |enabled| enabled.to_string())),
+            ),
+            ("output", serde_json::json!(format!("{:?}", self.output))),
+            (
+                "max_width",
+                serde_json::json!(match self.max_width {
+                    Some(width) => width.to_string(),
+                    None if self.terminal_width => "terminal".to_string(),
+                    None => "none".to_string(),
+                }),
+            ),
+            ("terminal_width", serde_json::json!(self.terminal_width)),
+            ("indent_size", serde_json::json!(self.indent_size)),
+            (
+                "indent_style",
+                serde_json::json!(format!("{:?}", self.indent_style)),
+            ),
+            (
+                "overflow",
+                serde_json::json!(format!("{:?}", self.overflow)),
+            ),
+            (
+                "continuation_marker",
+                serde_json::json!(self.continuation_marker),
+            ),
+            ("field_lines", serde_json::json!(self.field_lines)),
+            ("deferred_scopes", serde_json::json!(self.deferred_scopes)),
+            ("align_columns", serde_json::json!(self.align_columns)),
+            ("target_width", serde_json::json!(self.target_width)),
+            (
+                "timezone",
+                serde_json::json!(format!("{:?}", self.timezone)),
+            ),
+            (
+                "extra_outputs",
+                serde_json::json!(self
+                    .extra_outputs
+                    .iter()
+                    .map(|(output, level)| format!("{:?} at {}", output, level.as_str()))
+                    .collect::<Vec<_>>()),
+            ),
+            ("custom_fields", serde_json::json!(self.custom_fields)),
+            ("filter_targets", serde_json::json!(self.filter_targets)),
+            ("exclude_targets", serde_json::json!(self.exclude_targets)),
+            (
+                "sampling",
+                serde_json::json!(self
+                    .sampling
+                    .iter()
+                    .map(|(level, rate)| format!("{} at {}", level.as_str(), rate))
+                    .collect::<Vec<_>>()),
+            ),
+            (
+                "env_filter",
+                serde_json::json!(format!("{:?}", self.env_filter)),
+            ),
+            ("dynamic_fields", serde_json::json!(dynamic_fields)),
+            ("global_fields", serde_json::json!(self.global_fields)),
+            ("processors", serde_json::json!(self.processors.len())),
+            (
+                "field_renderers",
+                serde_json::json!(self.field_renderers.keys().collect::<Vec<_>>()),
+            ),
+            ("notifications", serde_json::json!(self.notifications.len())),
+            (
+                "check_outputs_on_build",
+                serde_json::json!(self.check_outputs_on_build),
+            ),
+            (
+                "syslog_facility",
+                serde_json::json!(format!("{:?}", self.syslog_facility)),
+            ),
+            ("app_name", serde_json::json!(self.app_name)),
+            (
+                "broken_pipe",
+                serde_json::json!(format!("{:?}", self.broken_pipe)),
+            ),
+            ("spool", serde_json::json!(self.spool)),
+            (
+                "compression",
+                serde_json::json!(format!("{:?}", self.compression)),
+            ),
+            (
+                "idle_flush",
+                serde_json::json!(format!("{:?}", self.idle_flush)),
+            ),
+            (
+                "file_buffer",
+                serde_json::json!(format!("{:?}", self.file_buffer)),
+            ),
+            ("min_free_space", serde_json::json!(self.min_free_space)),
+            ("budget", serde_json::json!(format!("{:?}", self.budget))),
+            (
+                "scope_timing",
+                serde_json::json!(self.scope_timing.map(|level| level.as_str())),
+            ),
+            (
+                "thread_local_scopes",
+                serde_json::json!(self.thread_local_scopes),
+            ),
+            (
+                "backtrace_level",
+                serde_json::json!(self.backtrace_level.map(|level| level.as_str())),
+            ),
+            (
+                "dedup_window",
+                serde_json::json!(format!("{:?}", self.dedup_window)),
+            ),
+            (
+                "rate_limit",
+                serde_json::json!(format!("{:?}", self.rate_limit)),
+            ),
+            ("id_gen", serde_json::json!(self.id_gen.name())),
+            ("dump_config", serde_json::json!(self.dump_config)),
+            ("logging_enabled", serde_json::json!(crate::LOGGING_ENABLED)),
+        ]
+    }
+
+    // Captured here so the cost is only paid at the configured levels
+    pub fn attach_backtrace(&self, level: LogLevel, context: LogContext) -> LogContext {
+        match self.backtrace_level {
+            Some(min) if level >= min && context.backtrace.is_none() => {
+                context.with_backtrace(crate::backtrace::capture())
+            }
+            _ => context,
+        }
+    }
+
+    pub fn apply_dynamic_fields(&self, level: LogLevel, mut context: LogContext) -> LogContext {
+        for (key, value) in &self.global_fields {
+            context
+                .fields
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for field in &self.dynamic_fields {
+            if level < field.min_level {
+                continue;
+            }
+            if let Some(value) = field.evaluate() {
+                context.fields.insert(field.key.clone(), value.into());
+            }
+        }
+        context
+    }
+
+    // Applies `max_field_len` and then `max_fields_len`, in field order
+    pub fn limit_fields(&self, mut context: LogContext) -> LogContext {
+        if self.max_field_len.is_none() && self.max_fields_len.is_none() {
+            return context;
+        }
+
+        let mut remaining = self.max_fields_len.unwrap_or(usize::MAX);
+        let mut truncated = false;
+        for value in context.fields.values_mut() {
+            let limit = self.max_field_len.unwrap_or(usize::MAX).min(remaining);
+            let text = match value.as_str() {
+                Some(text) => Cow::Borrowed(text),
+                None => Cow::Owned(value.to_string()),
+            };
+            let len = text.chars().count();
+            if len <= limit {
+                remaining -= len;
+                continue;
+            }
+            let cut = truncate_field(&text, limit);
+            remaining -= limit.min(remaining);
+            *value = cut.into();
+            truncated = true;
+        }
+        if truncated {
+            context.fields.insert(TRUNCATED.to_string(), true.into());
+        }
+        context
+    }
+
+    // Runs the record through the processors; false once one of them drops it
+    pub fn process(&self, record: &mut Record) -> bool {
+        self.processors
+            .iter()
+            .all(|processor| processor.process(record))
+    }
+
     pub fn should_log(&self, level: LogLevel, target: &str) -> bool {
-        // Check log level
-        if level < self.level {
+        let threshold = self.filter_level(target).unwrap_or(self.level);
+        self.should_log_at(level, threshold, target)
+    }
+
+    // Fraction of records at `level` that sampling keeps, if a rule covers it
+    pub fn sample_rate(&self, level: LogLevel) -> Option<f64> {
+        self.sampling
+            .iter()
+            .find(|(max, _)| level <= *max)
+            .map(|(_, rate)| *rate)
+    }
+
+    // Level the env filter sets for `target`, if any directive applies
+    pub fn filter_level(&self, target: &str) -> Option<LogLevel> {
+        self.env_filter
+            .as_ref()
+            .and_then(|filter| filter.level_for(target))
+    }
+
+    // Lowest level any output accepts, given the primary output's current threshold
+    pub fn output_floor(&self, threshold: LogLevel) -> LogLevel {
+        self.extra_outputs
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(threshold, LogLevel::min)
+    }
+
+    pub fn should_log_at(&self, level: LogLevel, threshold: LogLevel, target: &str) -> bool {
+        if !crate::LOGGING_ENABLED {
+            return false;
+        }
+
+        // Check log level; nothing is logged at Off itself
+        if level < threshold || level == LogLevel::Off {
+            return false;
+        }
+
+        if self
+            .env_filter
+            .as_ref()
+            .is_some_and(|filter| filter.disables(target))
+        {
             return false;
         }
 
@@ -145,7 +993,7 @@ impl LogConfig {
 
     pub fn pretty() -> Self {
         Self::default()
-            .with_colors(true)
+            .with_color_mode(ColorMode::Auto)
             .with_timestamps(true)
             .with_formatter(FormatterType::Pretty)
     }
@@ -164,12 +1012,173 @@ impl LogConfig {
             .with_formatter(FormatterType::Json)
     }
 
+    pub fn logfmt() -> Self {
+        Self::default()
+            .with_colors(false)
+            .with_timestamps(true)
+            .with_formatter(FormatterType::Logfmt)
+    }
+
+    pub fn gelf() -> Self {
+        Self::default()
+            .with_colors(false)
+            .with_timestamps(true)
+            .with_formatter(FormatterType::Gelf)
+    }
+
+    pub fn ecs() -> Self {
+        Self::default()
+            .with_colors(false)
+            .with_timestamps(true)
+            .with_formatter(FormatterType::Ecs)
+    }
+
     pub fn dev() -> Self {
         Self::default()
-            .with_colors(true)
+            .with_color_mode(ColorMode::Auto)
             .with_timestamps(true)
             .with_module(true)
             .with_file_line(true)
             .with_formatter(FormatterType::Pretty)
     }
 }
+
+impl LogConfig {
+    /// Default configuration with the `LOGFLOW_*` environment variables applied, see
+    /// [`apply_env`](Self::apply_env).
+    pub fn from_env() -> Result<Self, LogFlowError> {
+        Self::default().apply_env()
+    }
+
+    /// Overrides settings from the environment, so containers can switch output
+    /// without a rebuild. Unset or empty variables leave the setting alone:
+    ///
+    /// - `LOGFLOW_LEVEL`: `trace` to `fatal`
+    /// - `LOGFLOW_FORMAT`: `pretty`, `compact`, `json`, `logfmt`, `gelf`, `ecs`, `github`,
+    ///   `gitlab` or `teamcity`; when unset, pretty and compact output switch to the CI
+    ///   format under GitHub Actions, GitLab CI or TeamCity (see [`FormatterType::detect_ci`])
+    /// - `LOGFLOW_COLOR`: `always`, `never` or `auto` (on for terminals, see [`ColorMode`])
+    /// - `LOGFLOW_OUTPUT`: `stdout`, `stderr` or a file path
+    /// - `LOGFLOW_TIMESTAMPS`: `true` or `false`
+    /// - `LOGFLOW_TIMEZONE`: `utc`, `local` or an offset like `+05:30`
+    /// - `LOGFLOW_FILTER`: directives like `info,my_app::db=debug`
+    /// - `LOGFLOW_APP_NAME`: application name for syslog and journald
+    pub fn apply_env(mut self) -> Result<Self, LogFlowError> {
+        if let Some(level) = env_value(ENV_LEVEL) {
+            self.level =
+                LogLevel::from_str(&level).ok_or_else(|| invalid_env(ENV_LEVEL, &level))?;
+        }
+
+        if let Some(format) = env_value(ENV_FORMAT) {
+            self.formatter = match format.to_lowercase().as_str() {
+                "pretty" => FormatterType::Pretty,
+                "compact" => FormatterType::Compact,
+                "json" => FormatterType::Json,
+                "logfmt" => FormatterType::Logfmt,
+                "gelf" => FormatterType::Gelf,
+                "ecs" => FormatterType::Ecs,
+                "github" => FormatterType::GitHub,
+                "gitlab" => FormatterType::GitLab,
+                "teamcity" => FormatterType::TeamCity,
+                _ => return Err(invalid_env(ENV_FORMAT, &format)),
+            };
+            // Machine formats never want escape codes, unless asked for below
+            if !matches!(
+                self.formatter,
+                FormatterType::Pretty | FormatterType::Compact
+            ) {
+                self.color_mode = ColorMode::Never;
+            }
+        } else if matches!(
+            self.formatter,
+            FormatterType::Pretty | FormatterType::Compact
+        ) {
+            if let Some(ci) = FormatterType::detect_ci() {
+                self.formatter = ci;
+                self.color_mode = ColorMode::Never;
+            }
+        }
+
+        if let Some(color) = env_value(ENV_COLOR) {
+            self.color_mode =
+                ColorMode::from_str(&color).ok_or_else(|| invalid_env(ENV_COLOR, &color))?;
+        }
+
+        if let Some(output) = env_value(ENV_OUTPUT) {
+            self.output = match output.to_lowercase().as_str() {
+                "stdout" => OutputType::Stdout,
+                "stderr" => OutputType::Stderr,
+                _ => OutputType::File(PathBuf::from(output)),
+            };
+        }
+
+        if let Some(timestamps) = env_value(ENV_TIMESTAMPS) {
+            self.timestamps =
+                parse_flag(&timestamps).ok_or_else(|| invalid_env(ENV_TIMESTAMPS, &timestamps))?;
+        }
+
+        if let Some(timezone) = env_value(ENV_TIMEZONE) {
+            self.timezone =
+                parse_timezone(&timezone).ok_or_else(|| invalid_env(ENV_TIMEZONE, &timezone))?;
+        }
+
+        if let Some(filter) = env_value(ENV_FILTER) {
+            self.env_filter = Some(EnvFilter::parse(&filter)?);
+        }
+
+        if let Some(app_name) = env_value(ENV_APP_NAME) {
+            self.app_name = Some(app_name);
+        }
+
+        Ok(self)
+    }
+}
+
+fn env_value(var: &str) -> Option<String> {
+    std::env::var(var)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn invalid_env(var: &str, value: &str) -> LogFlowError {
+    LogFlowError::Config(format!("Invalid value for {}: {}", var, value))
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" | "always" => Some(true),
+        "0" | "false" | "no" | "off" | "never" => Some(false),
+        _ => None,
+    }
+}
+
+// `utc`, `local`, or `+HH:MM` / `-HH:MM`
+fn parse_timezone(value: &str) -> Option<Timezone> {
+    match value.to_lowercase().as_str() {
+        "utc" | "z" => return Some(Timezone::Utc),
+        "local" => return Some(Timezone::Local),
+        _ => {}
+    }
+
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    Timezone::fixed(sign * (hours * 3600 + minutes * 60))
+}
+
+// The first `len` chars of `text`, the last three replaced by `...`; never shorter
+// than the ellipsis, so a field cut to nothing still shows it was cut
+fn truncate_field(text: &str, len: usize) -> String {
+    let mut cut: String = text.chars().take(len.saturating_sub(3)).collect();
+    cut.push_str("...");
+    cut
+}
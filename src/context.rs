@@ -1,9 +1,261 @@
+use crate::diagnostics;
+use crate::formatter::Style;
+use crate::level::LogLevel;
+use crate::logger::LogFlowError;
 use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, MutexGuard};
 use uuid::Uuid;
 
+/// Field holding the correlation id of a request scope, inherited by nested contexts.
+pub const CORRELATION_ID: &str = "correlation_id";
+
+/// Field set to `true` on records whose field values were cut to the configured
+/// maximum lengths.
+pub const TRUNCATED: &str = "truncated";
+
+/// Stands in for a field value that failed to serialize, with
+/// [`LogConfig::with_serialization_placeholder`](crate::LogConfig::with_serialization_placeholder).
+pub const SERIALIZATION_ERROR: &str = "<serialization error>";
+
+/// A field value as stored on a [`LogContext`].
+///
+/// The typed field methods such as [`LogContext::with_str`] and
+/// [`LogContext::with_value`] keep scalars as they are, and so does `with_field` for
+/// values that serialize to a scalar; anything else is kept as JSON. Text formatters
+/// write values directly, and only JSON-based outputs convert them with
+/// [`FieldValue::to_json`].
+///
+/// ```
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// logger
+///     .with_str("user", "ann")
+///     .with_u64("bytes", 4096)
+///     .with_display("addr", &std::net::Ipv4Addr::LOCALHOST)
+///     .info("Upload finished")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Str(String),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Bool(bool),
+    /// Arrays, objects and null.
+    Json(serde_json::Value),
+}
+
+impl FieldValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FieldValue::Str(text) => Some(text),
+            FieldValue::Json(serde_json::Value::String(text)) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::I64(number) => Some(*number as f64),
+            FieldValue::U64(number) => Some(*number as f64),
+            FieldValue::F64(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    // Non-finite floats count too, as JSON has no number for them
+    pub fn is_null(&self) -> bool {
+        match self {
+            FieldValue::F64(number) => !number.is_finite(),
+            FieldValue::Json(value) => value.is_null(),
+            _ => false,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            FieldValue::Str(text) => text.as_str().into(),
+            FieldValue::I64(number) => (*number).into(),
+            FieldValue::U64(number) => (*number).into(),
+            // NaN and infinities have no JSON number and become null
+            FieldValue::F64(number) => (*number).into(),
+            FieldValue::Bool(flag) => (*flag).into(),
+            FieldValue::Json(value) => value.clone(),
+        }
+    }
+
+    // Strings as their bare text and everything else as JSON, for plain-text outputs
+    pub fn to_text(&self) -> String {
+        match self.as_str() {
+            Some(text) => text.to_string(),
+            None => self.to_string(),
+        }
+    }
+}
+
+/// JSON text of the value, so strings are quoted.
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::I64(number) => write!(f, "{}", number),
+            FieldValue::U64(number) => write!(f, "{}", number),
+            FieldValue::Bool(flag) => write!(f, "{}", flag),
+            _ => serde_json::to_writer(FmtWriter(f), self).map_err(|_| std::fmt::Error),
+        }
+    }
+}
+
+// Lets serde_json write straight into a formatter
+struct FmtWriter<'a, 'b>(&'a mut std::fmt::Formatter<'b>);
+
+impl std::io::Write for FmtWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = std::str::from_utf8(buf).map_err(std::io::Error::other)?;
+        self.0.write_str(text).map_err(std::io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Serialize for FieldValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FieldValue::Str(text) => serializer.serialize_str(text),
+            FieldValue::I64(number) => serializer.serialize_i64(*number),
+            FieldValue::U64(number) => serializer.serialize_u64(*number),
+            FieldValue::F64(number) => serializer.serialize_f64(*number),
+            FieldValue::Bool(flag) => serializer.serialize_bool(*flag),
+            FieldValue::Json(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde_json::Value::deserialize(deserializer).map(FieldValue::from)
+    }
+}
+
+impl From<serde_json::Value> for FieldValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(text) => FieldValue::Str(text),
+            serde_json::Value::Bool(flag) => FieldValue::Bool(flag),
+            serde_json::Value::Number(number) => {
+                if let Some(number) = number.as_i64() {
+                    FieldValue::I64(number)
+                } else if let Some(number) = number.as_u64() {
+                    FieldValue::U64(number)
+                } else {
+                    FieldValue::F64(number.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            value => FieldValue::Json(value),
+        }
+    }
+}
+
+impl From<FieldValue> for serde_json::Value {
+    fn from(value: FieldValue) -> Self {
+        match value {
+            FieldValue::Str(text) => text.into(),
+            FieldValue::Json(value) => value,
+            value => value.to_json(),
+        }
+    }
+}
+
+impl From<&str> for FieldValue {
+    fn from(text: &str) -> Self {
+        FieldValue::Str(text.to_string())
+    }
+}
+
+impl From<&String> for FieldValue {
+    fn from(text: &String) -> Self {
+        FieldValue::Str(text.clone())
+    }
+}
+
+impl From<String> for FieldValue {
+    fn from(text: String) -> Self {
+        FieldValue::Str(text)
+    }
+}
+
+impl From<bool> for FieldValue {
+    fn from(flag: bool) -> Self {
+        FieldValue::Bool(flag)
+    }
+}
+
+macro_rules! field_value_from {
+    ($variant:ident as $as:ty: $($ty:ty),*) => {
+        $(impl From<$ty> for FieldValue {
+            fn from(number: $ty) -> Self {
+                FieldValue::$variant(number as $as)
+            }
+        })*
+    };
+}
+
+field_value_from!(I64 as i64: i8, i16, i32, i64, isize);
+field_value_from!(U64 as u64: u8, u16, u32, u64, usize);
+field_value_from!(F64 as f64: f32, f64);
+
+// `with_str`, `with_u64` and the other typed shortcuts for `with_value`, on
+// contexts and field loggers (`consuming`) and on what starts a field logger
+macro_rules! typed_field_methods {
+    (consuming) => {
+        typed_field_methods!(@methods [] self, Self);
+    };
+    (borrowing $ret:ty) => {
+        typed_field_methods!(@methods [&] self, $ret);
+    };
+    (@methods [$($borrow:tt)?] $this:ident, $ret:ty) => {
+        pub fn with_str($($borrow)? $this, key: &str, value: &str) -> $ret {
+            $this.with_value(key, value)
+        }
+
+        pub fn with_i64($($borrow)? $this, key: &str, value: i64) -> $ret {
+            $this.with_value(key, $crate::context::FieldValue::I64(value))
+        }
+
+        pub fn with_u64($($borrow)? $this, key: &str, value: u64) -> $ret {
+            $this.with_value(key, $crate::context::FieldValue::U64(value))
+        }
+
+        pub fn with_f64($($borrow)? $this, key: &str, value: f64) -> $ret {
+            $this.with_value(key, $crate::context::FieldValue::F64(value))
+        }
+
+        pub fn with_bool($($borrow)? $this, key: &str, value: bool) -> $ret {
+            $this.with_value(key, $crate::context::FieldValue::Bool(value))
+        }
+
+        pub fn with_display($($borrow)? $this, key: &str, value: &dyn std::fmt::Display) -> $ret {
+            $this.with_value(key, value.to_string())
+        }
+
+        pub fn with_debug($($borrow)? $this, key: &str, value: &dyn std::fmt::Debug) -> $ret {
+            $this.with_value(key, format!("{:?}", value))
+        }
+    };
+}
+
+pub(crate) use typed_field_methods;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogContext {
     pub id: String,
@@ -14,14 +266,143 @@ pub struct LogContext {
     pub line: Option<u32>,
     pub target: String,
     pub subtitle: Option<String>,
-    pub fields: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub min_level: Option<LogLevel>,
+    pub fields: IndexMap<String, FieldValue>,
     pub parent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<Vec<String>>,
+    // Presentation only, so never part of serialized records
+    #[serde(skip)]
+    pub style: Option<Style>,
+    #[serde(skip)]
+    pub closes_scope: bool,
+}
+
+/// Call site of a record, captured by the logging macros with [`location!`](crate::location).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub module: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+}
+
+impl Location {
+    pub const fn new(module: &'static str, file: &'static str, line: u32) -> Self {
+        Self { module, file, line }
+    }
+}
+
+/// Source of context ids, and so of the `parent_id`s that link records.
+///
+/// The default is random UUIDs; plug in ULIDs, snowflake IDs or ids from a tracing
+/// system with [`LogConfig::with_id_gen`](crate::LogConfig::with_id_gen). Closures
+/// returning a `String` work too.
+pub trait IdGen: Send + Sync {
+    fn next_id(&self) -> String;
+
+    // Shown by `LogConfig::summary`
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    /// Whether every id is distinct and non-empty, which deferred scopes rely on;
+    /// generators returning empty or repeated ids return false.
+    fn unique(&self) -> bool {
+        true
+    }
+}
+
+impl<F> IdGen for F
+where
+    F: Fn() -> String + Send + Sync,
+{
+    fn next_id(&self) -> String {
+        self()
+    }
+}
+
+impl std::fmt::Debug for dyn IdGen + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IdGen({})", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UuidIdGen;
+
+impl IdGen for UuidIdGen {
+    fn name(&self) -> &'static str {
+        "uuid"
+    }
+
+    fn next_id(&self) -> String {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// Ids counting up from 1, so tests get the same ids on every run.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::SequentialIdGen;
+///
+/// let logger = LogFlow::new().with_id_gen(SequentialIdGen::new()).build().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct SequentialIdGen {
+    next: AtomicU64,
+}
+
+impl SequentialIdGen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdGen for SequentialIdGen {
+    fn name(&self) -> &'static str {
+        "sequential"
+    }
+
+    fn next_id(&self) -> String {
+        (self.next.fetch_add(1, Ordering::Relaxed) + 1).to_string()
+    }
+}
+
+/// Empty ids, skipping id generation on hot paths when no output shows them; nested
+/// records are still indented, but their `parent_id`s no longer link them.
+///
+/// Deferred scopes find their records by id, so a logger with
+/// [`LogConfig::with_deferred_scopes`](crate::LogConfig::with_deferred_scopes) fails
+/// to build with this generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoIdGen;
+
+impl IdGen for NoIdGen {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn unique(&self) -> bool {
+        false
+    }
+
+    fn next_id(&self) -> String {
+        String::new()
+    }
 }
 
 impl LogContext {
     pub fn new(target: String) -> Self {
+        Self::new_with_id(target, Uuid::new_v4().to_string())
+    }
+
+    pub fn new_with_id(target: String, id: String) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id,
             timestamp: Utc::now(),
             level: 0,
             module: None,
@@ -29,8 +410,13 @@ impl LogContext {
             line: None,
             target,
             subtitle: None,
-            fields: HashMap::new(),
+            scope: None,
+            min_level: None,
+            fields: IndexMap::new(),
             parent_id: None,
+            backtrace: None,
+            style: None,
+            closes_scope: false,
         }
     }
 
@@ -50,21 +436,102 @@ impl LogContext {
         self
     }
 
-    pub fn with_field<T>(mut self, key: &str, value: T) -> Self
+    pub fn with_location(self, location: &Location) -> Self {
+        self.with_module(location.module)
+            .with_file_line(location.file, location.line)
+    }
+
+    /// Adds a field, dropping it if `value` fails to serialize; see [`Self::try_with_field`].
+    pub fn with_field<T>(self, key: &str, value: T) -> Self
+    where
+        T: Serialize,
+    {
+        self.with_field_or(key, value, false)
+    }
+
+    /// Adds a field, or returns [`LogFlowError::Field`] if `value` fails to serialize.
+    pub fn try_with_field<T>(mut self, key: &str, value: T) -> Result<Self, LogFlowError>
     where
         T: Serialize,
     {
-        if let Ok(json_value) = serde_json::to_value(value) {
-            self.fields.insert(key.to_string(), json_value);
+        let value = serde_json::to_value(value).map_err(|source| LogFlowError::Field {
+            key: key.to_string(),
+            source,
+        })?;
+        self.fields.insert(key.to_string(), value.into());
+        Ok(self)
+    }
+
+    // `with_field`, keeping `SERIALIZATION_ERROR` in place of a value that fails to
+    // serialize when `placeholder` is set
+    pub(crate) fn with_field_or<T>(mut self, key: &str, value: T, placeholder: bool) -> Self
+    where
+        T: Serialize,
+    {
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.fields.insert(key.to_string(), value.into());
+            }
+            Err(_) if placeholder => {
+                self.fields
+                    .insert(key.to_string(), SERIALIZATION_ERROR.into());
+            }
+            Err(_) => {}
         }
         self
     }
 
+    /// Adds a typed field; see [`FieldValue`].
+    pub fn with_value(mut self, key: &str, value: impl Into<FieldValue>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    typed_field_methods!(consuming);
+
+    pub fn with_correlation_id(self, id: &str) -> Self {
+        self.with_field(CORRELATION_ID, id)
+    }
+
     pub fn with_subtitle(mut self, subtitle: &str) -> Self {
         self.subtitle = Some(subtitle.to_string());
         self
     }
 
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    // Marks the last record of its scope, drawn with the closing tree connector
+    pub fn closing_scope(mut self) -> Self {
+        self.closes_scope = true;
+        self
+    }
+
+    pub fn with_backtrace(mut self, frames: Vec<String>) -> Self {
+        self.backtrace = Some(frames);
+        self
+    }
+
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scope = Some(scope.to_string());
+        self
+    }
+
+    pub fn with_timestamp<T>(mut self, timestamp: T) -> Self
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        self.timestamp = timestamp.into();
+        self
+    }
+
+    pub fn with_min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
     pub fn with_parent(mut self, parent_id: String) -> Self {
         self.parent_id = Some(parent_id);
         self.level = self.parent_id.as_ref().map_or(0, |_| self.level + 1);
@@ -72,15 +539,29 @@ impl LogContext {
     }
 
     pub fn child(&self, target: String) -> Self {
-        LogContext::new(target)
-            .with_level(self.level + 1)
+        self.child_with_id(target, Uuid::new_v4().to_string())
+    }
+
+    pub fn child_with_id(&self, target: String, id: String) -> Self {
+        let mut child = LogContext::new_with_id(target, id)
             .with_parent(self.id.clone())
+            .with_level(self.level + 1);
+        child.scope = self.scope.clone();
+        child.min_level = self.min_level;
+        if let Some(id) = self.fields.get(CORRELATION_ID) {
+            child.fields.insert(CORRELATION_ID.to_string(), id.clone());
+        }
+        child
     }
 
-    pub fn get_field(&self, key: &str) -> Option<&serde_json::Value> {
+    pub fn get_field(&self, key: &str) -> Option<&FieldValue> {
         self.fields.get(key)
     }
 
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.fields.get(CORRELATION_ID).and_then(FieldValue::as_str)
+    }
+
     pub fn is_nested(&self) -> bool {
         self.parent_id.is_some()
     }
@@ -90,53 +571,148 @@ impl LogContext {
     }
 }
 
+static NEXT_STACK_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Per-thread overlays installed by `ContextStack::enter`, keyed by stack id
+    static OVERLAYS: RefCell<HashMap<usize, Vec<LogContext>>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContextSnapshot {
+    contexts: Vec<LogContext>,
+}
+
+impl ContextSnapshot {
+    pub fn current(&self) -> Option<&LogContext> {
+        self.contexts.last()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.contexts.len()
+    }
+}
+
+pub struct ContextGuard {
+    stack_id: usize,
+    previous: Option<Vec<LogContext>>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        OVERLAYS.with(|overlays| {
+            let mut overlays = overlays.borrow_mut();
+            match previous {
+                Some(contexts) => overlays.insert(self.stack_id, contexts),
+                None => overlays.remove(&self.stack_id),
+            };
+        });
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ContextStack {
+    id: usize,
     contexts: Arc<std::sync::Mutex<Vec<LogContext>>>,
+    // Every thread gets its own stack instead of sharing `contexts`
+    per_thread: bool,
 }
 
 impl ContextStack {
     pub fn new() -> Self {
         Self {
+            id: NEXT_STACK_ID.fetch_add(1, Ordering::Relaxed),
             contexts: Arc::new(std::sync::Mutex::new(Vec::new())),
+            per_thread: false,
         }
     }
 
+    pub fn per_thread() -> Self {
+        let mut stack = Self::new();
+        stack.per_thread = true;
+        stack
+    }
+
+    // The stack stays usable after a panic on another thread; the poison is reported and cleared
+    fn lock(&self) -> MutexGuard<'_, Vec<LogContext>> {
+        self.contexts.lock().unwrap_or_else(|poisoned| {
+            diagnostics::report(&LogFlowError::Poisoned("context stack".to_string()));
+            self.contexts.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    // Per-thread stacks live in the overlay map too, created on first use
+    fn with_overlay<R>(&self, f: impl FnOnce(&mut Vec<LogContext>) -> R) -> Option<R> {
+        OVERLAYS.with(|overlays| {
+            let mut overlays = overlays.borrow_mut();
+            if self.per_thread {
+                return Some(f(overlays.entry(self.id).or_default()));
+            }
+            overlays.get_mut(&self.id).map(f)
+        })
+    }
+
     pub fn push(&self, context: LogContext) {
-        if let Ok(mut contexts) = self.contexts.lock() {
-            contexts.push(context);
+        let mut context = Some(context);
+        if self
+            .with_overlay(|contexts| contexts.extend(context.take()))
+            .is_some()
+        {
+            return;
         }
+
+        self.lock().extend(context);
     }
 
     pub fn pop(&self) -> Option<LogContext> {
-        if let Ok(mut contexts) = self.contexts.lock() {
-            contexts.pop()
-        } else {
-            None
+        if let Some(popped) = self.with_overlay(|contexts| contexts.pop()) {
+            return popped;
         }
+
+        self.lock().pop()
     }
 
     pub fn current(&self) -> Option<LogContext> {
-        if let Ok(contexts) = self.contexts.lock() {
-            contexts.last().cloned()
-        } else {
-            None
+        if let Some(current) = self.with_overlay(|contexts| contexts.last().cloned()) {
+            return current;
         }
+
+        self.lock().last().cloned()
     }
 
     pub fn is_empty(&self) -> bool {
-        if let Ok(contexts) = self.contexts.lock() {
-            contexts.is_empty()
-        } else {
-            true
-        }
+        self.depth() == 0
     }
 
     pub fn depth(&self) -> usize {
-        if let Ok(contexts) = self.contexts.lock() {
-            contexts.len()
-        } else {
-            0
+        if let Some(depth) = self.with_overlay(|contexts| contexts.len()) {
+            return depth;
+        }
+
+        self.lock().len()
+    }
+
+    pub fn snapshot(&self) -> ContextSnapshot {
+        if let Some(contexts) = self.with_overlay(|contexts| contexts.clone()) {
+            return ContextSnapshot { contexts };
+        }
+
+        let contexts = self.lock().clone();
+        ContextSnapshot { contexts }
+    }
+
+    // Scopes opened on this thread while the guard is alive stay local to it
+    pub fn enter(&self, snapshot: ContextSnapshot) -> ContextGuard {
+        let previous =
+            OVERLAYS.with(|overlays| overlays.borrow_mut().insert(self.id, snapshot.contexts));
+
+        ContextGuard {
+            stack_id: self.id,
+            previous,
+            _not_send: PhantomData,
         }
     }
 }
@@ -146,3 +722,12 @@ impl Default for ContextStack {
         Self::new()
     }
 }
+
+// Other threads' overlays are freed with their thread-locals when those threads exit
+impl Drop for ContextStack {
+    fn drop(&mut self) {
+        if Arc::strong_count(&self.contexts) == 1 {
+            let _ = OVERLAYS.try_with(|overlays| overlays.borrow_mut().remove(&self.id));
+        }
+    }
+}
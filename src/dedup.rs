@@ -0,0 +1,98 @@
+use crate::level::LogLevel;
+use crate::output::Record;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Repeats collapsed since the last summary, reported as
+/// "Last message repeated N times" at the repeated record's level.
+#[derive(Debug, Clone, Copy)]
+pub struct Repeated {
+    pub level: LogLevel,
+    pub primary: bool,
+    pub count: u64,
+}
+
+impl Repeated {
+    pub fn message(&self) -> String {
+        format!("Last message repeated {} times", self.count)
+    }
+}
+
+struct Last {
+    level: LogLevel,
+    target: String,
+    message: String,
+    primary: bool,
+    count: u64,
+    since: Instant,
+}
+
+impl Last {
+    fn take(&mut self) -> Option<Repeated> {
+        let count = std::mem::take(&mut self.count);
+        (count > 0).then_some(Repeated {
+            level: self.level,
+            primary: self.primary,
+            count,
+        })
+    }
+}
+
+/// Collapses consecutive identical records (same level, target and message), the way
+/// syslog does.
+///
+/// The count is written when a different record arrives, when the logger is flushed,
+/// and every `window` while the repeats continue.
+pub struct Deduplicator {
+    window: Option<Duration>,
+    last: Mutex<Option<Last>>,
+}
+
+impl Deduplicator {
+    pub fn new(window: Option<Duration>) -> Self {
+        Self {
+            window,
+            last: Mutex::new(None),
+        }
+    }
+
+    // Whether to write the record, plus a summary to write before it
+    pub fn check(&self, record: &Record, primary: bool) -> (bool, Option<Repeated>) {
+        let Some(window) = self.window else {
+            return (true, None);
+        };
+        let Ok(mut last) = self.last.lock() else {
+            return (true, None);
+        };
+
+        if let Some(last) = last.as_mut() {
+            if last.level == record.level
+                && last.message == record.message
+                && last.target == record.context.target
+            {
+                last.count += 1;
+                if last.since.elapsed() < window {
+                    return (false, None);
+                }
+                last.since = Instant::now();
+                return (false, last.take());
+            }
+        }
+
+        let summary = last.as_mut().and_then(Last::take);
+        *last = Some(Last {
+            level: record.level,
+            target: record.context.target.clone(),
+            message: record.message.clone(),
+            primary,
+            count: 0,
+            since: Instant::now(),
+        });
+        (true, summary)
+    }
+
+    // Repeats not reported yet, e.g. before a flush
+    pub fn take_pending(&self) -> Option<Repeated> {
+        self.last.lock().ok()?.as_mut().and_then(Last::take)
+    }
+}
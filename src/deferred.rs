@@ -0,0 +1,92 @@
+use crate::context::LogContext;
+use crate::output::Record;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+// Records of open deferred scopes, held back until the outermost one closes so each
+// scope is written as one block instead of interleaving with other threads
+#[derive(Default)]
+pub(crate) struct DeferredScopes {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    // Open scope id -> id of the outermost deferred scope around it
+    roots: HashMap<String, String>,
+    blocks: HashMap<String, Block>,
+}
+
+pub(crate) struct Block {
+    pub name: String,
+    pub context: LogContext,
+    pub records: Vec<(Record, bool)>,
+}
+
+impl DeferredScopes {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub(crate) fn open(&self, name: &str, context: &LogContext) {
+        let mut state = self.state();
+        let root = context
+            .parent_id
+            .as_ref()
+            .and_then(|parent| state.roots.get(parent).cloned());
+        let root = match root {
+            Some(root) => root,
+            None => {
+                state.blocks.insert(
+                    context.id.clone(),
+                    Block {
+                        name: name.to_string(),
+                        context: context.clone(),
+                        records: Vec::new(),
+                    },
+                );
+                context.id.clone()
+            }
+        };
+        state.roots.insert(context.id.clone(), root);
+    }
+
+    // Keeps records of open deferred scopes and returns the rest
+    pub(crate) fn defer(&self, records: Vec<(Record, bool)>) -> Vec<(Record, bool)> {
+        let mut state = self.state();
+        if state.roots.is_empty() {
+            return records;
+        }
+
+        let mut rest = Vec::with_capacity(records.len());
+        for (record, primary) in records {
+            let context = &record.context;
+            let root = state
+                .roots
+                .get(&context.id)
+                .or_else(|| {
+                    context
+                        .parent_id
+                        .as_ref()
+                        .and_then(|id| state.roots.get(id))
+                })
+                .cloned();
+            match root.and_then(|root| state.blocks.get_mut(&root)) {
+                Some(block) => block.records.push((record, primary)),
+                None => rest.push((record, primary)),
+            }
+        }
+        rest
+    }
+
+    // The block of `id` once the outermost scope closes
+    pub(crate) fn close(&self, id: &str) -> Option<Block> {
+        let mut state = self.state();
+        match state.roots.remove(id) {
+            Some(root) if root == id => state.blocks.remove(id),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::logger::LogFlowError;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
+
+type ErrorHandler = Arc<dyn Fn(&LogFlowError) + Send + Sync>;
+
+static ERROR_HANDLER: Lazy<RwLock<Option<ErrorHandler>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn set_error_handler<F>(handler: F)
+where
+    F: Fn(&LogFlowError) + Send + Sync + 'static,
+{
+    if let Ok(mut current) = ERROR_HANDLER.write() {
+        *current = Some(Arc::new(handler));
+    }
+}
+
+pub fn clear_error_handler() {
+    if let Ok(mut current) = ERROR_HANDLER.write() {
+        *current = None;
+    }
+}
+
+pub fn report(error: &LogFlowError) {
+    let handler = ERROR_HANDLER
+        .read()
+        .ok()
+        .and_then(|current| current.clone());
+
+    match handler {
+        Some(handler) => handler(error),
+        None => eprintln!("logflow: {}", error),
+    }
+}
@@ -0,0 +1,126 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Hunk {
+        old_start: usize,
+        old_len: usize,
+        new_start: usize,
+        new_len: usize,
+    },
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl std::fmt::Display for DiffLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffLine::Hunk {
+                old_start,
+                old_len,
+                new_start,
+                new_len,
+            } => write!(
+                f,
+                "@@ -{},{} +{},{} @@",
+                old_start, old_len, new_start, new_len
+            ),
+            DiffLine::Context(line) => write!(f, " {}", line),
+            DiffLine::Removed(line) => write!(f, "-{}", line),
+            DiffLine::Added(line) => write!(f, "+{}", line),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Remove,
+    Add,
+}
+
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// Line-based unified diff of `old` against `new`, with `context` unchanged lines around each hunk.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&old, &new);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Keep)
+        .map(|(index, _)| index)
+        .collect();
+
+    // Group changes whose surrounding context would overlap into one hunk
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (start, end) in ranges {
+        let old_before = ops[..start].iter().filter(|(op, _)| *op != Op::Add).count();
+        let new_before = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != Op::Remove)
+            .count();
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|(op, _)| *op != Op::Add).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != Op::Remove).count();
+
+        lines.push(DiffLine::Hunk {
+            old_start: old_before + 1,
+            old_len,
+            new_start: new_before + 1,
+            new_len,
+        });
+        lines.extend(hunk.iter().map(|(op, text)| match op {
+            Op::Keep => DiffLine::Context(text.to_string()),
+            Op::Remove => DiffLine::Removed(text.to_string()),
+            Op::Add => DiffLine::Added(text.to_string()),
+        }));
+    }
+
+    lines
+}
+
+// Longest-common-subsequence edit script; quadratic, which is fine for config-sized inputs
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let width = new.len() + 1;
+    let mut table = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i * width + j] = if old[i] == new[j] {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push((Op::Keep, old[i]));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            ops.push((Op::Remove, old[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Add, new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| (Op::Remove, *line)));
+    ops.extend(new[j..].iter().map(|line| (Op::Add, *line)));
+    ops
+}
@@ -0,0 +1,167 @@
+use crate::batch::{BatchOptions, BatchSink};
+use crate::compression::Compression;
+use crate::diagnostics;
+use crate::formatter;
+use crate::http::HttpEndpoint;
+use crate::logger::LogFlowError;
+use crate::output::Record;
+use chrono::format::{Item, StrftimeItems};
+use serde_json::{json, Value};
+use std::io;
+
+pub const BULK_PATH: &str = "/_bulk";
+
+#[derive(Debug, Clone)]
+pub struct ElasticsearchConfig {
+    pub url: String,
+    pub index: String,
+    pub pipeline: Option<String>,
+    pub api_key: Option<String>,
+    pub batch: BatchOptions,
+}
+
+impl ElasticsearchConfig {
+    /// `index` is a strftime template over the record timestamp, e.g. `app-logs-%Y.%m.%d`.
+    pub fn new(url: &str, index: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            index: index.to_string(),
+            pipeline: None,
+            api_key: None,
+            batch: BatchOptions::default(),
+        }
+    }
+
+    pub fn with_pipeline(mut self, pipeline: &str) -> Self {
+        self.pipeline = Some(pipeline.to_string());
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_batch(mut self, batch: BatchOptions) -> Self {
+        self.batch = batch;
+        self
+    }
+}
+
+/// Indexes records through the `_bulk` API of Elasticsearch or OpenSearch.
+///
+/// Documents rejected with 429 or a 5xx status are retried; any other rejection is
+/// permanent and reported through [`diagnostics::report`] without retrying.
+pub struct ElasticsearchSink {
+    endpoint: HttpEndpoint,
+    index: String,
+}
+
+impl ElasticsearchSink {
+    pub fn new(config: &ElasticsearchConfig, compression: Compression) -> io::Result<Self> {
+        if StrftimeItems::new(&config.index).any(|item| matches!(item, Item::Error)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid index template: {}", config.index),
+            ));
+        }
+
+        let path = match config.pipeline {
+            Some(ref pipeline) => format!("{}?pipeline={}", BULK_PATH, pipeline),
+            None => BULK_PATH.to_string(),
+        };
+        let mut endpoint = HttpEndpoint::parse(&config.url)?
+            .with_path(&path)
+            .with_compression(compression);
+        if let Some(ref api_key) = config.api_key {
+            endpoint = endpoint.with_header("Authorization", &format!("ApiKey {}", api_key));
+        }
+
+        Ok(Self {
+            endpoint,
+            index: config.index.clone(),
+        })
+    }
+
+    pub fn index_name(&self, record: &Record) -> String {
+        record.context.timestamp.format(&self.index).to_string()
+    }
+
+    pub fn encode(&self, records: &[Record]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for record in records {
+            let action = json!({ "index": { "_index": self.index_name(record) } });
+            let mut document =
+                formatter::json_record(record.level, &record.message, &record.context);
+            document["@timestamp"] = Value::String(record.context.timestamp.to_rfc3339());
+
+            for line in [action, document] {
+                if serde_json::to_writer(&mut body, &line).is_ok() {
+                    body.push(b'\n');
+                }
+            }
+        }
+        body
+    }
+}
+
+impl BatchSink for ElasticsearchSink {
+    fn send(&self, records: &[Record]) -> io::Result<()> {
+        match self.send_partial(records)?.len() {
+            0 => Ok(()),
+            count => Err(io::Error::other(format!(
+                "{} documents were not indexed",
+                count
+            ))),
+        }
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.endpoint.probe()
+    }
+
+    fn send_partial(&self, records: &[Record]) -> io::Result<Vec<usize>> {
+        let response = self
+            .endpoint
+            .post("application/x-ndjson", &[], &self.encode(records))?
+            .into_result()?;
+        let response: Value = serde_json::from_slice(&response.body).map_err(io::Error::other)?;
+
+        if response["errors"] != Value::Bool(true) {
+            return Ok(Vec::new());
+        }
+
+        let mut retry = Vec::new();
+        let mut rejected = 0;
+        let mut reason = None;
+        let items = response["items"].as_array().map_or(&[][..], Vec::as_slice);
+        for (index, item) in items.iter().enumerate() {
+            let result = item.as_object().and_then(|item| item.values().next());
+            let Some(result) = result else {
+                continue;
+            };
+            match result["status"].as_u64().unwrap_or(0) {
+                200..=299 => {}
+                429 | 500..=599 => retry.push(index),
+                _ => {
+                    rejected += 1;
+                    reason.get_or_insert_with(|| result["error"].to_string());
+                }
+            }
+        }
+
+        if rejected > 0 {
+            diagnostics::report(&LogFlowError::Output(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Elasticsearch rejected {} of {} documents: {}",
+                    rejected,
+                    records.len(),
+                    reason.unwrap_or_default()
+                ),
+            )));
+        }
+
+        Ok(retry)
+    }
+}
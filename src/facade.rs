@@ -0,0 +1,242 @@
+use crate::context::{FieldValue, Location};
+use crate::level::LogLevel;
+use crate::logger::{LogFlow, LogFlowError};
+use crate::memory::MemorySink;
+use crate::output::Record;
+use crate::testkit::Matcher;
+
+type Result<T> = std::result::Result<T, LogFlowError>;
+
+/// Object-safe logging facade, so libraries can accept `&dyn Log` instead of a
+/// concrete logger type.
+///
+/// Implemented by [`LogFlow`] and its scopes and field loggers, `AsyncLogFlow` (through
+/// a blocking shim), [`CaptureLogger`] and [`NoopLogger`]. On a current-thread tokio
+/// runtime the shim cannot block, so `AsyncLogFlow` records are queued as tasks and
+/// written once the caller yields.
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::{CaptureLogger, Log};
+///
+/// fn sync_users(log: &dyn Log) {
+///     let scope = log.begin_scope("sync");
+///     scope.with_field("count", 3.into()).info("synced users").unwrap();
+/// }
+///
+/// let capture = CaptureLogger::new();
+/// sync_users(&capture);
+/// assert_eq!(capture.messages(), vec!["synced users"]);
+/// ```
+pub trait Log: Send + Sync {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()>;
+
+    // Whether a record at `level` would be written; loggers that can't tell say yes
+    fn enabled(&self, _level: LogLevel) -> bool {
+        true
+    }
+
+    // `log` from the call site the macros captured; loggers without source locations
+    // ignore it
+    fn log_at(&self, level: LogLevel, message: &str, _location: &Location) -> Result<()> {
+        self.log(level, message)
+    }
+
+    fn enabled_at(&self, level: LogLevel, _location: &Location) -> bool {
+        self.enabled(level)
+    }
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_>;
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_>;
+}
+
+impl dyn Log + '_ {
+    pub fn trace(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Trace, message)
+    }
+
+    pub fn debug(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Debug, message)
+    }
+
+    pub fn info(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Info, message)
+    }
+
+    pub fn warn(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Warn, message)
+    }
+
+    pub fn error(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Error, message)
+    }
+
+    pub fn fatal(&self, message: &str) -> Result<()> {
+        self.log(LogLevel::Fatal, message)
+    }
+}
+
+/// Logger that discards everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopLogger;
+
+impl Log for NoopLogger {
+    fn log(&self, _level: LogLevel, _message: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn with_field(&self, _key: &str, _value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(NoopLogger)
+    }
+
+    fn begin_scope(&self, _name: &str) -> Box<dyn Log + '_> {
+        Box::new(NoopLogger)
+    }
+}
+
+/// Logger for tests that keeps every record, at every level, in memory.
+pub struct CaptureLogger {
+    logger: LogFlow,
+    sink: MemorySink,
+}
+
+impl CaptureLogger {
+    pub fn new() -> Self {
+        let sink = MemorySink::new();
+        let logger = LogFlow::new()
+            .with_level(LogLevel::Trace)
+            .with_colors(false)
+            .with_output(sink.output())
+            .build()
+            .expect("in-memory output cannot fail to open");
+
+        Self { logger, sink }
+    }
+
+    pub fn logger(&self) -> &LogFlow {
+        &self.logger
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.sink.records()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.records()
+            .into_iter()
+            .map(|record| record.message)
+            .collect()
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.records()
+            .iter()
+            .any(|record| record.message.contains(text))
+    }
+
+    pub fn clear(&self) {
+        self.sink.clear();
+    }
+
+    // Records whose field `key` equals `value`, e.g. `records_with_field("user_id", 42)`
+    pub fn records_with_field<T: serde::Serialize>(&self, key: &str, value: T) -> Vec<Record> {
+        let Ok(value) = serde_json::to_value(value).map(FieldValue::from) else {
+            return Vec::new();
+        };
+        self.records()
+            .into_iter()
+            .filter(|record| record.context.fields.get(key) == Some(&value))
+            .collect()
+    }
+
+    /// Panics unless a record at `level` has a message matching `matcher`, listing the
+    /// captured records.
+    ///
+    /// ```
+    /// # if !logflow::LOGGING_ENABLED { return; }
+    /// use logflow::prelude::*;
+    /// use logflow::test::{contains, TestLogger};
+    ///
+    /// let capture = TestLogger::new();
+    /// capture.logger().warn("retry 1 of 3").unwrap();
+    /// capture.assert_logged(LogLevel::Warn, contains("retry"));
+    /// capture.assert_not_logged(LogLevel::Error, contains("retry"));
+    /// ```
+    #[track_caller]
+    pub fn assert_logged(&self, level: LogLevel, matcher: Matcher) {
+        let records = self.records();
+        if !records
+            .iter()
+            .any(|record| is_match(record, level, &matcher))
+        {
+            panic!(
+                "expected a record at {} with a message {}, captured:\n{}",
+                level,
+                matcher,
+                describe(&records)
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_not_logged(&self, level: LogLevel, matcher: Matcher) {
+        let records = self.records();
+        if records
+            .iter()
+            .any(|record| is_match(record, level, &matcher))
+        {
+            panic!(
+                "expected no record at {} with a message {}, captured:\n{}",
+                level,
+                matcher,
+                describe(&records)
+            );
+        }
+    }
+}
+
+fn is_match(record: &Record, level: LogLevel, matcher: &Matcher) -> bool {
+    record.level == level && matcher.matches(&record.message)
+}
+
+fn describe(records: &[Record]) -> String {
+    if records.is_empty() {
+        return "  (nothing)".to_string();
+    }
+    records
+        .iter()
+        .map(|record| format!("  {} {}", record.level, record.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Default for CaptureLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Log for CaptureLogger {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        Log::log(&self.logger, level, message)
+    }
+
+    fn enabled(&self, level: LogLevel) -> bool {
+        self.logger.enabled(level)
+    }
+
+    fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        self.logger.log_at(level, message, location)
+    }
+
+    fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        self.logger.enabled_at(level, location)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Log::with_field(&self.logger, key, value)
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Log::begin_scope(&self.logger, name)
+    }
+}
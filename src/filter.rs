@@ -0,0 +1,122 @@
+use crate::level::LogLevel;
+use crate::logger::LogFlowError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    target: Option<String>,
+    // None turns the target off
+    level: Option<LogLevel>,
+}
+
+impl Directive {
+    fn matches(&self, target: &str) -> bool {
+        match &self.target {
+            None => true,
+            Some(prefix) => {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            }
+        }
+    }
+}
+
+/// Per-target levels in `RUST_LOG` syntax, e.g. `info,my_app::db=debug,hyper=off`.
+///
+/// A bare level sets the default, `target=level` applies to that target and its
+/// submodules (the longest matching target wins), and a bare target enables all its
+/// levels. Scope levels still take precedence over the filter.
+///
+/// ```
+/// use logflow::filter::EnvFilter;
+/// use logflow::prelude::*;
+///
+/// let filter = EnvFilter::parse("warn,logflow::logger::db=debug").unwrap();
+/// assert_eq!(filter.level_for("logflow::logger::db::pool"), Some(LogLevel::Debug));
+/// assert_eq!(filter.level_for("logflow::logger"), Some(LogLevel::Warn));
+///
+/// let logger = LogFlow::new().with_env_filter("RUST_LOG").build().unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvFilter {
+    // Sorted by target length, longest first, so the first match is the most specific
+    directives: Vec<Directive>,
+}
+
+impl EnvFilter {
+    pub fn parse(spec: &str) -> Result<Self, LogFlowError> {
+        let mut directives = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (target, level) = match directive.split_once('=') {
+                Some((target, level)) => (Some(target.trim()), Some(level.trim())),
+                None if parse_level(directive).is_some() => (None, Some(directive)),
+                None => (Some(directive), None),
+            };
+            let level = match level {
+                Some(level) => parse_level(level).ok_or_else(|| {
+                    LogFlowError::Config(format!(
+                        "Invalid level in filter directive: {}",
+                        directive
+                    ))
+                })?,
+                None => Some(LogLevel::Trace),
+            };
+            if target.is_some_and(str::is_empty) {
+                return Err(LogFlowError::Config(format!(
+                    "Missing target in filter directive: {}",
+                    directive
+                )));
+            }
+
+            directives.push(Directive {
+                target: target.map(str::to_string),
+                level,
+            });
+        }
+
+        // Later directives for the same target win, as in env_logger
+        directives.reverse();
+        let mut seen = Vec::new();
+        directives.retain(|d| {
+            let fresh = !seen.contains(&d.target);
+            seen.push(d.target.clone());
+            fresh
+        });
+        directives.sort_by_key(|d| std::cmp::Reverse(d.target.as_ref().map_or(0, String::len)));
+        Ok(Self { directives })
+    }
+
+    // An unset variable gives an empty filter
+    pub fn from_env(var: &str) -> Result<Self, LogFlowError> {
+        match std::env::var(var) {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn directive_for(&self, target: &str) -> Option<&Directive> {
+        self.directives.iter().find(|d| d.matches(target))
+    }
+
+    // None when no directive applies or the target is turned off
+    pub fn level_for(&self, target: &str) -> Option<LogLevel> {
+        self.directive_for(target).and_then(|d| d.level)
+    }
+
+    pub fn disables(&self, target: &str) -> bool {
+        self.directive_for(target)
+            .is_some_and(|d| d.level.is_none())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directives.is_empty()
+    }
+}
+
+fn parse_level(level: &str) -> Option<Option<LogLevel>> {
+    if level.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    LogLevel::from_str(level).map(Some)
+}
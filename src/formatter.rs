@@ -1,24 +1,924 @@
+use crate::ansi;
+use crate::color::{self, Color, ColorDepth, ColorMode};
 use crate::config::LogConfig;
-use crate::context::LogContext;
+use crate::context::{FieldValue, LogContext};
+use crate::deferred::Block;
+use crate::diff::{unified_diff, DiffLine, DEFAULT_CONTEXT};
 use crate::level::LogLevel;
+use crate::output::Record;
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use owo_colors::OwoColorize;
 use serde_json;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum FormatterType {
     Pretty,
     Compact,
     Json,
-    Custom(fn(&LogLevel, &str, &LogContext, &LogConfig) -> String),
+    Logfmt,
+    Gelf,
+    Ecs,
+    /// GitHub Actions workflow commands: warnings and errors become `::warning` and
+    /// `::error` annotations, debug records `::debug`, and top-level scopes groups.
+    GitHub,
+    /// Plain lines with scopes as collapsible GitLab CI sections.
+    GitLab,
+    /// TeamCity `##teamcity[message ...]` service messages, with scopes as blocks.
+    TeamCity,
+    Custom(Arc<dyn FormatRecord>),
+}
+
+impl FormatterType {
+    pub fn custom<F: FormatRecord + 'static>(formatter: F) -> Self {
+        FormatterType::Custom(Arc::new(formatter))
+    }
+
+    /// The CI format for the CI system the process runs under, from `GITHUB_ACTIONS`,
+    /// `GITLAB_CI` or `TEAMCITY_VERSION`.
+    pub fn detect_ci() -> Option<Self> {
+        let set = |var: &str| std::env::var_os(var).is_some_and(|value| !value.is_empty());
+        if std::env::var("GITHUB_ACTIONS").as_deref() == Ok("true") {
+            Some(FormatterType::GitHub)
+        } else if set("GITLAB_CI") {
+            Some(FormatterType::GitLab)
+        } else if set("TEAMCITY_VERSION") {
+            Some(FormatterType::TeamCity)
+        } else {
+            None
+        }
+    }
+}
+
+// How scopes fold in the CI system's log view
+#[derive(Debug, Clone, Copy)]
+enum CiGroups {
+    GitHub,
+    GitLab,
+    TeamCity,
+}
+
+impl CiGroups {
+    fn of(formatter: &FormatterType) -> Option<Self> {
+        match formatter {
+            FormatterType::GitHub => Some(CiGroups::GitHub),
+            FormatterType::GitLab => Some(CiGroups::GitLab),
+            FormatterType::TeamCity => Some(CiGroups::TeamCity),
+            _ => None,
+        }
+    }
+
+    fn resolve(config: &LogConfig) -> Option<Self> {
+        match (config.ci_groups, &config.formatter) {
+            (Some(false), _) => None,
+            (_, FormatterType::GitHub | FormatterType::GitLab | FormatterType::TeamCity) => {
+                Self::of(&config.formatter)
+            }
+            (None, FormatterType::Pretty | FormatterType::Compact) | (Some(true), _) => {
+                FormatterType::detect_ci().as_ref().and_then(Self::of)
+            }
+            (None, _) => None,
+        }
+    }
+}
+
+/// Formats a record into a single line for [`FormatterType::Custom`].
+///
+/// Implemented for closures, so a formatter can capture templates, lookup tables
+/// or counters:
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::FormatterType;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let seq = AtomicUsize::new(0);
+/// let logger = LogFlow::new()
+///     .with_formatter(FormatterType::custom(
+///         move |level: &LogLevel, message: &str, _: &LogContext, _: &LogConfig| {
+///             format!("#{} {} {}", seq.fetch_add(1, Ordering::Relaxed), level, message)
+///         },
+///     ))
+///     .build()
+///     .unwrap();
+/// ```
+pub trait FormatRecord: Send + Sync {
+    fn format(
+        &self,
+        level: &LogLevel,
+        message: &str,
+        context: &LogContext,
+        config: &LogConfig,
+    ) -> String;
+}
+
+impl<F> FormatRecord for F
+where
+    F: Fn(&LogLevel, &str, &LogContext, &LogConfig) -> String + Send + Sync,
+{
+    fn format(
+        &self,
+        level: &LogLevel,
+        message: &str,
+        context: &LogContext,
+        config: &LogConfig,
+    ) -> String {
+        self(level, message, context, config)
+    }
+}
+
+impl std::fmt::Debug for dyn FormatRecord + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FormatRecord")
+    }
+}
+
+/// Colors and emphasis for a single record's message, e.g. to make milestones stand
+/// out; replaces the level color in the Pretty and Compact formatters and is ignored
+/// by JSON output.
+///
+/// ```
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// logger.styled(Style::new().magenta().bold()).info("Phase 2 complete").unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Style {
+    base: owo_colors::Style,
+    // RGB and palette colors, resolved against the terminal's color depth when painting
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+macro_rules! style_methods {
+    ($clear:ident: $($method:ident),* $(,)?) => {
+        $(
+            pub fn $method(self) -> Self {
+                Self {
+                    base: self.base.$method(),
+                    $clear: None,
+                    ..self
+                }
+            }
+        )*
+    };
+    ($($method:ident),* $(,)?) => {
+        $(
+            pub fn $method(self) -> Self {
+                Self {
+                    base: self.base.$method(),
+                    ..self
+                }
+            }
+        )*
+    };
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    style_methods!(
+        fg: black,
+        red,
+        green,
+        yellow,
+        blue,
+        magenta,
+        cyan,
+        white,
+        bright_black,
+        bright_red,
+        bright_green,
+        bright_yellow,
+        bright_blue,
+        bright_magenta,
+        bright_cyan,
+        bright_white,
+    );
+
+    style_methods!(
+        bg: on_black,
+        on_red,
+        on_green,
+        on_yellow,
+        on_blue,
+        on_magenta,
+        on_cyan,
+        on_white,
+    );
+
+    style_methods!(bold, dimmed, italic, underline, reversed);
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn on_color(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn truecolor(self, r: u8, g: u8, b: u8) -> Self {
+        self.color(Color::Rgb(r, g, b))
+    }
+
+    pub fn on_truecolor(self, r: u8, g: u8, b: u8) -> Self {
+        self.on_color(Color::Rgb(r, g, b))
+    }
+
+    pub fn ansi256(self, index: u8) -> Self {
+        self.color(Color::Ansi256(index))
+    }
+
+    pub fn on_ansi256(self, index: u8) -> Self {
+        self.on_color(Color::Ansi256(index))
+    }
+
+    fn paint(&self, text: &str, depth: ColorDepth) -> String {
+        let mut style = self.base;
+        if let Some(fg) = self.fg {
+            style = style.color(fg.resolve(depth));
+        }
+        if let Some(bg) = self.bg {
+            style = style.on_color(bg.resolve(depth));
+        }
+        text.style(style).to_string()
+    }
+}
+
+/// Colors of each level in the Pretty formatter: one style for the level tag and
+/// subtitle, one for the message.
+///
+/// Custom levels use the style they were registered with, or their base level's.
+/// Icons in front of the level tag are off until `with_icons` or `with_icon`; each
+/// has an ASCII fallback for terminals without Unicode, see
+/// [`LogConfig::with_unicode`](crate::LogConfig::with_unicode).
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::Theme;
+///
+/// let theme = Theme::light()
+///     .with_level(LogLevel::Info, Style::new().blue().bold())
+///     .with_icons()
+///     .with_icon(LogLevel::Info, "🚀", ">");
+/// let logger = LogFlow::new().with_theme(theme).build().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    name: String,
+    // Indexed like `LogLevel::all()`
+    tags: [Style; 6],
+    messages: [Style; 6],
+    // Glyph and ASCII fallback
+    icons: Option<[(String, String); 6]>,
+}
+
+impl Theme {
+    /// The default, for dark terminal backgrounds.
+    pub fn dark() -> Self {
+        let s = Style::new();
+        Self {
+            name: "dark".to_string(),
+            tags: [
+                s.magenta(),
+                s.blue(),
+                s.green(),
+                s.yellow(),
+                s.red(),
+                s.on_red().white().bold(),
+            ],
+            messages: [
+                s.magenta(),
+                s.blue(),
+                s.white(),
+                s.yellow(),
+                s.red(),
+                s.red(),
+            ],
+            icons: None,
+        }
+    }
+
+    pub fn light() -> Self {
+        let s = Style::new();
+        Self {
+            name: "light".to_string(),
+            tags: [
+                s.bright_black(),
+                s.blue(),
+                s.green(),
+                s.magenta(),
+                s.red(),
+                s.on_red().white().bold(),
+            ],
+            messages: [
+                s.bright_black(),
+                s.blue(),
+                s.black(),
+                s.magenta(),
+                s.red(),
+                s.red(),
+            ],
+            icons: None,
+        }
+    }
+
+    pub fn solarized() -> Self {
+        let s = Style::new();
+        let base01 = s.truecolor(88, 110, 117);
+        let blue = s.truecolor(38, 139, 210);
+        let green = s.truecolor(133, 153, 0);
+        let yellow = s.truecolor(181, 137, 0);
+        let red = s.truecolor(220, 50, 47);
+        Self {
+            name: "solarized".to_string(),
+            tags: [
+                base01,
+                blue,
+                green,
+                yellow,
+                red,
+                s.on_truecolor(211, 54, 130).truecolor(253, 246, 227).bold(),
+            ],
+            messages: [base01, blue, s.truecolor(131, 148, 150), yellow, red, red],
+            icons: None,
+        }
+    }
+
+    /// Okabe-Ito colors, telling levels apart without relying on red and green.
+    pub fn colorblind() -> Self {
+        let s = Style::new();
+        let sky = s.truecolor(86, 180, 233);
+        let blue = s.truecolor(0, 114, 178);
+        let orange = s.truecolor(230, 159, 0);
+        let vermillion = s.truecolor(213, 94, 0);
+        Self {
+            name: "colorblind".to_string(),
+            tags: [
+                s.bright_black(),
+                sky,
+                blue,
+                orange.bold(),
+                vermillion.bold(),
+                s.on_truecolor(213, 94, 0).white().bold(),
+            ],
+            messages: [s.bright_black(), sky, s, orange, vermillion, vermillion],
+            icons: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Built-in levels only; custom levels take their style from their definition
+    pub fn with_level(mut self, level: LogLevel, style: Style) -> Self {
+        if let Some(index) = builtin_index(level) {
+            self.tags[index] = style;
+        }
+        self
+    }
+
+    pub fn with_message(mut self, level: LogLevel, style: Style) -> Self {
+        if let Some(index) = builtin_index(level) {
+            self.messages[index] = style;
+        }
+        self
+    }
+
+    /// Shows an icon in front of each level tag: `›`, `•`, `✔`, `⚠`, `✖` and `☠`.
+    pub fn with_icons(mut self) -> Self {
+        if self.icons.is_none() {
+            let icons = [
+                ("›", ">"),
+                ("•", "*"),
+                ("✔", "+"),
+                ("⚠", "!"),
+                ("✖", "x"),
+                ("☠", "X"),
+            ];
+            self.icons = Some(icons.map(|(glyph, ascii)| (glyph.to_string(), ascii.to_string())));
+        }
+        self
+    }
+
+    // Built-in levels only; custom levels show their base level's icon
+    pub fn with_icon(mut self, level: LogLevel, glyph: &str, ascii: &str) -> Self {
+        if let Some(index) = builtin_index(level) {
+            self = self.with_icons();
+            if let Some(icons) = self.icons.as_mut() {
+                icons[index] = (glyph.to_string(), ascii.to_string());
+            }
+        }
+        self
+    }
+
+    pub fn icon(&self, level: LogLevel, unicode: bool) -> Option<&str> {
+        let (glyph, ascii) = &self.icons.as_ref()?[base_index(level)];
+        Some(if unicode { glyph } else { ascii })
+    }
+
+    // Columns of the widest icon, so narrower ones can be padded to line up
+    fn icon_width(&self, unicode: bool) -> usize {
+        self.icons
+            .iter()
+            .flatten()
+            .map(|(glyph, ascii)| ansi::visible_width(if unicode { glyph } else { ascii }))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn level_style(&self, level: LogLevel) -> Style {
+        level
+            .custom_style()
+            .unwrap_or_else(|| self.tags[base_index(level)])
+    }
+
+    pub fn message_style(&self, level: LogLevel) -> Style {
+        level
+            .custom_style()
+            .unwrap_or_else(|| self.messages[base_index(level)])
+    }
+
+    pub fn paint_level(&self, level: LogLevel, text: &str, depth: ColorDepth) -> String {
+        self.level_style(level).paint(text, depth)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+// Widest level tag, custom levels included
+fn level_column() -> usize {
+    LogLevel::all()
+        .iter()
+        .copied()
+        .chain(LogLevel::custom_levels())
+        .map(|level| ansi::visible_width(level.short_name()))
+        .max()
+        .unwrap_or(0)
+}
+
+fn builtin_index(level: LogLevel) -> Option<usize> {
+    LogLevel::all().iter().position(|builtin| *builtin == level)
+}
+
+fn base_index(level: LogLevel) -> usize {
+    builtin_index(level.base_level()).unwrap_or(0)
+}
+
+/// What the Pretty formatter does with a line wider than `max_width`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Cut the line and end it with `...`.
+    #[default]
+    Truncate,
+    /// Continue on the next lines, aligned under the message.
+    Wrap,
+}
+
+/// Guides drawn in front of records inside nested scopes.
+///
+/// With `tree` on, the default, a record gets a `├─` connector under its scope and
+/// the record that closes a scope, such as its `finished` timing record, gets `└─`;
+/// `marker` is the vertical guide for the enclosing scopes.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::IndentStyle;
+///
+/// let style = IndentStyle::unicode()
+///     .with_connectors("+-", "\\-")
+///     .with_color(Style::new().blue());
+/// let logger = LogFlow::new().with_indent_style(style).build().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndentStyle {
+    pub marker: String,
+    pub bullet: String,
+    pub branch: String,
+    pub last_branch: String,
+    pub tree: bool,
+    /// Color of the guides; dimmed when unset.
+    pub color: Option<Style>,
+    pub show_scope_name: bool,
+}
+
+impl IndentStyle {
+    pub fn unicode() -> Self {
+        Self {
+            marker: "│ ".to_string(),
+            bullet: "•".to_string(),
+            branch: "├─".to_string(),
+            last_branch: "└─".to_string(),
+            tree: true,
+            color: None,
+            show_scope_name: false,
+        }
+    }
+
+    pub fn ascii() -> Self {
+        Self {
+            marker: "| ".to_string(),
+            bullet: "-".to_string(),
+            branch: "|-".to_string(),
+            last_branch: "`-".to_string(),
+            tree: true,
+            color: None,
+            show_scope_name: false,
+        }
+    }
+
+    pub fn with_marker(mut self, marker: &str) -> Self {
+        self.marker = marker.to_string();
+        self
+    }
+
+    pub fn with_bullet(mut self, bullet: &str) -> Self {
+        self.bullet = bullet.to_string();
+        self
+    }
+
+    pub fn with_connectors(mut self, branch: &str, last_branch: &str) -> Self {
+        self.branch = branch.to_string();
+        self.last_branch = last_branch.to_string();
+        self
+    }
+
+    /// Draws only the repeated `marker`, without connectors.
+    pub fn with_tree(mut self, enabled: bool) -> Self {
+        self.tree = enabled;
+        self
+    }
+
+    pub fn with_color(mut self, style: Style) -> Self {
+        self.color = Some(style);
+        self
+    }
+
+    pub fn with_scope_name(mut self, enabled: bool) -> Self {
+        self.show_scope_name = enabled;
+        self
+    }
+
+    pub fn render(&self, depth: usize, indent_size: usize) -> String {
+        let unit = format!("{:<width$}", self.marker, width = indent_size);
+        unit.repeat(depth)
+    }
+
+    // Guides for the first line of a record and for the lines under it, which keep
+    // the vertical guides but not the connector
+    pub(crate) fn render_tree(
+        &self,
+        depth: usize,
+        indent_size: usize,
+        closes_scope: bool,
+    ) -> (String, String) {
+        if !self.tree || depth == 0 {
+            let guides = self.render(depth, indent_size);
+            return (guides.clone(), guides);
+        }
+
+        let outer = self.render(depth - 1, indent_size);
+        let (connector, below) = if closes_scope {
+            (&self.last_branch, String::new())
+        } else {
+            (&self.branch, self.marker.clone())
+        };
+        let first = format!(
+            "{}{:<width$}",
+            outer,
+            format!("{} ", connector),
+            width = indent_size
+        );
+        let width = first.chars().count() - outer.chars().count();
+        let rest = format!("{}{:<width$}", outer, below, width = width);
+        (first, rest)
+    }
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+/// Timezone timestamps are shown in by the Pretty, Compact, JSON and logfmt
+/// formatters; records are always stamped in UTC.
+///
+/// ```
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new()
+///     .with_timezone(Timezone::Local)
+///     .build()
+///     .unwrap();
+/// let fixed = Timezone::fixed(5 * 3600 + 1800).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timezone {
+    #[default]
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    // None when the offset is not within a day either way
+    pub fn fixed(seconds_east: i32) -> Option<Self> {
+        FixedOffset::east_opt(seconds_east).map(Timezone::Fixed)
+    }
+
+    pub fn convert(&self, timestamp: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::Utc => timestamp.fixed_offset(),
+            Timezone::Local => timestamp.with_timezone(&Local).fixed_offset(),
+            Timezone::Fixed(offset) => timestamp.with_timezone(offset),
+        }
+    }
+}
+
+/// How the JSON and logfmt formatters write timestamps. Epoch values are integers
+/// in UTC, for pipelines that expect them and because they are cheaper to format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    #[default]
+    Rfc3339,
+    EpochSeconds,
+    EpochMillis,
+}
+
+/// Order of context fields in pretty, logfmt and CI output. JSON, ECS and GELF
+/// always write keys sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldOrder {
+    /// In the order they were added.
+    #[default]
+    Insertion,
+    /// Sorted by key.
+    Sorted,
+}
+
+/// Where the JSON formatter writes context fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonFields {
+    /// Under a `"fields"` object.
+    #[default]
+    Nested,
+    /// As top-level keys, for pipelines that only index those. Fields named like a
+    /// built-in key (`message`, `level`, `fields`, ...) stay under `"fields"`, so
+    /// they never replace it.
+    Flatten,
+}
+
+// Top-level keys of JSON records, see `schema::json_schema`
+const JSON_KEYS: &[&str] = &[
+    "schema_version",
+    "timestamp",
+    "level",
+    "message",
+    "target",
+    "id",
+    "nesting_level",
+    "subtitle",
+    "module",
+    "file",
+    "line",
+    "parent_id",
+    "backtrace",
+    "fields",
+];
+
+type RenderFn = Arc<dyn Fn(&FieldValue, bool) -> String + Send + Sync>;
+
+/// Renders a single field value in the pretty formatter.
+///
+/// The callback receives the value and whether colors are currently enabled.
+#[derive(Clone)]
+pub struct FieldRenderer {
+    render: RenderFn,
+}
+
+impl FieldRenderer {
+    pub fn new<F>(render: F) -> Self
+    where
+        F: Fn(&FieldValue, bool) -> String + Send + Sync + 'static,
+    {
+        Self {
+            render: Arc::new(render),
+        }
+    }
+
+    /// Horizontal bar scaled against `max`, turning yellow past 50% and red past 80%.
+    pub fn bar(max: f64, width: usize) -> Self {
+        Self::new(move |value, colors| {
+            let Some(number) = value.as_f64() else {
+                return value.to_string();
+            };
+
+            let ratio = if max > 0.0 {
+                (number / max).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let filled = (ratio * width as f64).round() as usize;
+            let bar = format!(
+                "{}{}",
+                "█".repeat(filled),
+                "░".repeat(width.saturating_sub(filled))
+            );
+
+            if !colors {
+                return format!("{} {}", bar, number);
+            }
+
+            let bar = if ratio > 0.8 {
+                bar.red().to_string()
+            } else if ratio > 0.5 {
+                bar.yellow().to_string()
+            } else {
+                bar.green().to_string()
+            };
+            format!("{} {}", bar, number)
+        })
+    }
+
+    /// Green when `is_ok` accepts the value, red otherwise.
+    pub fn status<F>(is_ok: F) -> Self
+    where
+        F: Fn(&FieldValue) -> bool + Send + Sync + 'static,
+    {
+        Self::new(move |value, colors| {
+            let text = value.to_text();
+
+            if !colors {
+                text
+            } else if is_ok(value) {
+                text.green().to_string()
+            } else {
+                text.red().to_string()
+            }
+        })
+    }
+
+    pub fn render(&self, value: &FieldValue, colors: bool) -> String {
+        (self.render)(value, colors)
+    }
+}
+
+impl std::fmt::Debug for FieldRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FieldRenderer")
+    }
+}
+
+#[derive(Debug)]
+pub struct DisplayToggles {
+    colors: AtomicBool,
+    timestamps: AtomicBool,
+    show_date: AtomicBool,
+    show_target: AtomicBool,
+    show_module: AtomicBool,
+    show_file_line: AtomicBool,
+}
+
+impl DisplayToggles {
+    pub fn from_config(config: &LogConfig) -> Self {
+        Self {
+            colors: AtomicBool::new(config.colors_enabled()),
+            timestamps: AtomicBool::new(config.timestamps),
+            show_date: AtomicBool::new(config.show_date),
+            show_target: AtomicBool::new(config.show_target),
+            show_module: AtomicBool::new(config.show_module),
+            show_file_line: AtomicBool::new(config.show_file_line),
+        }
+    }
+
+    pub fn colors(&self) -> bool {
+        self.colors.load(Ordering::Relaxed)
+    }
+
+    pub fn timestamps(&self) -> bool {
+        self.timestamps.load(Ordering::Relaxed)
+    }
+
+    pub fn show_date(&self) -> bool {
+        self.show_date.load(Ordering::Relaxed)
+    }
+
+    pub fn show_target(&self) -> bool {
+        self.show_target.load(Ordering::Relaxed)
+    }
+
+    pub fn show_module(&self) -> bool {
+        self.show_module.load(Ordering::Relaxed)
+    }
+
+    pub fn show_file_line(&self) -> bool {
+        self.show_file_line.load(Ordering::Relaxed)
+    }
+
+    pub fn set_colors(&self, enabled: bool) {
+        self.colors.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_timestamps(&self, enabled: bool) {
+        self.timestamps.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_show_date(&self, enabled: bool) {
+        self.show_date.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_show_target(&self, enabled: bool) {
+        self.show_target.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_show_module(&self, enabled: bool) {
+        self.show_module.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_show_file_line(&self, enabled: bool) {
+        self.show_file_line.store(enabled, Ordering::Relaxed);
+    }
+
+    // Snapshot of the config with the live toggles applied, for custom formatters
+    pub fn apply(&self, config: &LogConfig) -> LogConfig {
+        let mut config = config.clone();
+        config.color_mode = if self.colors() {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        };
+        config.timestamps = self.timestamps();
+        config.show_date = self.show_date();
+        config.show_target = self.show_target();
+        config.show_module = self.show_module();
+        config.show_file_line = self.show_file_line();
+        config
+    }
 }
 
 pub struct Formatter {
     config: LogConfig,
+    toggles: Arc<DisplayToggles>,
+    depth: ColorDepth,
+    width: Option<usize>,
+    unicode: bool,
+    hyperlinks: bool,
+    groups: Option<CiGroups>,
+    // Widest target so far, for aligned columns without a fixed target width
+    target_column: AtomicUsize,
 }
 
 impl Formatter {
     pub fn new(config: LogConfig) -> Self {
-        Self { config }
+        let toggles = Arc::new(DisplayToggles::from_config(&config));
+        let depth = config.color_depth.unwrap_or_else(ColorDepth::detect);
+        let width = config.max_width.or_else(|| {
+            (config.terminal_width && config.output.is_terminal())
+                .then(ansi::terminal_width)
+                .flatten()
+        });
+        let unicode = config.unicode.unwrap_or_else(color::supports_unicode);
+        let hyperlinks = config
+            .hyperlinks
+            .unwrap_or_else(|| color::supports_hyperlinks() && config.output.is_terminal());
+        let groups = CiGroups::resolve(&config);
+        Self {
+            config,
+            toggles,
+            depth,
+            width,
+            unicode,
+            hyperlinks,
+            groups,
+            target_column: AtomicUsize::new(0),
+        }
+    }
+
+    /// A formatter for `config` that shares this one's display toggles, so flipping
+    /// them on either affects both.
+    pub fn with_shared_toggles(&self, config: LogConfig) -> Self {
+        Self {
+            toggles: Arc::clone(&self.toggles),
+            ..Self::new(config)
+        }
+    }
+
+    pub fn toggles(&self) -> &DisplayToggles {
+        &self.toggles
     }
 
     pub fn format(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
@@ -26,22 +926,349 @@ impl Formatter {
             FormatterType::Pretty => self.format_pretty(level, message, context),
             FormatterType::Compact => self.format_compact(level, message, context),
             FormatterType::Json => self.format_json(level, message, context),
-            FormatterType::Custom(formatter) => formatter(&level, message, context, &self.config),
+            FormatterType::Logfmt => self.format_logfmt(level, message, context),
+            FormatterType::Gelf => serde_json::to_string(&gelf_record(level, message, context))
+                .unwrap_or_else(|_| "{}".to_string()),
+            FormatterType::Ecs => serde_json::to_string(&ecs_record(level, message, context))
+                .unwrap_or_else(|_| "{}".to_string()),
+            FormatterType::GitHub => self.format_github(level, message, context),
+            FormatterType::GitLab => {
+                format!("{:<5} {}", level.as_str(), self.ci_text(message, context))
+            }
+            FormatterType::TeamCity => self.format_teamcity(level, message, context),
+            FormatterType::Custom(formatter) => {
+                formatter.format(&level, message, context, &self.toggles.apply(&self.config))
+            }
+        }
+    }
+
+    pub fn render(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
+        self.format(level, message, context)
+    }
+
+    pub fn format_list(
+        &self,
+        level: LogLevel,
+        header: &str,
+        items: &[String],
+        context: &LogContext,
+    ) -> String {
+        match &self.config.formatter {
+            FormatterType::Pretty | FormatterType::Compact => {
+                let mut output = self.format(level, header, context);
+                let indent = self.continuation_indent(context);
+                let padding = " ".repeat(self.config.indent_size);
+                let bullet = &self.config.indent_style.bullet;
+
+                for item in items {
+                    output.push('\n');
+                    if self.toggles.colors() {
+                        output.push_str(&format!(
+                            "{}{}{} {}",
+                            self.paint_guides(&indent),
+                            padding,
+                            bullet.dimmed(),
+                            item
+                        ));
+                    } else {
+                        output.push_str(&format!("{}{}{} {}", indent, padding, bullet, item));
+                    }
+                }
+
+                output
+            }
+            FormatterType::Json
+            | FormatterType::Logfmt
+            | FormatterType::Gelf
+            | FormatterType::Ecs
+            | FormatterType::GitHub
+            | FormatterType::GitLab
+            | FormatterType::TeamCity
+            | FormatterType::Custom(_) => {
+                let context = context.clone().with_field("items", items);
+                self.format(level, header, &context)
+            }
         }
     }
 
+    pub fn format_diff(
+        &self,
+        level: LogLevel,
+        header: &str,
+        old: &str,
+        new: &str,
+        context: &LogContext,
+    ) -> String {
+        match &self.config.formatter {
+            FormatterType::Pretty | FormatterType::Compact => {
+                let mut output = self.format(level, header, context);
+                let indent = self.continuation_indent(context);
+                let padding = " ".repeat(self.config.indent_size);
+
+                for line in unified_diff(old, new, DEFAULT_CONTEXT) {
+                    output.push('\n');
+                    if self.toggles.colors() {
+                        let text = line.to_string();
+                        let colored = match line {
+                            DiffLine::Hunk { .. } => text.cyan().to_string(),
+                            DiffLine::Context(_) => text.dimmed().to_string(),
+                            DiffLine::Removed(_) => text.red().to_string(),
+                            DiffLine::Added(_) => text.green().to_string(),
+                        };
+                        output.push_str(&format!(
+                            "{}{}{}",
+                            self.paint_guides(&indent),
+                            padding,
+                            colored
+                        ));
+                    } else {
+                        output.push_str(&format!("{}{}{}", indent, padding, line));
+                    }
+                }
+
+                output
+            }
+            FormatterType::Json
+            | FormatterType::Logfmt
+            | FormatterType::Gelf
+            | FormatterType::Ecs
+            | FormatterType::GitHub
+            | FormatterType::GitLab
+            | FormatterType::TeamCity
+            | FormatterType::Custom(_) => {
+                let context = context
+                    .clone()
+                    .with_field("old", old)
+                    .with_field("new", new);
+                self.format(level, header, &context)
+            }
+        }
+    }
+
+    pub fn format_banner(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
+        match &self.config.formatter {
+            FormatterType::Pretty | FormatterType::Compact => {
+                self.draw_box(level, None, message, context)
+            }
+            _ => self.format(level, message, context),
+        }
+    }
+
+    pub fn format_panel(
+        &self,
+        level: LogLevel,
+        title: &str,
+        body: &str,
+        context: &LogContext,
+    ) -> String {
+        match &self.config.formatter {
+            FormatterType::Pretty | FormatterType::Compact => {
+                self.draw_box(level, Some(title), body, context)
+            }
+            _ => self.format(level, title, &context.clone().with_field("body", body)),
+        }
+    }
+
+    // Rounded box as wide as the terminal (or `max_width`) when known, otherwise as wide
+    // as its text; a banner is centered, a panel has its title in the top border
+    fn draw_box(
+        &self,
+        level: LogLevel,
+        title: Option<&str>,
+        body: &str,
+        context: &LogContext,
+    ) -> String {
+        let indent = self.continuation_indent(context);
+        let natural = body
+            .lines()
+            .map(ansi::visible_width)
+            .chain(title.map(|title| ansi::visible_width(title) + 2))
+            .max()
+            .unwrap_or(0)
+            + 4;
+        let outer = match self.width {
+            Some(width) => width.saturating_sub(ansi::visible_width(&indent)),
+            None => natural,
+        }
+        .max(8);
+        let inner = outer - 4;
+
+        let colors = self.toggles.colors();
+        let border = |text: String| {
+            if colors {
+                self.config
+                    .theme
+                    .level_style(level)
+                    .paint(&text, self.depth)
+            } else {
+                text
+            }
+        };
+        let indent = if colors && !indent.is_empty() {
+            self.paint_guides(&indent)
+        } else {
+            indent
+        };
+
+        let mut lines = Vec::new();
+        lines.push(match title {
+            Some(title) => {
+                let title = ansi::truncate(title, inner.saturating_sub(2));
+                let title_width = ansi::visible_width(&title);
+                let title = if colors {
+                    title.bold().to_string()
+                } else {
+                    title.into_owned()
+                };
+                format!(
+                    "{}{}{}",
+                    border("╭─ ".to_string()),
+                    title,
+                    border(format!(" {}╮", "─".repeat(outer - 5 - title_width)))
+                )
+            }
+            None => border(format!("╭{}╮", "─".repeat(outer - 2))),
+        });
+        for line in body.lines() {
+            for line in ansi::wrap(line, inner, "").lines() {
+                let spare = inner.saturating_sub(ansi::visible_width(line));
+                let (left, right) = if title.is_none() {
+                    (spare / 2, spare - spare / 2)
+                } else {
+                    (0, spare)
+                };
+                let text = if colors && title.is_none() {
+                    self.config
+                        .theme
+                        .message_style(level)
+                        .bold()
+                        .paint(line, self.depth)
+                } else {
+                    line.to_string()
+                };
+                lines.push(format!(
+                    "{}{}{}{}",
+                    border("│ ".to_string()),
+                    " ".repeat(left),
+                    text,
+                    border(format!("{} │", " ".repeat(right)))
+                ));
+            }
+        }
+        lines.push(border(format!("╰{}╯", "─".repeat(outer - 2))));
+
+        lines
+            .iter()
+            .map(|line| format!("{}{}", indent, line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // A closed deferred scope as one tree: a header naming the scope, then its records
+    // one level in, the scope's own on branches and the last of those closing it
+    pub(crate) fn format_scope_block(&self, block: Block) -> Vec<(Record, bool)> {
+        let Block {
+            name,
+            context,
+            mut records,
+        } = block;
+        if records.is_empty()
+            || !matches!(
+                self.config.formatter,
+                FormatterType::Pretty | FormatterType::Compact
+            )
+        {
+            return records;
+        }
+
+        let own = |record: &Record| record.context.level == context.level;
+        let last = records.iter().rposition(|(record, _)| own(record));
+        for (i, (record, _)) in records.iter_mut().enumerate() {
+            let (first, rest) =
+                self.config
+                    .indent_style
+                    .render_tree(1, self.config.indent_size, Some(i) == last);
+            let (first, rest) = match (own(record), self.toggles.colors()) {
+                (true, true) => (self.paint_guides(&first), self.paint_guides(&rest)),
+                (true, false) => (first, rest),
+                (false, true) => (self.paint_guides(&rest), self.paint_guides(&rest)),
+                (false, false) => (rest.clone(), rest),
+            };
+            record.line = record
+                .line
+                .split('\n')
+                .enumerate()
+                .map(|(n, line)| format!("{}{}", if n == 0 { &first } else { &rest }, line))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let primary = records.iter().any(|(_, primary)| *primary);
+        let line = self.format(LogLevel::Info, &name, &context);
+        records.insert(
+            0,
+            (Record::new(LogLevel::Info, &name, context, line), primary),
+        );
+        records
+    }
+
+    // Leading indent for the extra lines of multi-line records (lists, diffs)
+    fn continuation_indent(&self, context: &LogContext) -> String {
+        let depth = context.nesting_level() as usize;
+        match &self.config.formatter {
+            FormatterType::Pretty if context.is_nested() => {
+                self.config
+                    .indent_style
+                    .render_tree(depth, self.config.indent_size, context.closes_scope)
+                    .1
+            }
+            FormatterType::Pretty => String::new(),
+            _ => " ".repeat(depth * self.config.indent_size),
+        }
+    }
+
+    // Target of a `file:line` hyperlink; relative paths are taken from the current
+    // directory, which is the crate root under `cargo run`
+    fn link_url(&self, file: &str, line: u32) -> String {
+        let path = std::path::Path::new(file);
+        let path = match std::env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        };
+        let path = path
+            .to_string_lossy()
+            .replace('\\', "/")
+            .replace(' ', "%20");
+        self.config
+            .link_format
+            .replace("{path}", &path)
+            .replace("{line}", &line.to_string())
+    }
+
+    fn paint_guides(&self, guides: &str) -> String {
+        match self.config.indent_style.color {
+            Some(style) => style.paint(guides, self.depth),
+            None => guides.dimmed().to_string(),
+        }
+    }
+
+    pub fn config(&self) -> &LogConfig {
+        &self.config
+    }
+
     fn format_pretty(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
         let mut parts = Vec::new();
 
         // Timestamp
-        if self.config.timestamps {
-            let timestamp = if self.config.show_date {
-                context.timestamp.format("%Y-%m-%d %H:%M:%S%.3f")
+        if self.toggles.timestamps() {
+            let local = self.config.timezone.convert(context.timestamp);
+            let timestamp = if self.toggles.show_date() {
+                local.format("%Y-%m-%d %H:%M:%S%.3f")
             } else {
-                context.timestamp.format("%H:%M:%S%.3f")
+                local.format("%H:%M:%S%.3f")
             };
 
-            if self.config.colors_enabled {
+            if self.toggles.colors() {
                 parts.push(format!("{}", timestamp.dimmed()));
             } else {
                 parts.push(timestamp.to_string());
@@ -49,63 +1276,67 @@ impl Formatter {
         }
 
         // Level with colors
+        if let Some(icon) = self.config.theme.icon(level, self.unicode) {
+            let width = self.config.theme.icon_width(self.unicode);
+            let pad = " ".repeat(width.saturating_sub(ansi::visible_width(icon)));
+            if self.toggles.colors() {
+                let icon = self.config.theme.paint_level(level, icon, self.depth);
+                parts.push(format!("{}{}", icon, pad));
+            } else {
+                parts.push(format!("{}{}", icon, pad));
+            }
+        }
         let level_str = level.short_name().to_string();
+        let level_pad = if self.config.align_columns {
+            " ".repeat(level_column().saturating_sub(ansi::visible_width(&level_str)))
+        } else {
+            String::new()
+        };
 
-        if self.config.colors_enabled {
-            let colored_level = match level {
-                LogLevel::Trace => level_str.purple().to_string(),
-                LogLevel::Debug => level_str.blue().to_string(),
-                LogLevel::Info => level_str.green().to_string(),
-                LogLevel::Warn => level_str.yellow().to_string(),
-                LogLevel::Error => level_str.red().to_string(),
-                LogLevel::Fatal => level_str.on_red().white().bold().to_string(),
-            };
-            parts.push(format!("[{}]", colored_level));
+        if self.toggles.colors() {
+            let colored_level = self.config.theme.paint_level(level, &level_str, self.depth);
+            parts.push(format!("[{}]{}", colored_level, level_pad));
         } else {
-            parts.push(format!("[{}]", level_str));
+            parts.push(format!("[{}]{}", level_str, level_pad));
         }
 
         // Subtitle with bold formatting and colors
         if let Some(ref subtitle) = context.subtitle {
-            if self.config.colors_enabled && self.config.bold_subtitles {
-                let colored_subtitle = match level {
-                    LogLevel::Trace => subtitle.purple().bold().to_string(),
-                    LogLevel::Debug => subtitle.blue().bold().to_string(),
-                    LogLevel::Info => subtitle.green().bold().to_string(),
-                    LogLevel::Warn => subtitle.yellow().bold().to_string(),
-                    LogLevel::Error => subtitle.red().bold().to_string(),
-                    LogLevel::Fatal => subtitle.on_red().white().bold().to_string(),
-                };
-                parts.push(colored_subtitle);
+            if self.toggles.colors() {
+                let mut style = self.config.theme.level_style(level);
+                if self.config.bold_subtitles {
+                    style = style.bold();
+                }
+                parts.push(style.paint(subtitle, self.depth));
             } else if self.config.bold_subtitles {
                 parts.push(subtitle.bold().to_string());
-            } else if self.config.colors_enabled {
-                let colored_subtitle = match level {
-                    LogLevel::Trace => subtitle.purple().to_string(),
-                    LogLevel::Debug => subtitle.blue().to_string(),
-                    LogLevel::Info => subtitle.green().to_string(),
-                    LogLevel::Warn => subtitle.yellow().to_string(),
-                    LogLevel::Error => subtitle.red().to_string(),
-                    LogLevel::Fatal => subtitle.on_red().white().to_string(),
-                };
-                parts.push(colored_subtitle);
             } else {
                 parts.push(subtitle.clone());
             }
         }
 
         // Target/Module
-        if self.config.show_target {
-            if self.config.colors_enabled {
-                parts.push(format!("{}", context.target.cyan()));
+        if self.toggles.show_target() {
+            let length = ansi::visible_width(&context.target);
+            let (target, width) = match self.config.target_width {
+                Some(width) => (ansi::truncate(&context.target, width), width),
+                None if self.config.align_columns => {
+                    let widest = self.target_column.fetch_max(length, Ordering::Relaxed);
+                    (Cow::Borrowed(context.target.as_str()), widest.max(length))
+                }
+                None => (Cow::Borrowed(context.target.as_str()), 0),
+            };
+            let pad = " ".repeat(width.saturating_sub(ansi::visible_width(&target)));
+            if self.toggles.colors() {
+                parts.push(format!("{}{}", target.cyan(), pad));
             } else {
-                parts.push(context.target.clone());
+                parts.push(format!("{}{}", target, pad));
             }
         }
 
-        if self.config.show_module {
+        if self.toggles.show_module() {
             if let Some(ref module) = context.module {
-                if self.config.colors_enabled {
+                if self.toggles.colors() {
                     parts.push(format!("{}::", module.cyan()));
                 } else {
                     parts.push(format!("{}::", module));
@@ -114,91 +1345,175 @@ impl Formatter {
         }
 
         // File and line
-        if self.config.show_file_line {
+        if self.toggles.show_file_line() {
             if let (Some(ref file), Some(line)) = (&context.file, context.line) {
-                if self.config.colors_enabled {
-                    parts.push(format!("({}:{})", file.dimmed(), line.to_string().dimmed()));
+                if self.toggles.colors() {
+                    let shown = format!("{}:{}", file.dimmed(), line.to_string().dimmed());
+                    if self.hyperlinks {
+                        let url = self.link_url(file, line);
+                        parts.push(format!("(\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\)", url, shown));
+                    } else {
+                        parts.push(format!("({})", shown));
+                    }
                 } else {
                     parts.push(format!("({}:{})", file, line));
                 }
             }
         }
 
+        // Scope name prefix
+        if self.config.indent_style.show_scope_name {
+            if let Some(ref scope) = context.scope {
+                if self.toggles.colors() {
+                    parts.push(format!("{}:", scope.cyan()));
+                } else {
+                    parts.push(format!("{}:", scope));
+                }
+            }
+        }
+
         // Indentation for nested logs
-        let _indent = " ".repeat(context.nesting_level() as usize * self.config.indent_size);
-        let indent_marker = if context.is_nested() {
-            if self.config.colors_enabled {
-                "│ "
-                    .repeat(context.nesting_level() as usize)
-                    .dimmed()
-                    .to_string()
+        let (indent_marker, guides) = if context.is_nested() {
+            let (first, rest) = self.config.indent_style.render_tree(
+                context.nesting_level() as usize,
+                self.config.indent_size,
+                context.closes_scope,
+            );
+            if self.toggles.colors() {
+                (self.paint_guides(&first), self.paint_guides(&rest))
             } else {
-                "│ ".repeat(context.nesting_level() as usize)
+                (first, rest)
             }
         } else {
+            (String::new(), String::new())
+        };
+
+        // Combine all parts
+        let prefix = if parts.is_empty() {
             String::new()
+        } else {
+            format!("{} ", parts.join(" "))
         };
 
-        // Message
-        let formatted_message = if self.config.colors_enabled {
-            match level {
-                LogLevel::Error | LogLevel::Fatal => message.red().to_string(),
-                LogLevel::Warn => message.yellow().to_string(),
-                LogLevel::Info => message.white().to_string(),
-                LogLevel::Debug => message.blue().to_string(),
-                LogLevel::Trace => message.purple().to_string(),
+        // Lines after the first go under the message column, behind the same guides
+        let align = format!("{}{}", guides, " ".repeat(ansi::visible_width(&prefix)));
+        let mut continuation = align.clone();
+        if let Some(ref marker) = self.config.continuation_marker {
+            let marker = format!("{} ", marker);
+            if self.toggles.colors() {
+                continuation.push_str(&marker.dimmed().to_string());
+            } else {
+                continuation.push_str(&marker);
+            }
+        }
+
+        // Message, painted line by line so the guides between lines keep their own color
+        let paint = |line: &str| {
+            if !self.toggles.colors() {
+                line.to_string()
+            } else if let Some(style) = context.style {
+                style.paint(line, self.depth)
+            } else {
+                self.config
+                    .theme
+                    .message_style(level)
+                    .paint(line, self.depth)
             }
-        } else {
-            message.to_string()
         };
+        let formatted_message = message
+            .split('\n')
+            .map(|line| paint(line.strip_suffix('\r').unwrap_or(line)))
+            .collect::<Vec<_>>()
+            .join(&format!("\n{}", continuation));
 
         // Custom fields
         let mut fields_str = String::new();
         if !context.fields.is_empty() {
-            let fields: Vec<String> = context
-                .fields
-                .iter()
+            let colors = self.toggles.colors();
+            let key_width = context.fields.keys().map(|k| ansi::visible_width(k)).max();
+            let fields: Vec<String> = self
+                .ordered_fields(context)
+                .into_iter()
                 .map(|(k, v)| {
-                    if self.config.colors_enabled {
-                        format!("{}={}", k.cyan(), v.to_string().white())
+                    let value = match self.config.field_renderers.get(k) {
+                        Some(renderer) => renderer.render(v, colors),
+                        None if colors => v.to_string().white().to_string(),
+                        None => v.to_string(),
+                    };
+                    // Keys padded to one width so the values line up
+                    let separator = if self.config.field_lines {
+                        let padding = key_width.unwrap_or(0) - ansi::visible_width(k);
+                        format!("{} = ", " ".repeat(padding))
+                    } else {
+                        "=".to_string()
+                    };
+                    if colors {
+                        format!("{}{}{}", k.cyan(), separator, value)
                     } else {
-                        format!("{}={}", k, v)
+                        format!("{}{}{}", k, separator, value)
                     }
                 })
                 .collect();
-            fields_str = format!(" {{{}}}", fields.join(", "));
+            fields_str = if self.config.field_lines {
+                fields
+                    .iter()
+                    .map(|field| format!("\n{}{}", align, field))
+                    .collect()
+            } else {
+                format!(" {{{}}}", fields.join(", "))
+            };
         }
 
-        // Combine all parts
-        let prefix = if parts.is_empty() {
-            String::new()
-        } else {
-            format!("{} ", parts.join(" "))
-        };
-
         // Apply width limit if configured
         let full_message = format!(
             "{}{}{}{}{}",
             indent_marker, prefix, formatted_message, fields_str, ""
         );
 
-        if let Some(max_width) = self.config.max_width {
-            if full_message.len() > max_width {
-                format!("{}...", &full_message[..max_width.saturating_sub(3)])
+        let line = match (self.width, self.config.overflow) {
+            (Some(width), Overflow::Truncate) => full_message
+                .split('\n')
+                .map(|line| ansi::truncate(line, width))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            (Some(width), Overflow::Wrap) => {
+                // Under the message unless that leaves less than half the line
+                if ansi::visible_width(&continuation) * 2 > width {
+                    continuation = guides.clone();
+                }
+                ansi::wrap(&full_message, width, &continuation)
+            }
+            (None, _) => full_message,
+        };
+
+        match context.backtrace {
+            Some(ref frames) => self.append_backtrace(line, &guides, frames),
+            None => line,
+        }
+    }
+
+    // One indented `at ...` line per frame below the record
+    fn append_backtrace(&self, mut line: String, indent_marker: &str, frames: &[String]) -> String {
+        for frame in frames {
+            let frame = format!("    at {}", frame);
+            line.push('\n');
+            line.push_str(indent_marker);
+            if self.toggles.colors() {
+                line.push_str(&frame.dimmed().to_string());
             } else {
-                full_message
+                line.push_str(&frame);
             }
-        } else {
-            full_message
         }
+        line
     }
 
     fn format_compact(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
-        let timestamp = if self.config.timestamps {
-            if self.config.show_date {
-                context.timestamp.format("%Y-%m-%d %H:%M:%S").to_string()
+        let timestamp = if self.toggles.timestamps() {
+            let local = self.config.timezone.convert(context.timestamp);
+            if self.toggles.show_date() {
+                local.format("%Y-%m-%d %H:%M:%S").to_string()
             } else {
-                context.timestamp.format("%H:%M:%S").to_string()
+                local.format("%H:%M:%S").to_string()
             }
         } else {
             String::new()
@@ -211,6 +1526,7 @@ impl Formatter {
             LogLevel::Warn => "W",
             LogLevel::Error => "E",
             LogLevel::Fatal => "F",
+            other => other.short_name().get(..1).unwrap_or("?"),
         };
 
         let prefix = if timestamp.is_empty() {
@@ -219,62 +1535,478 @@ impl Formatter {
             format!("{} {} ", timestamp, level_char)
         };
 
-        let indent = "  ".repeat(context.nesting_level() as usize);
-        format!("{}{}{}", prefix, indent, message)
+        let indent = " ".repeat(context.nesting_level() as usize * self.config.indent_size);
+        match context.style {
+            Some(style) if self.toggles.colors() => {
+                format!("{}{}{}", prefix, indent, style.paint(message, self.depth))
+            }
+            _ => format!("{}{}{}", prefix, indent, message),
+        }
     }
 
     fn format_json(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
-        let mut json_obj = serde_json::json!({
-            "timestamp": context.timestamp.to_rfc3339(),
-            "level": level.as_str(),
-            "message": message,
-            "target": context.target,
-            "id": context.id,
-            "nesting_level": context.nesting_level(),
-        });
+        let mut record = json_record(level, message, context);
+        if self.config.timezone != Timezone::Utc
+            || self.config.timestamp_format != TimestampFormat::Rfc3339
+        {
+            record["timestamp"] = self.machine_timestamp(context.timestamp);
+        }
+        if !self.config.json_keys.is_empty() {
+            rename_keys(&mut record, &self.config.json_keys);
+        }
+        if self.config.json_fields == JsonFields::Flatten {
+            flatten_fields(&mut record, &self.config.json_keys);
+        }
+        serde_json::to_string(&record).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    fn machine_timestamp(&self, timestamp: DateTime<Utc>) -> serde_json::Value {
+        match self.config.timestamp_format {
+            TimestampFormat::Rfc3339 => self.config.timezone.convert(timestamp).to_rfc3339().into(),
+            TimestampFormat::EpochSeconds => timestamp.timestamp().into(),
+            TimestampFormat::EpochMillis => timestamp.timestamp_millis().into(),
+        }
+    }
+
+    /// The line opening scope `name` as a collapsible CI group: a GitHub group (top
+    /// level only, as groups don't nest), a GitLab section or a TeamCity block.
+    pub fn format_scope_start(&self, name: &str, context: &LogContext) -> Option<String> {
+        match self.groups? {
+            CiGroups::GitHub if !context.is_nested() => {
+                Some(format!("::group::{}", github_data(name)))
+            }
+            CiGroups::GitLab => Some(format!(
+                "\x1b[0Ksection_start:{}:{}\r\x1b[0K{}",
+                context.timestamp.timestamp(),
+                gitlab_section(name, context),
+                name.replace(['\r', '\n'], " ")
+            )),
+            CiGroups::TeamCity => Some(format!(
+                "##teamcity[blockOpened name='{}']",
+                teamcity_value(name)
+            )),
+            _ => None,
+        }
+    }
+
+    /// The line closing the scope opened with [`format_scope_start`](Self::format_scope_start).
+    pub fn format_scope_end(&self, name: &str, context: &LogContext) -> Option<String> {
+        match self.groups? {
+            CiGroups::GitHub if !context.is_nested() => Some("::endgroup::".to_string()),
+            CiGroups::GitLab => Some(format!(
+                "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+                Utc::now().timestamp(),
+                gitlab_section(name, context)
+            )),
+            CiGroups::TeamCity => Some(format!(
+                "##teamcity[blockClosed name='{}']",
+                teamcity_value(name)
+            )),
+            _ => None,
+        }
+    }
+
+    // Context fields in the configured order
+    fn ordered_fields<'a>(&self, context: &'a LogContext) -> Vec<(&'a String, &'a FieldValue)> {
+        let mut fields: Vec<_> = context.fields.iter().collect();
+        if self.config.field_order == FieldOrder::Sorted {
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+        }
+        fields
+    }
+
+    // Message and fields as one line of text for the CI formats
+    fn ci_text(&self, message: &str, context: &LogContext) -> String {
+        let mut text = message.to_string();
+        for (key, value) in self.ordered_fields(context) {
+            let value = value.to_text();
+            text.push_str(&format!(" {}={}", logfmt_key(key), logfmt_value(&value)));
+        }
+        text
+    }
+
+    fn format_github(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
+        let text = github_data(&self.ci_text(message, context));
+        let command = match level.base_level() {
+            LogLevel::Trace | LogLevel::Debug => return format!("::debug::{}", text),
+            LogLevel::Warn => "warning",
+            LogLevel::Error | LogLevel::Fatal => "error",
+            _ => return self.ci_text(message, context),
+        };
+
+        let mut properties = Vec::new();
+        if let Some(ref file) = context.file {
+            properties.push(format!("file={}", github_property(file)));
+            if let Some(line) = context.line {
+                properties.push(format!("line={}", line));
+            }
+        }
+        properties.push(format!("title={}", github_property(&context.target)));
+        format!("::{} {}::{}", command, properties.join(","), text)
+    }
+
+    fn format_teamcity(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
+        let status = match level.base_level() {
+            LogLevel::Warn => "WARNING",
+            LogLevel::Error | LogLevel::Fatal => "ERROR",
+            _ => "NORMAL",
+        };
+        let mut line = format!(
+            "##teamcity[message text='{}' status='{}'",
+            teamcity_value(&self.ci_text(message, context)),
+            status
+        );
+        if let (Some(backtrace), "ERROR") = (&context.backtrace, status) {
+            line.push_str(&format!(
+                " errorDetails='{}'",
+                teamcity_value(&backtrace.join("\n"))
+            ));
+        }
+        line.push(']');
+        line
+    }
+
+    fn format_logfmt(&self, level: LogLevel, message: &str, context: &LogContext) -> String {
+        let mut pairs = Vec::new();
+
+        if self.toggles.timestamps() {
+            let ts = match self.machine_timestamp(context.timestamp) {
+                serde_json::Value::String(ts) => ts,
+                ts => ts.to_string(),
+            };
+            pairs.push(("ts".to_string(), ts));
+        }
+        pairs.push(("level".to_string(), level.as_str().to_lowercase()));
+        pairs.push(("target".to_string(), context.target.clone()));
+        pairs.push(("msg".to_string(), message.to_string()));
 
         if let Some(ref subtitle) = context.subtitle {
-            json_obj["subtitle"] = serde_json::Value::String(subtitle.clone());
+            pairs.push(("subtitle".to_string(), subtitle.clone()));
         }
 
-        if let Some(ref module) = context.module {
-            json_obj["module"] = serde_json::Value::String(module.clone());
+        for (key, value) in self.ordered_fields(context) {
+            let value = match value {
+                value if value.is_null() => String::new(),
+                value => value.to_text(),
+            };
+            pairs.push((logfmt_key(key), value));
         }
 
-        if let (Some(ref file), Some(line)) = (&context.file, context.line) {
-            json_obj["file"] = serde_json::Value::String(file.clone());
-            json_obj["line"] = serde_json::Value::Number(line.into());
+        pairs
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, logfmt_value(value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// ECS version the `Ecs` formatter declares in `ecs.version`.
+pub const ECS_VERSION: &str = "8.11.0";
+
+/// The Elastic Common Schema document the `Ecs` formatter renders for a record.
+///
+/// Fields with dotted names (`http.request.method`, `error.type`) are taken to be ECS
+/// fields and kept at the top level; an `error` field becomes `error.message`, and
+/// everything else goes under `labels`.
+pub fn ecs_record(level: LogLevel, message: &str, context: &LogContext) -> serde_json::Value {
+    let mut ecs = serde_json::Map::new();
+    ecs.insert(
+        "@timestamp".to_string(),
+        context.timestamp.to_rfc3339().into(),
+    );
+    ecs.insert(
+        "log.level".to_string(),
+        level.as_str().to_lowercase().into(),
+    );
+    ecs.insert("log.logger".to_string(), context.target.clone().into());
+    ecs.insert("message".to_string(), message.into());
+    ecs.insert("ecs.version".to_string(), ECS_VERSION.into());
+    ecs.insert("event.id".to_string(), context.id.clone().into());
+
+    if let Some(ref module) = context.module {
+        ecs.insert("log.origin.function".to_string(), module.clone().into());
+    }
+    if let (Some(ref file), Some(line)) = (&context.file, context.line) {
+        ecs.insert("log.origin.file.name".to_string(), file.clone().into());
+        ecs.insert("log.origin.file.line".to_string(), line.into());
+    }
+
+    if let Some(ref frames) = context.backtrace {
+        ecs.insert("error.stack_trace".to_string(), frames.join("\n").into());
+    }
+
+    let mut labels = serde_json::Map::new();
+    if let Some(ref subtitle) = context.subtitle {
+        labels.insert("subtitle".to_string(), subtitle.clone().into());
+    }
+    if let Some(ref parent_id) = context.parent_id {
+        labels.insert("parent_id".to_string(), parent_id.clone().into());
+    }
+
+    for (key, value) in &context.fields {
+        if key == "error" {
+            ecs.insert("error.message".to_string(), value.to_text().into());
+        } else if key.contains('.') {
+            ecs.insert(key.clone(), value.to_json());
+        } else {
+            // ECS labels are keywords
+            labels.insert(key.clone(), value.to_text().into());
         }
+    }
 
-        if let Some(ref parent_id) = context.parent_id {
-            json_obj["parent_id"] = serde_json::Value::String(parent_id.clone());
+    if !labels.is_empty() {
+        ecs.insert("labels".to_string(), serde_json::Value::Object(labels));
+    }
+
+    serde_json::Value::Object(ecs)
+}
+
+/// The GELF 1.1 message the `Gelf` formatter and output send for a record.
+///
+/// Context fields become `_`-prefixed additional fields; `level` is the syslog
+/// severity.
+pub fn gelf_record(level: LogLevel, message: &str, context: &LogContext) -> serde_json::Value {
+    let short_message = message.lines().next().unwrap_or_default();
+    let timestamp = context.timestamp.timestamp_micros() as f64 / 1_000_000.0;
+
+    let mut gelf = serde_json::json!({
+        "version": "1.1",
+        "host": gelf_host(),
+        "short_message": short_message,
+        "timestamp": timestamp,
+        "level": crate::syslog::severity(level),
+        "_level_name": level.as_str(),
+        "_target": context.target,
+        "_record_id": context.id,
+    });
+
+    if short_message.len() < message.len() {
+        gelf["full_message"] = serde_json::Value::String(message.to_string());
+    }
+
+    let optional = [
+        ("_subtitle", context.subtitle.clone()),
+        ("_module", context.module.clone()),
+        ("_file", context.file.clone()),
+        ("_parent_id", context.parent_id.clone()),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            gelf[key] = serde_json::Value::String(value);
         }
+    }
+    if let Some(line) = context.line {
+        gelf["_line"] = line.into();
+    }
 
-        if !context.fields.is_empty() {
-            json_obj["fields"] = serde_json::Value::Object(
-                context
-                    .fields
-                    .iter()
-                    .map(|(k, v)| (k.clone(), v.clone()))
-                    .collect(),
-            );
+    for (key, value) in &context.fields {
+        gelf[gelf_field_name(key)] = value.to_json();
+    }
+
+    gelf
+}
+
+fn gelf_host() -> &'static str {
+    static HOST: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    HOST.get_or_init(crate::syslog::hostname)
+}
+
+// Additional field names must match `^_[\w\.\-]*$`, and `_id` is reserved
+fn gelf_field_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name == "id" {
+        "_id_".to_string()
+    } else {
+        format!("_{}", name)
+    }
+}
+
+// Keys can't be quoted, so anything that would end them becomes `_`
+// Workflow command data, where `%`, CR and LF are escaped
+fn github_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+// Workflow command properties also escape the `:` and `,` separating them
+fn github_property(text: &str) -> String {
+    github_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+// Section names may only hold letters, digits, `_`, `.` and `-`; the context id keeps
+// sections of the same name apart
+fn gitlab_section(name: &str, context: &LogContext) -> String {
+    format!("{}_{}", name, context.id)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn teamcity_value(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn logfmt_key(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c == '=' || c == '"' || c.is_whitespace() || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn logfmt_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c == '=' || c == '"' || c == '\\' || c.is_whitespace() || c.is_control());
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The JSON object the `Json` formatter renders for a record.
+pub fn json_record(level: LogLevel, message: &str, context: &LogContext) -> serde_json::Value {
+    let mut json_obj = serde_json::json!({
+        "schema_version": crate::schema::SCHEMA_VERSION,
+        "timestamp": context.timestamp.to_rfc3339(),
+        "level": level.as_str(),
+        "message": message,
+        "target": context.target,
+        "id": context.id,
+        "nesting_level": context.nesting_level(),
+    });
+
+    if let Some(ref subtitle) = context.subtitle {
+        json_obj["subtitle"] = serde_json::Value::String(subtitle.clone());
+    }
+
+    if let Some(ref module) = context.module {
+        json_obj["module"] = serde_json::Value::String(module.clone());
+    }
+
+    if let (Some(ref file), Some(line)) = (&context.file, context.line) {
+        json_obj["file"] = serde_json::Value::String(file.clone());
+        json_obj["line"] = serde_json::Value::Number(line.into());
+    }
+
+    if let Some(ref parent_id) = context.parent_id {
+        json_obj["parent_id"] = serde_json::Value::String(parent_id.clone());
+    }
+
+    if let Some(ref frames) = context.backtrace {
+        json_obj["backtrace"] = frames.clone().into();
+    }
+
+    if !context.fields.is_empty() {
+        json_obj["fields"] = serde_json::Value::Object(
+            context
+                .fields
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_json()))
+                .collect(),
+        );
+    }
+
+    json_obj
+}
+
+// Name a built-in JSON key is written under
+fn json_key<'a>(key: &'a str, names: &'a HashMap<String, String>) -> &'a str {
+    names.get(key).map_or(key, String::as_str)
+}
+
+fn rename_keys(record: &mut serde_json::Value, names: &HashMap<String, String>) {
+    let Some(record) = record.as_object_mut() else {
+        return;
+    };
+    for &key in JSON_KEYS {
+        let name = json_key(key, names);
+        if name != key {
+            if let Some(value) = record.remove(key) {
+                record.insert(name.to_string(), value);
+            }
         }
+    }
+}
 
-        serde_json::to_string(&json_obj).unwrap_or_else(|_| "{}".to_string())
+// Moves fields to the top level, leaving those named like a built-in key nested
+fn flatten_fields(record: &mut serde_json::Value, names: &HashMap<String, String>) {
+    let Some(record) = record.as_object_mut() else {
+        return;
+    };
+    let fields_key = json_key("fields", names);
+    let Some(serde_json::Value::Object(fields)) = record.remove(fields_key) else {
+        return;
+    };
+    let mut nested = serde_json::Map::new();
+    for (key, value) in fields {
+        if JSON_KEYS
+            .iter()
+            .any(|&builtin| json_key(builtin, names) == key)
+        {
+            nested.insert(key, value);
+        } else {
+            record.insert(key, value);
+        }
+    }
+    if !nested.is_empty() {
+        record.insert(fields_key.to_string(), serde_json::Value::Object(nested));
     }
 }
 
+// In the default theme
 pub fn colorize_level(level: LogLevel, text: &str, enabled: bool) -> String {
     if !enabled {
         return text.to_string();
     }
-
-    match level {
-        LogLevel::Trace => text.purple().to_string(),
-        LogLevel::Debug => text.blue().to_string(),
-        LogLevel::Info => text.green().to_string(),
-        LogLevel::Warn => text.yellow().to_string(),
-        LogLevel::Error => text.red().to_string(),
-        LogLevel::Fatal => text.on_red().white().bold().to_string(),
-    }
+    Theme::dark().paint_level(level, text, ColorDepth::detect())
 }
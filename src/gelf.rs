@@ -0,0 +1,130 @@
+use crate::compression::Compression;
+use crate::formatter::gelf_record;
+use crate::output::{OutputWriter, Record};
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LEN: usize = 12;
+const MAX_CHUNKS: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GelfConfig {
+    pub address: String,
+    pub chunk_size: usize,
+}
+
+impl GelfConfig {
+    /// `address` is the Graylog GELF UDP input, e.g. `graylog:12201`.
+    pub fn udp(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            chunk_size: 8192,
+        }
+    }
+
+    // Largest datagram sent; bigger messages are split into GELF chunks
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(CHUNK_HEADER_LEN + 1);
+        self
+    }
+}
+
+/// Sends each record as a GELF 1.1 message over UDP, chunked when it exceeds the
+/// configured datagram size.
+///
+/// Messages are built from the record itself, so any formatter can be used for the
+/// other outputs. Gzip compression is supported; Graylog does not accept zstd.
+pub struct GelfWriter {
+    socket: UdpSocket,
+    chunk_size: usize,
+    compression: Compression,
+}
+
+impl GelfWriter {
+    pub fn connect(config: &GelfConfig, compression: Compression) -> io::Result<Self> {
+        if compression == Compression::Zstd {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "GELF accepts gzip compression only",
+            ));
+        }
+
+        let addr = config
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No GELF address"))?;
+        let local = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+
+        Ok(Self {
+            socket,
+            chunk_size: config.chunk_size,
+            compression,
+        })
+    }
+
+    fn send(&self, message: &[u8]) -> io::Result<()> {
+        let payload = self.compression.compress(message)?;
+        if payload.len() <= self.chunk_size {
+            return self.socket.send(&payload).map(|_| ());
+        }
+
+        let body = self.chunk_size - CHUNK_HEADER_LEN;
+        let count = payload.len().div_ceil(body);
+        if count > MAX_CHUNKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "GELF message needs {} chunks, at most {} allowed",
+                    count, MAX_CHUNKS
+                ),
+            ));
+        }
+
+        let id = uuid::Uuid::new_v4();
+        let mut datagram = Vec::with_capacity(self.chunk_size);
+        for (sequence, chunk) in payload.chunks(body).enumerate() {
+            datagram.clear();
+            datagram.extend_from_slice(&CHUNK_MAGIC);
+            datagram.extend_from_slice(&id.as_bytes()[..8]);
+            datagram.push(sequence as u8);
+            datagram.push(count as u8);
+            datagram.extend_from_slice(chunk);
+            self.socket.send(&datagram)?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputWriter for GelfWriter {
+    // Bytes that bypass the logger are sent as the short message of an Info record
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        let gelf = gelf_record(record.level, &record.message, &record.context);
+        self.send(&serde_json::to_vec(&gelf)?)
+    }
+    // UDP has no handshake, so this only sees errors the OS has recorded on the
+    // socket, such as ICMP port unreachable after an earlier send
+    fn health_check(&self) -> io::Result<()> {
+        self.socket.peer_addr()?;
+        self.socket.take_error()?.map_or(Ok(()), Err)
+    }
+}
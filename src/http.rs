@@ -0,0 +1,249 @@
+use crate::compression::Compression;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Caps what a misbehaving server can make us buffer
+const MAX_BODY: usize = 16 * 1024 * 1024;
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Minimal HTTP/1.1 endpoint for the push-based sinks.
+///
+/// Only plain `http://` URLs are supported; put a local agent or TLS-terminating
+/// proxy in front of remote collectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub compression: Compression,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn into_result(self) -> io::Result<Self> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(io::Error::other(format!(
+                "HTTP {}: {}",
+                self.status,
+                String::from_utf8_lossy(&self.body)
+            )))
+        }
+    }
+}
+
+impl HttpEndpoint {
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let rest = match url.strip_prefix("http://") {
+            Some(rest) => rest,
+            None if url.starts_with("https://") => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "https endpoints are not supported, use a local proxy",
+                ))
+            }
+            None => url,
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid port"))?,
+            ),
+            None => (authority, 80),
+        };
+
+        if host.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Missing host"));
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            headers: Vec::new(),
+            compression: Compression::None,
+        })
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_path(&self, path: &str) -> Self {
+        let mut endpoint = self.clone();
+        endpoint.path = path.to_string();
+        endpoint
+    }
+
+    /// Checks that the endpoint accepts TCP connections, without sending a request.
+    pub fn probe(&self) -> io::Result<()> {
+        self.connect().map(|_| ())
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Unresolvable host"))?;
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(stream)
+    }
+
+    pub fn post(
+        &self,
+        content_type: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> io::Result<HttpResponse> {
+        let compressed;
+        let body = match self.compression {
+            Compression::None => body,
+            compression => {
+                compressed = compression.compress(body)?;
+                &compressed[..]
+            }
+        };
+
+        let mut stream = self.connect()?;
+
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path,
+            self.host,
+            self.port,
+            content_type,
+            body.len()
+        );
+        let extra = headers.iter().map(|(name, value)| (*name, *value));
+        for (name, value) in self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(extra)
+        {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if let Some(encoding) = self.compression.content_encoding() {
+            request.push_str(&format!("Content-Encoding: {}\r\n", encoding));
+        }
+        request.push_str("\r\n");
+
+        stream.write_all(request.as_bytes())?;
+        stream.write_all(body)?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream))
+    }
+}
+
+fn read_response<R: BufRead>(mut reader: R) -> io::Result<HttpResponse> {
+    let mut status_line = String::new();
+    read_line(&mut reader, &mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Malformed HTTP response"))?;
+
+    let mut chunked = false;
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if read_line(&mut reader, &mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "content-length" => content_length = value.parse::<usize>().ok(),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size = String::new();
+            read_line(&mut reader, &mut size)?;
+            let size = usize::from_str_radix(size.trim().split(';').next().unwrap_or(""), 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Malformed chunk"))?;
+            if size == 0 {
+                break;
+            }
+            read_body(&mut reader, &mut body, size)?;
+            let mut crlf = String::new();
+            read_line(&mut reader, &mut crlf)?;
+        }
+    } else if let Some(length) = content_length {
+        read_body(&mut reader, &mut body, length)?;
+    } else {
+        reader
+            .by_ref()
+            .take(MAX_BODY as u64 + 1)
+            .read_to_end(&mut body)?;
+        if body.len() > MAX_BODY {
+            return Err(too_large());
+        }
+    }
+
+    Ok(HttpResponse { status, body })
+}
+
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    let read = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "HTTP response line too long",
+        ));
+    }
+    Ok(read)
+}
+
+// Appends `len` bytes, growing the buffer as data arrives instead of trusting `len`
+fn read_body<R: Read>(reader: &mut R, body: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    if len > MAX_BODY - body.len() {
+        return Err(too_large());
+    }
+    let read = reader.by_ref().take(len as u64).read_to_end(body)?;
+    if read < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("HTTP response body over {} bytes", MAX_BODY),
+    )
+}
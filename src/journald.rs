@@ -0,0 +1,139 @@
+use crate::level::LogLevel;
+use crate::output::{OutputWriter, Record};
+use crate::syslog::{self, Facility};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+pub const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+pub struct JournaldWriter {
+    socket: UnixDatagram,
+    path: PathBuf,
+    identifier: String,
+    facility: Facility,
+}
+
+impl JournaldWriter {
+    pub fn connect(facility: Facility, app_name: Option<&str>) -> io::Result<Self> {
+        Self::connect_to(JOURNALD_SOCKET, facility, app_name)
+    }
+
+    pub fn connect_to<P: AsRef<Path>>(
+        path: P,
+        facility: Facility,
+        app_name: Option<&str>,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path)?;
+
+        Ok(Self {
+            socket,
+            path,
+            identifier: app_name.map_or_else(syslog::default_app_name, str::to_string),
+            facility,
+        })
+    }
+
+    pub fn encode(&self, level: LogLevel, message: &str, record: Option<&Record>) -> Vec<u8> {
+        let mut payload = Vec::new();
+        append_field(
+            &mut payload,
+            "PRIORITY",
+            &syslog::severity(level).to_string(),
+        );
+        append_field(&mut payload, "MESSAGE", message);
+        append_field(&mut payload, "SYSLOG_IDENTIFIER", &self.identifier);
+        append_field(
+            &mut payload,
+            "SYSLOG_FACILITY",
+            &(self.facility as u8).to_string(),
+        );
+
+        let Some(record) = record else {
+            return payload;
+        };
+        let context = &record.context;
+
+        append_field(&mut payload, "LOGFLOW_TARGET", &context.target);
+        if let Some(ref scope) = context.scope {
+            append_field(&mut payload, "LOGFLOW_SCOPE", scope);
+        }
+        if let Some(ref module) = context.module {
+            append_field(&mut payload, "CODE_MODULE", module);
+        }
+        if let (Some(ref file), Some(line)) = (&context.file, context.line) {
+            append_field(&mut payload, "CODE_FILE", file);
+            append_field(&mut payload, "CODE_LINE", &line.to_string());
+        }
+
+        for (key, value) in &context.fields {
+            let Some(key) = field_name(key) else {
+                continue;
+            };
+            append_field(&mut payload, &key, &value.to_text());
+        }
+
+        payload
+    }
+}
+
+impl OutputWriter for JournaldWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        let text = String::from_utf8_lossy(data);
+        let payload = self.encode(LogLevel::Info, text.trim_end_matches('\n'), None);
+        self.socket.send(&payload).map(|_| ())
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        let payload = self.encode(record.level, &record.message, Some(record));
+        self.socket.send(&payload).map(|_| ())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        std::fs::metadata(&self.path).map(|_| ())
+    }
+}
+
+// Native protocol: `KEY=value\n`, or a little-endian length prefix when the value spans lines
+fn append_field(payload: &mut Vec<u8>, key: &str, value: &str) {
+    payload.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+// Journal field names are uppercase ASCII, digits and `_`, and may not start with `_` or a digit
+fn field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches(|c: char| c == '_' || c.is_ascii_digit());
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.chars().take(64).collect())
+    }
+}
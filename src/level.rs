@@ -1,14 +1,30 @@
-use serde::{Deserialize, Serialize};
+use crate::formatter::Style;
+use crate::logger::LogFlowError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering as CmpOrdering;
 use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+// Codes of the built-in levels in a `LevelCell`; custom levels follow them
+const OFF_CODE: u8 = 6;
+const FIRST_CUSTOM_CODE: u8 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum LogLevel {
-    Trace = 0,
-    Debug = 1,
-    Info = 2,
-    Warn = 3,
-    Error = 4,
-    Fatal = 5,
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    /// Above every level: as a threshold it turns logging off, and records at it are
+    /// never written.
+    Off,
+    /// A level defined with [`LevelDef::register`].
+    Custom(CustomLevel),
 }
 
 impl LogLevel {
@@ -20,6 +36,8 @@ impl LogLevel {
             LogLevel::Warn => "WARN",
             LogLevel::Error => "ERROR",
             LogLevel::Fatal => "FATAL",
+            LogLevel::Off => "OFF",
+            LogLevel::Custom(custom) => custom.def().map_or("CUSTOM", |def| def.name),
         }
     }
 
@@ -31,9 +49,50 @@ impl LogLevel {
             LogLevel::Warn => "WRN",
             LogLevel::Error => "ERR",
             LogLevel::Fatal => "FTL",
+            LogLevel::Off => "OFF",
+            LogLevel::Custom(custom) => custom.def().map_or("CUS", |def| def.short_name),
+        }
+    }
+
+    /// Position in the level order: 0 for Trace, then steps of 100 up to 500 for Fatal,
+    /// so custom levels can sit between them.
+    pub fn severity(&self) -> u16 {
+        match self {
+            LogLevel::Trace => 0,
+            LogLevel::Debug => 100,
+            LogLevel::Info => 200,
+            LogLevel::Warn => 300,
+            LogLevel::Error => 400,
+            LogLevel::Fatal => 500,
+            LogLevel::Off => u16::MAX,
+            LogLevel::Custom(custom) => custom.def().map_or(0, |def| def.severity),
+        }
+    }
+
+    /// The highest built-in level at or below this one, for outputs with a fixed set
+    /// of levels such as syslog.
+    pub fn base_level(&self) -> LogLevel {
+        match self {
+            LogLevel::Off => LogLevel::Fatal,
+            LogLevel::Custom(_) => Self::all()
+                .iter()
+                .rev()
+                .copied()
+                .find(|level| level.severity() <= self.severity())
+                .unwrap_or(LogLevel::Trace),
+            level => *level,
         }
     }
 
+    // Color of a custom level, if it was given one
+    pub(crate) fn custom_style(&self) -> Option<Style> {
+        match self {
+            LogLevel::Custom(custom) => custom.def().and_then(|def| def.style),
+            _ => None,
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<LogLevel> {
         match s.to_uppercase().as_str() {
             "TRACE" | "TRC" => Some(LogLevel::Trace),
@@ -42,10 +101,38 @@ impl LogLevel {
             "WARN" | "WRN" | "WARNING" => Some(LogLevel::Warn),
             "ERROR" | "ERR" => Some(LogLevel::Error),
             "FATAL" | "FTL" => Some(LogLevel::Fatal),
-            _ => None,
+            "OFF" => Some(LogLevel::Off),
+            _ => CustomLevel::find(s).map(LogLevel::Custom),
         }
     }
 
+    fn code(&self) -> u8 {
+        match self {
+            LogLevel::Trace => 0,
+            LogLevel::Debug => 1,
+            LogLevel::Info => 2,
+            LogLevel::Warn => 3,
+            LogLevel::Error => 4,
+            LogLevel::Fatal => 5,
+            LogLevel::Off => OFF_CODE,
+            LogLevel::Custom(custom) => FIRST_CUSTOM_CODE + custom.0,
+        }
+    }
+
+    fn from_code(value: u8) -> LogLevel {
+        match value {
+            0 => LogLevel::Trace,
+            1 => LogLevel::Debug,
+            2 => LogLevel::Info,
+            3 => LogLevel::Warn,
+            4 => LogLevel::Error,
+            5 => LogLevel::Fatal,
+            OFF_CODE => LogLevel::Off,
+            custom => LogLevel::Custom(CustomLevel(custom - FIRST_CUSTOM_CODE)),
+        }
+    }
+
+    // The built-in levels, without Off
     pub fn all() -> &'static [LogLevel] {
         &[
             LogLevel::Trace,
@@ -56,6 +143,174 @@ impl LogLevel {
             LogLevel::Fatal,
         ]
     }
+
+    // Levels registered so far, in registration order
+    pub fn custom_levels() -> Vec<LogLevel> {
+        let count = CUSTOM_LEVELS.read().map_or(0, |levels| levels.len());
+        (0..count)
+            .map(|index| LogLevel::Custom(CustomLevel(index as u8)))
+            .collect()
+    }
+}
+
+// Ordered by severity; levels of equal severity by definition order
+impl Ord for LogLevel {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.severity(), self.code()).cmp(&(other.severity(), other.code()))
+    }
+}
+
+impl PartialOrd for LogLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Built-in levels keep their variant names, custom levels use their own
+impl Serialize for LogLevel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self {
+            LogLevel::Trace => "Trace",
+            LogLevel::Debug => "Debug",
+            LogLevel::Info => "Info",
+            LogLevel::Warn => "Warn",
+            LogLevel::Error => "Error",
+            LogLevel::Fatal => "Fatal",
+            LogLevel::Off => "Off",
+            LogLevel::Custom(_) => self.as_str(),
+        };
+        serializer.serialize_str(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for LogLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        LogLevel::from_str(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown log level: {}", name)))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Registered {
+    name: &'static str,
+    short_name: &'static str,
+    severity: u16,
+    style: Option<Style>,
+}
+
+// Custom levels are never removed, so their names can be handed out as `&'static str`
+static CUSTOM_LEVELS: RwLock<Vec<Registered>> = RwLock::new(Vec::new());
+
+/// Handle to a level defined with [`LevelDef::register`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomLevel(u8);
+
+impl CustomLevel {
+    fn def(&self) -> Option<Registered> {
+        let levels = CUSTOM_LEVELS.read().ok()?;
+        levels.get(self.0 as usize).copied()
+    }
+
+    fn find(name: &str) -> Option<CustomLevel> {
+        let levels = CUSTOM_LEVELS.read().ok()?;
+        levels
+            .iter()
+            .position(|def| {
+                def.name.eq_ignore_ascii_case(name) || def.short_name.eq_ignore_ascii_case(name)
+            })
+            .map(|index| CustomLevel(index as u8))
+    }
+}
+
+impl fmt::Debug for CustomLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(LogLevel::Custom(*self).as_str())
+    }
+}
+
+/// Definition of a user level such as `NOTICE` or `AUDIT`, placed among the built-in
+/// levels by its [`severity`](LogLevel::severity).
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::LevelDef;
+///
+/// let notice = LevelDef::new("NOTICE", 250)
+///     .with_short_name("NTC")
+///     .with_style(Style::new().cyan())
+///     .register()
+///     .unwrap();
+/// assert!(notice > LogLevel::Info && notice < LogLevel::Warn);
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// logger.log(notice, "maintenance window starts at 02:00").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct LevelDef {
+    name: String,
+    short_name: Option<String>,
+    severity: u16,
+    style: Option<Style>,
+}
+
+impl LevelDef {
+    pub fn new(name: &str, severity: u16) -> Self {
+        Self {
+            name: name.to_uppercase(),
+            short_name: None,
+            severity: severity.min(u16::MAX - 1),
+            style: None,
+        }
+    }
+
+    // Defaults to the first three letters of the name
+    pub fn with_short_name(mut self, short_name: &str) -> Self {
+        self.short_name = Some(short_name.to_uppercase());
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Makes the level known to parsing, ordering and the formatters; registering a
+    /// name again returns the existing level.
+    pub fn register(self) -> Result<LogLevel, LogFlowError> {
+        if self.name.is_empty() {
+            return Err(LogFlowError::Config(
+                "Custom level needs a name".to_string(),
+            ));
+        }
+        if let Some(level) = LogLevel::from_str(&self.name) {
+            return match level {
+                LogLevel::Custom(_) => Ok(level),
+                _ => Err(LogFlowError::Config(format!(
+                    "{} is a built-in level",
+                    self.name
+                ))),
+            };
+        }
+
+        let mut levels = CUSTOM_LEVELS
+            .write()
+            .map_err(|_| LogFlowError::Poisoned("custom levels".to_string()))?;
+        if levels.len() >= usize::from(u8::MAX - FIRST_CUSTOM_CODE) {
+            return Err(LogFlowError::Config("Too many custom levels".to_string()));
+        }
+
+        let short_name = self
+            .short_name
+            .unwrap_or_else(|| self.name.chars().take(3).collect());
+        levels.push(Registered {
+            name: Box::leak(self.name.into_boxed_str()),
+            short_name: Box::leak(short_name.into_boxed_str()),
+            severity: self.severity,
+            style: self.style,
+        });
+        Ok(LogLevel::Custom(CustomLevel((levels.len() - 1) as u8)))
+    }
 }
 
 impl fmt::Display for LogLevel {
@@ -64,8 +319,78 @@ impl fmt::Display for LogLevel {
     }
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
+#[derive(Debug, Clone, Copy)]
+struct Boost {
+    level: LogLevel,
+    until: Instant,
+}
+
+/// Global level of a running logger, changeable without rebuilding it.
+#[derive(Debug)]
+pub struct LevelCell(AtomicU8);
+
+impl LevelCell {
+    pub fn new(level: LogLevel) -> Self {
+        Self(AtomicU8::new(level.code()))
+    }
+
+    pub fn get(&self) -> LogLevel {
+        LogLevel::from_code(self.0.load(Ordering::Relaxed))
+    }
+
+    // Returns the previous level
+    pub fn set(&self, level: LogLevel) -> LogLevel {
+        LogLevel::from_code(self.0.swap(level.code(), Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LevelBoost {
+    active: Mutex<Option<Boost>>,
+}
+
+pub enum BoostState {
+    Inactive,
+    Active(LogLevel),
+    Expired(LogLevel),
+}
+
+impl LevelBoost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, level: LogLevel, duration: Duration) {
+        if let Ok(mut active) = self.active.lock() {
+            *active = Some(Boost {
+                level,
+                until: Instant::now() + duration,
+            });
+        }
+    }
+
+    pub fn clear(&self) -> Option<LogLevel> {
+        self.active
+            .lock()
+            .ok()
+            .and_then(|mut active| active.take())
+            .map(|boost| boost.level)
+    }
+
+    // An expired boost is cleared on the first check after its deadline
+    pub fn check(&self) -> BoostState {
+        let mut active = match self.active.lock() {
+            Ok(active) => active,
+            Err(_) => return BoostState::Inactive,
+        };
+
+        match *active {
+            Some(boost) if boost.until <= Instant::now() => {
+                *active = None;
+                BoostState::Expired(boost.level)
+            }
+            Some(boost) => BoostState::Active(boost.level),
+            None => BoostState::Inactive,
+        }
     }
 }
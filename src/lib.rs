@@ -28,31 +28,93 @@
 //! }
 //! ```
 
+mod ansi;
+mod backtrace;
+pub mod batch;
+pub mod budget;
+pub mod color;
+pub mod compat;
+pub mod compression;
 pub mod config;
 pub mod context;
+pub mod dedup;
+mod deferred;
+pub mod diagnostics;
+pub mod diff;
+#[cfg(feature = "elasticsearch")]
+pub mod elasticsearch;
+pub mod facade;
+pub mod filter;
 pub mod formatter;
+pub mod gelf;
+pub mod http;
+#[cfg(all(unix, feature = "journald"))]
+pub mod journald;
 pub mod level;
+#[cfg(feature = "log")]
+pub mod log_compat;
 pub mod logger;
+#[cfg(feature = "loki")]
+pub mod loki;
 pub mod macros;
+pub mod memory;
+pub mod notify;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "otlp")]
+pub mod otlp;
 pub mod output;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod pipeline;
+pub mod propagate;
+pub mod ratelimit;
+mod sampling;
+pub mod schema;
+pub mod scopes;
+pub mod spool;
+pub mod syslog;
+pub mod testkit;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+pub mod worker;
 
 #[cfg(feature = "async")]
 pub mod async_logger;
 
 pub use config::*;
 pub use context::*;
+pub use facade::{CaptureLogger, Log, NoopLogger};
 pub use formatter::*;
 pub use level::*;
 pub use logger::*;
 
+#[cfg(feature = "log")]
+pub use log_compat::LogCompat;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::LogFlowLayer;
+
 pub use macros::*;
+pub use notify::{Notification, NotificationKind};
+pub use propagate::ContextPropagatingSpawn;
+/// Test helpers, also available as `logflow::testkit`.
+pub use testkit as test;
+pub use worker::WorkerGuard;
 
 #[cfg(feature = "async")]
 pub use async_logger::*;
 
+/// `false` when the `disabled` feature compiles every logging call down to a no-op.
+pub const LOGGING_ENABLED: bool = !cfg!(feature = "disabled");
+
 /// Re-export commonly used types
 pub mod prelude {
-    pub use crate::output::OutputType;
+    pub use crate::compression::Compression;
+    pub use crate::formatter::{Style, TimestampFormat, Timezone};
+    pub use crate::memory::MemorySink;
+    pub use crate::output::{
+        BrokenPipePolicy, DocBuffer, FileBuffer, IdleFlush, OutputType, Rotation, Router,
+    };
     pub use crate::{LogConfig, LogContext, LogFlow, LogLevel};
 
     #[cfg(feature = "async")]
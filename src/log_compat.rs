@@ -0,0 +1,74 @@
+use crate::level::LogLevel;
+use crate::logger::LogFlow;
+
+/// Adapter that sends records from the `log` crate, e.g. from dependencies, into a
+/// [`LogFlow`] logger, keeping their target, module and file/line.
+///
+/// Records are nested under the current scope and filtered like LogFlow's own.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::LogCompat;
+///
+/// let logger = LogFlow::new().with_level(LogLevel::Debug).build().unwrap();
+/// LogCompat::new(logger).init().unwrap();
+///
+/// log::info!(target: "my_dependency", "connected");
+/// ```
+pub struct LogCompat {
+    logger: LogFlow,
+}
+
+impl LogCompat {
+    pub fn new(logger: LogFlow) -> Self {
+        Self { logger }
+    }
+
+    // Installs the adapter as the `log` crate's global logger, passing every level
+    // through so LogFlow's own filtering decides
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(log::LevelFilter::Trace);
+        Ok(())
+    }
+
+    pub fn logger(&self) -> &LogFlow {
+        &self.logger
+    }
+}
+
+pub fn level_from_log(level: log::Level) -> LogLevel {
+    match level {
+        log::Level::Error => LogLevel::Error,
+        log::Level::Warn => LogLevel::Warn,
+        log::Level::Info => LogLevel::Info,
+        log::Level::Debug => LogLevel::Debug,
+        log::Level::Trace => LogLevel::Trace,
+    }
+}
+
+impl log::Log for LogCompat {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.logger
+            .enabled_for(level_from_log(metadata.level()), metadata.target())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let mut context = self.logger.scoped_context(record.target().to_string());
+        if let Some(module) = record.module_path() {
+            context = context.with_module(module);
+        }
+        if let (Some(file), Some(line)) = (record.file(), record.line()) {
+            context = context.with_file_line(file, line);
+        }
+
+        let message = record.args().to_string();
+        let _ =
+            self.logger
+                .log_with_context(level_from_log(record.level()), &message, Some(context));
+    }
+
+    fn flush(&self) {
+        let _ = self.logger.flush();
+    }
+}
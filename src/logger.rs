@@ -1,9 +1,29 @@
+use crate::budget::{BudgetState, BudgetTracker};
+use crate::color::{ColorDepth, ColorMode};
 use crate::config::LogConfig;
-use crate::context::{ContextStack, LogContext};
-use crate::formatter::Formatter;
-use crate::level::LogLevel;
-use crate::output::{Output, OutputType};
-use std::sync::{Arc, Mutex};
+use crate::context::{
+    typed_field_methods, ContextSnapshot, ContextStack, FieldValue, Location, LogContext,
+};
+use crate::dedup::{Deduplicator, Repeated};
+use crate::deferred::DeferredScopes;
+use crate::diagnostics;
+use crate::facade::Log;
+use crate::formatter::{
+    FieldOrder, FieldRenderer, Formatter, FormatterType, IndentStyle, JsonFields, Overflow, Style,
+    Theme, TimestampFormat, Timezone,
+};
+use crate::level::{BoostState, LevelBoost, LevelCell, LogLevel};
+use crate::memory::RecordQuery;
+use crate::notify::{self, Notification};
+use crate::output::{BrokenPipePolicy, Output, OutputId, OutputType, Record};
+use crate::pipeline::RecordHooks;
+use crate::ratelimit::{suppressed_notice, RateDecision, RateLimiter};
+use crate::sampling;
+use crate::syslog::Facility;
+use crate::worker::{Worker, WorkerGuard};
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -14,6 +34,13 @@ pub enum LogFlowError {
     Config(String),
     #[error("Context error: {0}")]
     Context(String),
+    #[error("Field `{key}` could not be serialized: {source}")]
+    Field {
+        key: String,
+        source: serde_json::Error,
+    },
+    #[error("Lock poisoned: {0}")]
+    Poisoned(String),
 }
 
 type Result<T> = std::result::Result<T, LogFlowError>;
@@ -23,23 +50,67 @@ pub struct LogFlow {
     formatter: Formatter,
     output: Arc<Mutex<Output>>,
     context_stack: ContextStack,
+    level: Arc<LevelCell>,
+    boost: Arc<LevelBoost>,
+    budget: Arc<BudgetTracker>,
+    rate_limiter: Arc<RateLimiter>,
+    dedup: Arc<Deduplicator>,
+    hooks: Arc<RecordHooks>,
+    deferred: Arc<DeferredScopes>,
+    worker: Option<Worker>,
 }
 
 impl LogFlow {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> LogFlowBuilder {
         LogFlowBuilder::new()
     }
 
+    /// Builds a logger from the `LOGFLOW_*` environment variables, see
+    /// [`LogConfig::apply_env`].
+    pub fn from_env() -> Result<Self> {
+        Self::with_config(LogConfig::from_env()?)
+    }
+
     pub fn with_config(config: LogConfig) -> Result<Self> {
+        // Deferred records are matched to their scope by context id
+        if config.deferred_scopes && !config.id_gen.unique() {
+            return Err(LogFlowError::Config(
+                "Deferred scopes need distinct, non-empty context ids".to_string(),
+            ));
+        }
+
         let formatter = Formatter::new(config.clone());
-        let output = Output::new(config.output.clone())?;
+        let output = Output::from_config(&config)?;
 
-        Ok(Self {
+        if config.check_outputs_on_build {
+            output.health_check()?;
+        }
+
+        let logger = Self {
             formatter,
             output: Arc::new(Mutex::new(output)),
+            level: Arc::new(LevelCell::new(config.level)),
+            budget: Arc::new(BudgetTracker::new(config.budget)),
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limit)),
+            dedup: Arc::new(Deduplicator::new(config.dedup_window)),
+            hooks: Arc::new(RecordHooks::default()),
+            deferred: Arc::new(DeferredScopes::default()),
+            context_stack: if config.thread_local_scopes {
+                ContextStack::per_thread()
+            } else {
+                ContextStack::new()
+            },
             config,
-            context_stack: ContextStack::new(),
-        })
+            boost: Arc::new(LevelBoost::new()),
+            worker: None,
+        };
+
+        if logger.config.dump_config {
+            logger.write_config_dump();
+        }
+
+        Ok(logger)
     }
 
     pub fn log(&self, level: LogLevel, message: &str) -> Result<()> {
@@ -52,49 +123,413 @@ impl LogFlow {
         message: &str,
         extra_context: Option<LogContext>,
     ) -> Result<()> {
-        let target = std::module_path!().to_string();
+        self.log_rendered(level, message, extra_context, |formatter, record| {
+            formatter.render(record.level, &record.message, &record.context)
+        })
+    }
+
+    /// Logs `message` from `location`, which becomes the record's target, module and
+    /// file/line; the logging macros pass their call site here.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().with_file_line(true).build().unwrap();
+    /// logger.log_at(LogLevel::Info, "from here", &logflow::location!()).unwrap();
+    /// ```
+    pub fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        let context = self.scoped_context(location.module.to_string());
+        self.log_with_context(level, message, Some(context.with_location(location)))
+    }
+
+    fn format_record(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> Option<(Record, bool)> {
+        self.render_routed(level, message, extra_context, |formatter, record| {
+            formatter.render(record.level, &record.message, &record.context)
+        })
+    }
+
+    // Routes, processes and renders a record with `render`, then writes it out
+    fn log_rendered(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+        render: impl FnOnce(&Formatter, &Record) -> String,
+    ) -> Result<()> {
+        match self.render_routed(level, message, extra_context, render) {
+            Some(routed) => self.emit(vec![routed]),
+            None => Ok(()),
+        }
+    }
+
+    // None when no output takes the record or a processor drops it
+    fn render_routed(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+        render: impl FnOnce(&Formatter, &Record) -> String,
+    ) -> Option<(Record, bool)> {
+        let primary = self.route(level, extra_context.as_ref(), std::module_path!())?;
+
+        let mut record = self.processed_record(level, message, extra_context)?;
+        record.line = render(&self.formatter, &record);
+
+        Some((record, primary))
+    }
+
+    // Unformatted record after the processors; None when one of them drops it
+    fn processed_record(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> Option<Record> {
+        let context = self.build_context(level, extra_context);
+        let mut record = Record::new(level, message, context, String::new());
+        self.config.process(&mut record).then_some(record)
+    }
+
+    /// Whether a record at `level` logged here would currently be written anywhere, so
+    /// expensive messages can be skipped; the logging macros check it before formatting.
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.enabled_in(level, None, None)
+    }
+
+    /// Like [`enabled`](Self::enabled), for a record from `target` in the current scope.
+    pub fn enabled_for(&self, level: LogLevel, target: &str) -> bool {
+        self.enabled_in(level, None, Some(target))
+    }
+
+    /// Like [`enabled`](Self::enabled), for a record logged with [`log_at`](Self::log_at).
+    pub fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        self.enabled_for(level, location.module)
+    }
+
+    // `route` without sampling, which is only decided when the record is logged; the
+    // target defaults to the scope's
+    fn enabled_in(
+        &self,
+        level: LogLevel,
+        context: Option<&LogContext>,
+        target: Option<&str>,
+    ) -> bool {
+        let current = match context {
+            Some(_) => None,
+            None => self.context_stack.current(),
+        };
+        let scope = context.or(current.as_ref());
+        let target = target
+            .or(scope.map(|scope| scope.target.as_str()))
+            .unwrap_or(std::module_path!());
+
+        let threshold = self.threshold(scope, target);
+        let floor = self.config.output_floor(threshold).max(self.budget.floor());
+        self.config.should_log_at(level, floor, target)
+    }
+
+    // Context for a record from `target`, e.g. another facade, nested under the current scope
+    pub(crate) fn scoped_context(&self, target: String) -> LogContext {
+        match self.context_stack.current() {
+            Some(current) => self.config.child_context(&current, target),
+            None => self.config.new_context(target),
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn config(&self) -> &LogConfig {
+        &self.config
+    }
+
+    // None when no output takes the record, otherwise whether the primary output does;
+    // extra outputs filter on their own levels
+    fn route(&self, level: LogLevel, context: Option<&LogContext>, target: &str) -> Option<bool> {
+        let current = match context {
+            Some(_) => None,
+            None => self.context_stack.current(),
+        };
+        let scope = context.or(current.as_ref());
+        // Records in a scope are filtered by the scope's target
+        let target = scope.map_or(target, |scope| scope.target.as_str());
+
+        let threshold = self.threshold(scope, target);
+        let floor = self.config.output_floor(threshold).max(self.budget.floor());
+        if !self.config.should_log_at(level, floor, target) {
+            return None;
+        }
 
-        if !self.config.should_log(level, &target) {
+        // Sampled out records are dropped before any formatting work
+        if let Some(rate) = self.config.sample_rate(level) {
+            if !sampling::keep(rate) {
+                return None;
+            }
+        }
+        Some(level >= threshold)
+    }
+
+    pub fn effective_level(&self) -> LogLevel {
+        let current = self.context_stack.current();
+        let target = current
+            .as_ref()
+            .map_or(std::module_path!(), |scope| scope.target.as_str());
+        self.threshold(current.as_ref(), target)
+    }
+
+    // A scope level replaces the global or env filter level; an active boost still
+    // lowers it further
+    fn threshold(&self, scope: Option<&LogContext>, target: &str) -> LogLevel {
+        let base = scope
+            .and_then(|scope| scope.min_level)
+            .or_else(|| self.config.filter_level(target))
+            .unwrap_or_else(|| self.level.get());
+
+        let threshold = match self.boost.check() {
+            BoostState::Active(boosted) => boosted.min(base),
+            BoostState::Expired(boosted) => {
+                self.write_notice(&format!(
+                    "Log level boost to {} expired, restored {}",
+                    boosted,
+                    self.level.get()
+                ));
+                base
+            }
+            BoostState::Inactive => base,
+        };
+
+        // A spent budget overrides scopes and boosts alike
+        match self.budget.check() {
+            BudgetState::Degraded(level) => threshold.max(level),
+            BudgetState::Renewed => {
+                self.write_notice("Log budget renewed, full logging restored");
+                threshold
+            }
+            BudgetState::Open => threshold,
+        }
+    }
+
+    /// Changes the global level of the running logger; scope levels, env filter
+    /// directives and boosts still apply on top of it.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// logger.set_level(LogLevel::Debug);
+    /// assert_eq!(logger.effective_level(), LogLevel::Debug);
+    /// ```
+    pub fn set_level(&self, level: LogLevel) {
+        let previous = self.level.set(level);
+        if previous != level {
+            self.write_notice(&format!("Log level changed from {} to {}", previous, level));
+        }
+    }
+
+    pub fn boost_level(&self, level: LogLevel, duration: Duration) {
+        self.boost.set(level, duration);
+        self.write_notice(&format!(
+            "Log level boosted to {} for {:?}",
+            level, duration
+        ));
+    }
+
+    pub fn cancel_boost(&self) {
+        if let Some(boosted) = self.boost.clear() {
+            self.write_notice(&format!(
+                "Log level boost to {} cancelled, restored {}",
+                boosted,
+                self.level.get()
+            ));
+        }
+    }
+
+    // Level changes are always recorded, regardless of the current threshold
+    fn write_notice(&self, message: &str) {
+        let record = self.render_record(LogLevel::Info, message, None);
+        let _ = self.deliver(vec![(record, true)]);
+    }
+
+    fn write_budget_notice(&self) {
+        if let Some(budget) = self.budget.budget() {
+            self.write_notice(&format!(
+                "Log budget exhausted, only {} and above until the {:?} period ends",
+                budget.degraded_level, budget.period
+            ));
+        }
+    }
+
+    // Written regardless of level or filters, since it explains what they are
+    fn write_config_dump(&self) {
+        let context = self.config.summary().into_iter().fold(
+            self.config.new_context(std::module_path!().to_string()),
+            |context, (key, value)| context.with_field(key, value),
+        );
+        let record = self.render_record(LogLevel::Info, "Logger configuration", Some(context));
+        let _ = self.deliver(vec![(record, true)]);
+    }
+
+    fn lock_output(&self) -> MutexGuard<'_, Output> {
+        lock_output(&self.output, &self.config)
+    }
+
+    // Hands records to the writer thread in non-blocking mode, writes them here otherwise
+    fn emit(&self, records: Vec<(Record, bool)>) -> Result<()> {
+        let mut kept = Vec::with_capacity(records.len());
+        for (record, primary) in records {
+            let (fresh, repeated) = self.dedup.check(&record, primary);
+            if let Some(repeated) = repeated {
+                kept.push(self.repeated_record(repeated));
+            }
+            if !fresh {
+                continue;
+            }
+
+            match self.rate_limiter.check(&record) {
+                RateDecision::Allow => kept.push((record, primary)),
+                RateDecision::Resume { key, suppressed } => {
+                    let notice = suppressed_notice(&key, suppressed);
+                    kept.push((self.render_record(LogLevel::Info, &notice, None), true));
+                    kept.push((record, primary));
+                }
+                RateDecision::Suppress => {}
+            }
+        }
+
+        if kept.is_empty() {
             return Ok(());
         }
+        self.deliver(kept)
+    }
+
+    fn repeated_record(&self, repeated: Repeated) -> (Record, bool) {
+        let record = self.render_record(repeated.level, &repeated.message(), None);
+        (record, repeated.primary)
+    }
 
+    // Notices skip the rate limiter, but still count against the budget
+    fn deliver(&self, records: Vec<(Record, bool)>) -> Result<()> {
+        let exhausted = records.iter().fold(false, |spent, (record, _)| {
+            self.budget.charge(record.line.len() + 1) || spent
+        });
+        if exhausted {
+            self.write_budget_notice();
+        }
+        for (record, primary) in &records {
+            self.hooks.notify(record);
+            if *primary {
+                notify::dispatch(&self.config.notifications, record.level, &record.message);
+            }
+        }
+
+        let records = self.deferred.defer(records);
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.write_out(records)
+    }
+
+    fn write_out(&self, records: Vec<(Record, bool)>) -> Result<()> {
+        let records = match &self.worker {
+            Some(worker) => match worker.send(records) {
+                Ok(()) => return Ok(()),
+                Err(records) => records,
+            },
+            None => records,
+        };
+
+        self.lock_output().write_records_to(&records)?;
+        Ok(())
+    }
+
+    fn spawn_worker(&mut self, capacity: usize) -> Result<WorkerGuard> {
+        let (worker, guard) =
+            Worker::spawn(Arc::clone(&self.output), self.config.clone(), capacity)?;
+        self.worker = Some(worker);
+        Ok(guard)
+    }
+
+    fn build_context(&self, level: LogLevel, extra_context: Option<LogContext>) -> LogContext {
         let context = if let Some(ctx) = extra_context {
             ctx
         } else if let Some(current_ctx) = self.context_stack.current() {
-            current_ctx.child(target)
+            self.config
+                .child_context(&current_ctx, std::module_path!().to_string())
         } else {
-            LogContext::new(target)
+            self.config.new_context(std::module_path!().to_string())
         };
 
-        let formatted = self.formatter.format(level, message, &context);
+        let context = self.config.apply_dynamic_fields(level, context);
+        let context = self.config.limit_fields(context);
+        #[cfg(feature = "opentelemetry")]
+        let context = crate::otel::attach_trace_ids(context);
+        self.config.attach_backtrace(level, context)
+    }
 
-        if let Ok(mut output) = self.output.lock() {
-            output.write_line(&formatted)?;
-        }
+    pub fn format_only(&self, level: LogLevel, message: &str) -> String {
+        self.format_only_with_context(level, message, None)
+    }
 
-        Ok(())
+    pub fn format_only_with_context(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> String {
+        self.render_record(level, message, extra_context).line
     }
 
-    pub fn log_with_subtitle(&self, level: LogLevel, subtitle: &str, message: &str) -> Result<()> {
-        let target = std::module_path!().to_string();
+    fn render_record(
+        &self,
+        level: LogLevel,
+        message: &str,
+        extra_context: Option<LogContext>,
+    ) -> Record {
+        let context = self.build_context(level, extra_context);
+        let line = self.formatter.render(level, message, &context);
+        Record::new(level, message, context, line)
+    }
 
-        if !self.config.should_log(level, &target) {
-            return Ok(());
-        }
+    pub fn set_colors(&self, enabled: bool) {
+        self.formatter.toggles().set_colors(enabled);
+    }
 
+    pub fn set_show_timestamps(&self, enabled: bool) {
+        self.formatter.toggles().set_timestamps(enabled);
+    }
+
+    pub fn set_show_date(&self, enabled: bool) {
+        self.formatter.toggles().set_show_date(enabled);
+    }
+
+    pub fn set_show_target(&self, enabled: bool) {
+        self.formatter.toggles().set_show_target(enabled);
+    }
+
+    pub fn set_show_module(&self, enabled: bool) {
+        self.formatter.toggles().set_show_module(enabled);
+    }
+
+    pub fn set_show_file_line(&self, enabled: bool) {
+        self.formatter.toggles().set_show_file_line(enabled);
+    }
+
+    pub fn formatter(&self) -> &Formatter {
+        &self.formatter
+    }
+
+    pub fn log_with_subtitle(&self, level: LogLevel, subtitle: &str, message: &str) -> Result<()> {
         let context = if let Some(current_ctx) = self.context_stack.current() {
-            current_ctx.child(target).with_subtitle(subtitle)
+            self.config
+                .child_context(&current_ctx, std::module_path!().to_string())
         } else {
-            LogContext::new(target).with_subtitle(subtitle)
+            self.config.new_context(std::module_path!().to_string())
         };
 
-        let formatted = self.formatter.format(level, message, &context);
-
-        if let Ok(mut output) = self.output.lock() {
-            output.write_line(&formatted)?;
-        }
-
-        Ok(())
+        self.log_with_context(level, message, Some(context.with_subtitle(subtitle)))
     }
 
     pub fn trace(&self, message: &str) -> Result<()> {
@@ -121,6 +556,15 @@ impl LogFlow {
         self.log(LogLevel::Fatal, message)
     }
 
+    /// Logs `message` at Fatal, writes out every output (including a non-blocking
+    /// writer's queue and network batches) and exits the process with `code`.
+    pub fn fatal_exit(&self, code: i32, message: &str) -> ! {
+        let _ = self.fatal(message);
+        let _ = self.flush();
+        let _ = self.lock_output().close();
+        std::process::exit(code)
+    }
+
     pub fn trace_with_subtitle(&self, subtitle: &str, message: &str) -> Result<()> {
         self.log_with_subtitle(LogLevel::Trace, subtitle, message)
     }
@@ -145,115 +589,831 @@ impl LogFlow {
         self.log_with_subtitle(LogLevel::Fatal, subtitle, message)
     }
 
-    pub fn begin_scope(&self, name: &str) -> LogScope {
-        let target = format!("{}::{}", std::module_path!(), name);
-        let context = if let Some(current) = self.context_stack.current() {
-            current.child(target)
-        } else {
-            LogContext::new(target)
-        };
-
-        self.context_stack.push(context.clone());
+    pub fn list<I>(&self, header: &str, items: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: std::fmt::Display,
+    {
+        self.list_at(LogLevel::Info, header, items)
+    }
+
+    pub fn list_at<I>(&self, level: LogLevel, header: &str, items: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: std::fmt::Display,
+    {
+        self.log_rendered(level, header, None, |formatter, record| {
+            let items: Vec<String> = items.into_iter().map(|item| item.to_string()).collect();
+            formatter.format_list(record.level, &record.message, &items, &record.context)
+        })
+    }
+
+    pub fn diff(&self, header: &str, old: &str, new: &str) -> Result<()> {
+        self.diff_at(LogLevel::Info, header, old, new)
+    }
+
+    pub fn diff_at(&self, level: LogLevel, header: &str, old: &str, new: &str) -> Result<()> {
+        self.log_rendered(level, header, None, |formatter, record| {
+            formatter.format_diff(record.level, &record.message, old, new, &record.context)
+        })
+    }
+
+    /// Draws `message` centered in a box, for startup banners.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// logger.banner("Release v2.1.0 deployed").unwrap();
+    /// logger
+    ///     .panel_at(LogLevel::Error, "3 checks failed", "disk full\nno route to host")
+    ///     .unwrap();
+    /// ```
+    pub fn banner(&self, message: &str) -> Result<()> {
+        self.banner_at(LogLevel::Info, message)
+    }
+
+    pub fn banner_at(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.log_rendered(level, message, None, |formatter, record| {
+            formatter.format_banner(record.level, &record.message, &record.context)
+        })
+    }
+
+    /// Draws `body` in a box with `title` in its top border.
+    pub fn panel(&self, title: &str, body: &str) -> Result<()> {
+        self.panel_at(LogLevel::Info, title, body)
+    }
+
+    pub fn panel_at(&self, level: LogLevel, title: &str, body: &str) -> Result<()> {
+        self.log_rendered(level, title, None, |formatter, record| {
+            formatter.format_panel(record.level, &record.message, body, &record.context)
+        })
+    }
+
+    pub fn begin_scope(&self, name: &str) -> LogScope<'_> {
+        self.push_scope(name, None, None)
+    }
+
+    pub fn begin_scope_with_level(&self, name: &str, level: LogLevel) -> LogScope<'_> {
+        self.push_scope(name, Some(level), None)
+    }
+
+    /// Starts a "request" scope with a fresh correlation id, attached to every record
+    /// logged inside it, nested scopes included.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// let request = logger.begin_request_scope();
+    /// let db = request.begin_scope("db");
+    /// assert_eq!(db.correlation_id(), request.correlation_id());
+    /// ```
+    pub fn begin_request_scope(&self) -> LogScope<'_> {
+        let id = self.config.id_gen.next_id();
+        self.begin_request_scope_with_id(&id)
+    }
+
+    // For ids received from upstream, e.g. an `X-Request-Id` header
+    pub fn begin_request_scope_with_id(&self, id: &str) -> LogScope<'_> {
+        self.push_scope("request", None, Some(id))
+    }
+
+    fn push_scope(
+        &self,
+        name: &str,
+        min_level: Option<LogLevel>,
+        correlation_id: Option<&str>,
+    ) -> LogScope<'_> {
+        let target = format!("{}::{}", std::module_path!(), name);
+        let mut context = if let Some(current) = self.context_stack.current() {
+            self.config.child_context(&current, target)
+        } else {
+            self.config.new_context(target)
+        }
+        .with_scope(name);
+
+        if let Some(level) = min_level {
+            context = context.with_min_level(level);
+        }
+        if let Some(id) = correlation_id {
+            context = context.with_correlation_id(id);
+        }
+
+        self.context_stack.push(context.clone());
+        if self.config.deferred_scopes {
+            self.deferred.open(name, &context);
+        }
+        let start = self.formatter.format_scope_start(name, &context);
+        self.write_scope_marker(start, name, &context);
+
+        LogScope {
+            logger: self,
+            context,
+            name: name.to_string(),
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    pub fn end_scope(&self) {
+        self.context_stack.pop();
+    }
+
+    // Group, section or block markers of the CI formats, written past filters and limits
+    // so every opened scope gets closed
+    fn write_scope_marker(&self, marker: Option<String>, name: &str, context: &LogContext) {
+        if let Some(line) = marker {
+            let record = Record::new(LogLevel::Info, name, context.clone(), line);
+            let _ = self.write_out(vec![(record, true)]);
+        }
+    }
+
+    // Writes out a deferred scope's block once its outermost scope closes
+    fn close_deferred(&self, id: &str) -> Result<()> {
+        match self.deferred.close(id) {
+            Some(block) => self.write_out(self.formatter.format_scope_block(block)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn with_field<T>(&self, key: &str, value: T) -> FieldLogger<'_>
+    where
+        T: serde::Serialize,
+    {
+        let mut context = self
+            .context_stack
+            .current()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+
+        context = context.with_field_or(key, value, self.config.serialization_placeholder);
+
+        FieldLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    /// Like [`Self::with_field`], but fails if `value` does not serialize.
+    pub fn try_with_field<T>(&self, key: &str, value: T) -> Result<FieldLogger<'_>>
+    where
+        T: serde::Serialize,
+    {
+        let context = self
+            .context_stack
+            .current()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+
+        Ok(FieldLogger {
+            logger: self,
+            context: context.try_with_field(key, value)?,
+        })
+    }
+
+    /// Starts a field logger with a typed field; see [`FieldValue`].
+    pub fn with_value(&self, key: &str, value: impl Into<FieldValue>) -> FieldLogger<'_> {
+        let context = self
+            .context_stack
+            .current()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()));
+
+        FieldLogger {
+            logger: self,
+            context: context.with_value(key, value),
+        }
+    }
+
+    typed_field_methods!(borrowing FieldLogger<'_>);
+
+    /// Another handle to this logger, sharing its outputs, scopes, level, display
+    /// toggles and limits.
+    ///
+    /// Fields bound to the child with [`bind_field`](Self::bind_field) stay on every
+    /// record it logs, so a subsystem can hold its own pre-tagged logger:
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// let db = logger.child().bind_field("subsystem", "db");
+    /// db.info("pool ready").unwrap();
+    /// ```
+    pub fn child(&self) -> LogFlow {
+        Self {
+            config: self.config.clone(),
+            formatter: self.formatter.with_shared_toggles(self.config.clone()),
+            output: Arc::clone(&self.output),
+            context_stack: self.context_stack.clone(),
+            level: Arc::clone(&self.level),
+            boost: Arc::clone(&self.boost),
+            budget: Arc::clone(&self.budget),
+            rate_limiter: Arc::clone(&self.rate_limiter),
+            dedup: Arc::clone(&self.dedup),
+            hooks: Arc::clone(&self.hooks),
+            deferred: Arc::clone(&self.deferred),
+            worker: self.worker.clone(),
+        }
+    }
+
+    /// Records kept by `OutputType::RingBuffer` outputs that match `query`, oldest
+    /// first, e.g. to attach to a crash report; see [`RecordQuery`].
+    pub fn recent(&self, query: &RecordQuery) -> Vec<Record> {
+        if let Some(worker) = &self.worker {
+            worker.flush();
+        }
+        self.lock_output().recent(query)
+    }
+
+    /// Calls `hook` with every record this logger writes, on the logging thread, e.g. to
+    /// count errors or feed a UI without parsing formatted output.
+    ///
+    /// ```
+    /// # if !logflow::LOGGING_ENABLED { return; }
+    /// use logflow::prelude::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// let errors = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&errors);
+    /// logger.on_record(move |record| {
+    ///     if record.level >= LogLevel::Error {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// });
+    ///
+    /// logger.error("disk full").unwrap();
+    /// assert_eq!(errors.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn on_record<F>(&self, hook: F)
+    where
+        F: Fn(&Record) + Send + Sync + 'static,
+    {
+        self.hooks.add(Box::new(hook));
+    }
+
+    pub fn bind_field<T>(mut self, key: &str, value: T) -> Self
+    where
+        T: serde::Serialize,
+    {
+        self.config = self.config.with_global_field(key, value);
+        self
+    }
+
+    pub fn try_bind_field<T>(mut self, key: &str, value: T) -> Result<Self>
+    where
+        T: serde::Serialize,
+    {
+        self.config = self.config.try_with_global_field(key, value)?;
+        Ok(self)
+    }
+
+    // Records carry `timestamp` as their event time instead of the time they were logged
+    pub fn with_timestamp<T>(&self, timestamp: T) -> FieldLogger<'_>
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        let context = self
+            .context_stack
+            .current()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()))
+            .with_timestamp(timestamp);
+
+        FieldLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    // One-off emphasis for a record, without changing its level
+    pub fn styled(&self, style: Style) -> FieldLogger<'_> {
+        let context = self
+            .context_stack
+            .current()
+            .unwrap_or_else(|| self.config.new_context(std::module_path!().to_string()))
+            .with_style(style);
+
+        FieldLogger {
+            logger: self,
+            context,
+        }
+    }
+
+    pub fn batch(&self) -> LogBatch<'_> {
+        LogBatch {
+            logger: self,
+            lines: Vec::new(),
+        }
+    }
+
+    fn write_batch(&self, lines: Vec<(Record, bool)>) -> Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        // One `emit` for the whole batch: the records that dedup and rate limiting keep,
+        // plus any notices they add, go out in order under one output lock (or as one
+        // message to the worker), except those held back by an open deferred scope
+        self.emit(lines)
+    }
+
+    pub fn critical_section<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let previous = self.set_direct_writes(true);
+        let _ = self.write_pending();
+
+        struct Restore<'a>(&'a LogFlow, bool);
+
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.flush();
+                self.0.set_direct_writes(self.1);
+            }
+        }
+
+        let _restore = Restore(self, previous);
+        f()
+    }
+
+    fn set_direct_writes(&self, direct: bool) -> bool {
+        let mut output = self.lock_output();
+        let previous = output.is_direct();
+        output.set_direct(direct);
+        previous
+    }
+
+    pub fn snapshot_context(&self) -> ContextSnapshot {
+        self.context_stack.snapshot()
+    }
+
+    pub fn in_context<F, R>(&self, snapshot: ContextSnapshot, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let _guard = self.context_stack.enter(snapshot);
+        f()
+    }
+
+    pub fn propagating<'a, F, A, R>(&'a self, f: F) -> impl Fn(A) -> R + Send + Sync + 'a
+    where
+        F: Fn(A) -> R + Send + Sync + 'a,
+    {
+        let snapshot = self.snapshot_context();
+        move |arg| self.in_context(snapshot.clone(), || f(arg))
+    }
+
+    pub fn current_depth(&self) -> usize {
+        self.context_stack.depth()
+    }
+
+    /// Health of each output: the primary one (each route, for a level router), then
+    /// the extra outputs, numbered in that order.
+    pub fn check_outputs(&self) -> Vec<(OutputId, Result<()>)> {
+        self.lock_output()
+            .health_checks()
+            .into_iter()
+            .map(|(id, result)| (id, result.map_err(LogFlowError::from)))
+            .collect()
+    }
+
+    /// Writes out pending records and clears the status line.
+    pub fn flush(&self) -> Result<()> {
+        self.write_pending()?;
+        self.lock_output().clear_status()?;
+        Ok(())
+    }
+
+    fn write_pending(&self) -> Result<()> {
+        if let Some(repeated) = self.dedup.take_pending() {
+            self.deliver(vec![self.repeated_record(repeated)])?;
+        }
+
+        if let Some(worker) = &self.worker {
+            if worker.flush() {
+                return Ok(());
+            }
+        }
+
+        self.lock_output().flush()?;
+        Ok(())
+    }
+
+    /// Pins `status`, such as a progress counter, as the last line of the terminal
+    /// while records scroll above it. Each call replaces it; `clear_status`, `flush`
+    /// and shutdown remove it. Outputs that aren't terminals ignore it.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// for done in 1..=3 {
+    ///     logger.set_status(&format!("processed {} / 3", done)).unwrap();
+    /// }
+    /// logger.clear_status().unwrap();
+    /// ```
+    pub fn set_status(&self, status: &str) -> Result<()> {
+        self.lock_output().set_status(status)?;
+        Ok(())
+    }
+
+    pub fn clear_status(&self) -> Result<()> {
+        self.lock_output().clear_status()?;
+        Ok(())
+    }
+}
+
+// A panic mid-write poisons the lock; the writer is kept if it still flushes, and only
+// reopened when it doesn't, instead of muting logging for good
+pub(crate) fn lock_output<'a>(
+    output: &'a Mutex<Output>,
+    config: &LogConfig,
+) -> MutexGuard<'a, Output> {
+    output.lock().unwrap_or_else(|poisoned| {
+        output.clear_poison();
+        let mut guard = poisoned.into_inner();
+        if guard.flush().is_ok() {
+            diagnostics::report(&LogFlowError::Poisoned("output lock".to_string()));
+            return guard;
+        }
+
+        diagnostics::report(&LogFlowError::Poisoned(
+            "output lock, reopening the output".to_string(),
+        ));
+        match Output::from_config(config) {
+            Ok(mut fresh) => {
+                fresh.set_direct(guard.is_direct());
+                *guard = fresh;
+            }
+            Err(err) => diagnostics::report(&LogFlowError::Output(err)),
+        }
+        guard
+    })
+}
+
+impl Default for LogFlow {
+    fn default() -> Self {
+        LogFlow::with_config(LogConfig::default()).unwrap()
+    }
+}
+
+pub struct LogFlowBuilder {
+    config: LogConfig,
+}
+
+impl LogFlowBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: LogConfig::default(),
+        }
+    }
+
+    pub fn with_level(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_level(level);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: crate::filter::EnvFilter) -> Self {
+        self.config = self.config.with_filter(filter);
+        self
+    }
+
+    pub fn with_sampling(mut self, level: LogLevel, rate: f64) -> Self {
+        self.config = self.config.with_sampling(level, rate);
+        self
+    }
+
+    pub fn with_env_filter(mut self, var: &str) -> Self {
+        self.config = self.config.with_env_filter(var);
+        self
+    }
+
+    pub fn with_colors(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_colors(enabled);
+        self
+    }
+
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.config = self.config.with_color_mode(mode);
+        self
+    }
+
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_timestamps(enabled);
+        self
+    }
+
+    pub fn with_date(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_date(enabled);
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.config = self.config.with_timezone(timezone);
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.config = self.config.with_timestamp_format(format);
+        self
+    }
+
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.config = self.config.with_field_order(order);
+        self
+    }
+
+    pub fn with_json_fields(mut self, json_fields: JsonFields) -> Self {
+        self.config = self.config.with_json_fields(json_fields);
+        self
+    }
+
+    pub fn with_json_key(mut self, key: &str, name: &str) -> Self {
+        self.config = self.config.with_json_key(key, name);
+        self
+    }
+
+    pub fn with_serialization_placeholder(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_serialization_placeholder(enabled);
+        self
+    }
+
+    pub fn with_max_field_len(mut self, len: usize) -> Self {
+        self.config = self.config.with_max_field_len(len);
+        self
+    }
+
+    pub fn with_max_fields_len(mut self, len: usize) -> Self {
+        self.config = self.config.with_max_fields_len(len);
+        self
+    }
+
+    pub fn with_output(mut self, output: OutputType) -> Self {
+        self.config = self.config.with_output(output);
+        self
+    }
+
+    pub fn with_target(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_target(enabled);
+        self
+    }
+
+    pub fn with_formatter(mut self, formatter: FormatterType) -> Self {
+        self.config = self.config.with_formatter(formatter);
+        self
+    }
+
+    pub fn with_module(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_module(enabled);
+        self
+    }
+
+    pub fn with_file_line(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_file_line(enabled);
+        self
+    }
+
+    pub fn with_bold_subtitles(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_bold_subtitles(enabled);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.config = self.config.with_theme(theme);
+        self
+    }
+
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.config = self.config.with_color_depth(depth);
+        self
+    }
+
+    pub fn with_unicode(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_unicode(enabled);
+        self
+    }
+
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_hyperlinks(enabled);
+        self
+    }
+
+    pub fn with_link_format(mut self, format: &str) -> Self {
+        self.config = self.config.with_link_format(format);
+        self
+    }
+
+    pub fn with_ci_groups(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_ci_groups(enabled);
+        self
+    }
+
+    pub fn with_output_at_level(mut self, output: OutputType, level: LogLevel) -> Self {
+        self.config = self.config.with_output_at_level(output, level);
+        self
+    }
+
+    pub fn with_max_width(mut self, width: usize) -> Self {
+        self.config = self.config.with_max_width(width);
+        self
+    }
+
+    pub fn with_terminal_width(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_terminal_width(enabled);
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.config = self.config.with_overflow(overflow);
+        self
+    }
+
+    pub fn with_continuation_marker(mut self, marker: impl Into<String>) -> Self {
+        self.config = self.config.with_continuation_marker(marker);
+        self
+    }
+
+    pub fn with_field_lines(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_field_lines(enabled);
+        self
+    }
+
+    pub fn with_aligned_columns(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_aligned_columns(enabled);
+        self
+    }
+
+    pub fn with_target_width(mut self, width: usize) -> Self {
+        self.config = self.config.with_target_width(width);
+        self
+    }
+
+    pub fn with_deferred_scopes(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_deferred_scopes(enabled);
+        self
+    }
+
+    pub fn with_indent_size(mut self, size: usize) -> Self {
+        self.config = self.config.with_indent_size(size);
+        self
+    }
+
+    pub fn with_indent_style(mut self, style: IndentStyle) -> Self {
+        self.config = self.config.with_indent_style(style);
+        self
+    }
+
+    pub fn with_ascii_indent(mut self) -> Self {
+        self.config = self.config.with_ascii_indent();
+        self
+    }
+
+    pub fn with_scope_names(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_scope_names(enabled);
+        self
+    }
+
+    pub fn with_output_check(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_output_check(enabled);
+        self
+    }
+
+    pub fn with_field_renderer(mut self, key: &str, renderer: FieldRenderer) -> Self {
+        self.config = self.config.with_field_renderer(key, renderer);
+        self
+    }
+
+    pub fn with_syslog_facility(mut self, facility: Facility) -> Self {
+        self.config = self.config.with_syslog_facility(facility);
+        self
+    }
+
+    pub fn with_app_name(mut self, name: &str) -> Self {
+        self.config = self.config.with_app_name(name);
+        self
+    }
 
-        LogScope {
-            logger: self,
-            context,
-            name: name.to_string(),
-        }
+    pub fn with_broken_pipe_policy(mut self, policy: BrokenPipePolicy) -> Self {
+        self.config = self.config.with_broken_pipe_policy(policy);
+        self
     }
 
-    pub fn end_scope(&self) {
-        self.context_stack.pop();
+    pub fn with_spool<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.config = self.config.with_spool(path);
+        self
     }
 
-    pub fn with_field<T>(&self, key: &str, value: T) -> FieldLogger
-    where
-        T: serde::Serialize,
-    {
-        let mut context = self
-            .context_stack
-            .current()
-            .unwrap_or_else(|| LogContext::new(std::module_path!().to_string()));
+    pub fn with_compression(mut self, compression: crate::compression::Compression) -> Self {
+        self.config = self.config.with_compression(compression);
+        self
+    }
 
-        context = context.with_field(key, value);
+    pub fn with_idle_flush(mut self, idle_flush: crate::output::IdleFlush) -> Self {
+        self.config = self.config.with_idle_flush(idle_flush);
+        self
+    }
 
-        FieldLogger {
-            logger: self,
-            context,
-        }
+    pub fn with_file_buffer(mut self, file_buffer: crate::output::FileBuffer) -> Self {
+        self.config = self.config.with_file_buffer(file_buffer);
+        self
     }
 
-    pub fn current_depth(&self) -> usize {
-        self.context_stack.depth()
+    pub fn with_min_free_space(mut self, bytes: u64) -> Self {
+        self.config = self.config.with_min_free_space(bytes);
+        self
     }
 
-    pub fn flush(&self) -> Result<()> {
-        if let Ok(mut output) = self.output.lock() {
-            output.flush()?;
-        }
-        Ok(())
+    pub fn with_budget(mut self, budget: crate::budget::LogBudget) -> Self {
+        self.config = self.config.with_budget(budget);
+        self
     }
-}
 
-impl Default for LogFlow {
-    fn default() -> Self {
-        LogFlow::with_config(LogConfig::default()).unwrap()
+    pub fn with_rate_limit(mut self, limit: crate::ratelimit::RateLimit) -> Self {
+        self.config = self.config.with_rate_limit(limit);
+        self
     }
-}
 
-pub struct LogFlowBuilder {
-    config: LogConfig,
-}
+    pub fn with_dedup(mut self, window: Duration) -> Self {
+        self.config = self.config.with_dedup(window);
+        self
+    }
 
-impl LogFlowBuilder {
-    pub fn new() -> Self {
-        Self {
-            config: LogConfig::default(),
-        }
+    pub fn with_backtraces(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_backtraces(level);
+        self
     }
 
-    pub fn with_level(mut self, level: LogLevel) -> Self {
-        self.config = self.config.with_level(level);
+    pub fn with_scope_timing(mut self, level: LogLevel) -> Self {
+        self.config = self.config.with_scope_timing(level);
         self
     }
 
-    pub fn with_colors(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_colors(enabled);
+    pub fn with_thread_local_scopes(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_thread_local_scopes(enabled);
         self
     }
 
-    pub fn with_timestamps(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_timestamps(enabled);
+    pub fn with_id_gen<G: crate::context::IdGen + 'static>(mut self, id_gen: G) -> Self {
+        self.config = self.config.with_id_gen(id_gen);
         self
     }
 
-    pub fn with_date(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_date(enabled);
+    pub fn with_config_dump(mut self, enabled: bool) -> Self {
+        self.config = self.config.with_config_dump(enabled);
         self
     }
 
-    pub fn with_output(mut self, output: OutputType) -> Self {
-        self.config = self.config.with_output(output);
+    pub fn with_processor<P: crate::pipeline::Processor + 'static>(mut self, processor: P) -> Self {
+        self.config = self.config.with_processor(processor);
         self
     }
 
-    pub fn with_target(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_target(enabled);
+    pub fn with_notification(mut self, notification: Notification) -> Self {
+        self.config = self.config.with_notification(notification);
         self
     }
 
-    pub fn with_module(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_module(enabled);
+    pub fn with_bell(self, min_level: LogLevel) -> Self {
+        self.with_notification(Notification::bell(min_level))
+    }
+
+    pub fn with_global_field<T: serde::Serialize>(mut self, key: &str, value: T) -> Self {
+        self.config = self.config.with_global_field(key, value);
         self
     }
 
-    pub fn with_file_line(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_file_line(enabled);
+    pub fn try_with_global_field<T: serde::Serialize>(
+        mut self,
+        key: &str,
+        value: T,
+    ) -> Result<Self> {
+        self.config = self.config.try_with_global_field(key, value)?;
+        Ok(self)
+    }
+
+    pub fn with_dynamic_field<F, T>(mut self, key: &str, provider: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: serde::Serialize,
+    {
+        self.config = self.config.with_dynamic_field(key, provider);
         self
     }
 
-    pub fn with_bold_subtitles(mut self, enabled: bool) -> Self {
-        self.config = self.config.with_bold_subtitles(enabled);
+    pub fn with_dynamic_field_at_level<F, T>(
+        mut self,
+        key: &str,
+        min_level: LogLevel,
+        provider: F,
+    ) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: serde::Serialize,
+    {
+        self.config = self
+            .config
+            .with_dynamic_field_at_level(key, min_level, provider);
         self
     }
 
@@ -272,6 +1432,21 @@ impl LogFlowBuilder {
         self
     }
 
+    pub fn logfmt(mut self) -> Self {
+        self.config = LogConfig::logfmt();
+        self
+    }
+
+    pub fn gelf(mut self) -> Self {
+        self.config = LogConfig::gelf();
+        self
+    }
+
+    pub fn ecs(mut self) -> Self {
+        self.config = LogConfig::ecs();
+        self
+    }
+
     pub fn dev(mut self) -> Self {
         self.config = LogConfig::dev();
         self
@@ -280,6 +1455,24 @@ impl LogFlowBuilder {
     pub fn build(self) -> Result<LogFlow> {
         LogFlow::with_config(self.config)
     }
+
+    /// Builds a logger that hands formatted records to a dedicated writer thread over
+    /// a queue of `capacity` batches, so logging calls don't wait on I/O.
+    ///
+    /// Calls block only while the queue is full. Keep the returned guard alive for as
+    /// long as the logger is used; dropping it writes out the queue.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let (logger, _guard) = LogFlow::new().build_non_blocking(1024).unwrap();
+    /// logger.info("written by the worker thread").unwrap();
+    /// ```
+    pub fn build_non_blocking(self, capacity: usize) -> Result<(LogFlow, WorkerGuard)> {
+        let mut logger = LogFlow::with_config(self.config)?;
+        let guard = logger.spawn_worker(capacity)?;
+        Ok((logger, guard))
+    }
 }
 
 impl Default for LogFlowBuilder {
@@ -292,6 +1485,9 @@ pub struct LogScope<'a> {
     logger: &'a LogFlow,
     context: LogContext,
     name: String,
+    started: Instant,
+    // Set once the closing record was logged by `finish`
+    finished: bool,
 }
 
 impl<'a> LogScope<'a> {
@@ -373,29 +1569,142 @@ impl<'a> LogScope<'a> {
         )
     }
 
-    pub fn begin_scope(&self, name: &str) -> LogScope {
+    pub fn begin_scope(&self, name: &str) -> LogScope<'_> {
         self.logger.begin_scope(name)
     }
 
+    pub fn begin_scope_with_level(&self, name: &str, level: LogLevel) -> LogScope<'_> {
+        self.logger.begin_scope_with_level(name, level)
+    }
+
+    pub fn min_level(&self) -> Option<LogLevel> {
+        self.context.min_level
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn with_field<T>(&self, key: &str, value: T) -> FieldLogger
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.context.correlation_id()
+    }
+
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.logger.enabled_in(level, Some(&self.context), None)
+    }
+
+    pub fn enabled_at(&self, level: LogLevel, _location: &Location) -> bool {
+        self.enabled(level)
+    }
+
+    // Records keep the scope's target, so filters on the scope still apply
+    pub fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        let context = self.context.clone().with_location(location);
+        self.logger.log_with_context(level, message, Some(context))
+    }
+
+    pub fn with_field<T>(&self, key: &str, value: T) -> FieldLogger<'_>
+    where
+        T: serde::Serialize,
+    {
+        let placeholder = self.logger.config.serialization_placeholder;
+        let context = self.context.clone().with_field_or(key, value, placeholder);
+        FieldLogger {
+            logger: self.logger,
+            context,
+        }
+    }
+
+    pub fn try_with_field<T>(&self, key: &str, value: T) -> Result<FieldLogger<'_>>
     where
         T: serde::Serialize,
     {
-        let context = self.context.clone().with_field(key, value);
+        Ok(FieldLogger {
+            logger: self.logger,
+            context: self.context.clone().try_with_field(key, value)?,
+        })
+    }
+
+    pub fn with_value(&self, key: &str, value: impl Into<FieldValue>) -> FieldLogger<'_> {
+        FieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_value(key, value),
+        }
+    }
+
+    typed_field_methods!(borrowing FieldLogger<'_>);
+
+    pub fn with_timestamp<T>(&self, timestamp: T) -> FieldLogger<'_>
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        let context = self.context.clone().with_timestamp(timestamp);
         FieldLogger {
             logger: self.logger,
             context,
         }
     }
+
+    pub fn styled(&self, style: Style) -> FieldLogger<'_> {
+        FieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_style(style),
+        }
+    }
+
+    // Sets a field on every record logged through this scope from now on
+    pub(crate) fn set_field(&mut self, key: &str, value: FieldValue) {
+        self.context.fields.insert(key.to_string(), value);
+    }
+
+    /// Ends the scope with a record of its name and `duration_ms`, at the
+    /// `with_scope_timing` level or Debug.
+    ///
+    /// ```
+    /// use logflow::prelude::*;
+    ///
+    /// let logger = LogFlow::new().build().unwrap();
+    /// let scope = logger.begin_scope("import");
+    /// scope.info("importing").unwrap();
+    /// scope.finish().unwrap();
+    /// ```
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        let level = self.logger.config.scope_timing.unwrap_or(LogLevel::Debug);
+        self.log_duration(level)
+    }
+
+    fn log_duration(&self, level: LogLevel) -> Result<()> {
+        let context = self
+            .context
+            .clone()
+            .with_timestamp(Utc::now())
+            .with_field("scope", &self.name)
+            .with_field("duration_ms", self.started.elapsed().as_millis() as u64)
+            .closing_scope();
+        self.logger
+            .log_with_context(level, &format!("{} finished", self.name), Some(context))
+    }
 }
 
 impl<'a> Drop for LogScope<'a> {
     fn drop(&mut self) {
+        let config = &self.logger.config;
+        // Deferred blocks always end in a duration footer
+        let timing = config
+            .scope_timing
+            .or(config.deferred_scopes.then_some(LogLevel::Info));
+        if let (false, Some(level)) = (self.finished, timing) {
+            let _ = self.log_duration(level);
+        }
         self.logger.end_scope();
+        let _ = self.logger.close_deferred(&self.context.id);
+        let end = self
+            .logger
+            .formatter
+            .format_scope_end(&self.name, &self.context);
+        self.logger
+            .write_scope_marker(end, &self.name, &self.context);
     }
 }
 
@@ -405,19 +1714,70 @@ pub struct FieldLogger<'a> {
 }
 
 impl<'a> FieldLogger<'a> {
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        self.logger.enabled_in(level, Some(&self.context), None)
+    }
+
+    pub fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        self.logger
+            .enabled_in(level, Some(&self.context), self.target_at(location))
+    }
+
+    pub fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        let mut context = self.context.clone().with_location(location);
+        if let Some(target) = self.target_at(location) {
+            context.target = target.to_string();
+        }
+        self.logger.log_with_context(level, message, Some(context))
+    }
+
+    // Outside a scope records take the call site's target, as with `LogFlow::log_at`
+    fn target_at(&self, location: &Location) -> Option<&'static str> {
+        self.context.scope.is_none().then_some(location.module)
+    }
+
     pub fn with_field<T>(mut self, key: &str, value: T) -> Self
     where
         T: serde::Serialize,
     {
-        self.context = self.context.with_field(key, value);
+        let placeholder = self.logger.config.serialization_placeholder;
+        self.context = self.context.with_field_or(key, value, placeholder);
+        self
+    }
+
+    pub fn try_with_field<T>(mut self, key: &str, value: T) -> Result<Self>
+    where
+        T: serde::Serialize,
+    {
+        self.context = self.context.try_with_field(key, value)?;
+        Ok(self)
+    }
+
+    pub fn with_value(mut self, key: &str, value: impl Into<FieldValue>) -> Self {
+        self.context = self.context.with_value(key, value);
         self
     }
 
+    typed_field_methods!(consuming);
+
     pub fn with_subtitle(mut self, subtitle: &str) -> Self {
         self.context = self.context.with_subtitle(subtitle);
         self
     }
 
+    pub fn with_timestamp<T>(mut self, timestamp: T) -> Self
+    where
+        T: Into<DateTime<Utc>>,
+    {
+        self.context = self.context.with_timestamp(timestamp);
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.context = self.context.with_style(style);
+        self
+    }
+
     pub fn trace(&self, message: &str) -> Result<()> {
         self.logger
             .log_with_context(LogLevel::Trace, message, Some(self.context.clone()))
@@ -448,3 +1808,161 @@ impl<'a> FieldLogger<'a> {
             .log_with_context(LogLevel::Fatal, message, Some(self.context.clone()))
     }
 }
+
+pub struct LogBatch<'a> {
+    logger: &'a LogFlow,
+    lines: Vec<(Record, bool)>,
+}
+
+impl<'a> LogBatch<'a> {
+    pub fn log(&mut self, level: LogLevel, message: &str) -> &mut Self {
+        if let Some(routed) = self.logger.format_record(level, message, None) {
+            self.lines.push(routed);
+        }
+        self
+    }
+
+    pub fn log_with_context(
+        &mut self,
+        level: LogLevel,
+        message: &str,
+        context: LogContext,
+    ) -> &mut Self {
+        if let Some(routed) = self.logger.format_record(level, message, Some(context)) {
+            self.lines.push(routed);
+        }
+        self
+    }
+
+    pub fn trace(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Trace, message)
+    }
+
+    pub fn debug(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Debug, message)
+    }
+
+    pub fn info(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Info, message)
+    }
+
+    pub fn warn(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Warn, message)
+    }
+
+    pub fn error(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Error, message)
+    }
+
+    pub fn fatal(&mut self, message: &str) -> &mut Self {
+        self.log(LogLevel::Fatal, message)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn discard(mut self) {
+        self.lines.clear();
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        let lines = std::mem::take(&mut self.lines);
+        self.logger.write_batch(lines)
+    }
+}
+
+impl<'a> Drop for LogBatch<'a> {
+    fn drop(&mut self) {
+        // Uncommitted records are written on drop; use `discard` to drop them instead
+        let lines = std::mem::take(&mut self.lines);
+        let _ = self.logger.write_batch(lines);
+    }
+}
+
+impl Log for LogFlow {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        LogFlow::log(self, level, message)
+    }
+
+    fn enabled(&self, level: LogLevel) -> bool {
+        LogFlow::enabled(self, level)
+    }
+
+    fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        LogFlow::log_at(self, level, message, location)
+    }
+
+    fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        LogFlow::enabled_at(self, level, location)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(LogFlow::with_field(self, key, value))
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(LogFlow::begin_scope(self, name))
+    }
+}
+
+impl Log for LogScope<'_> {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(level, message, Some(self.context.clone()))
+    }
+
+    fn enabled(&self, level: LogLevel) -> bool {
+        LogScope::enabled(self, level)
+    }
+
+    fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        LogScope::log_at(self, level, message, location)
+    }
+
+    fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        LogScope::enabled_at(self, level, location)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(LogScope::with_field(self, key, value))
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(LogScope::begin_scope(self, name))
+    }
+}
+
+impl Log for FieldLogger<'_> {
+    fn log(&self, level: LogLevel, message: &str) -> Result<()> {
+        self.logger
+            .log_with_context(level, message, Some(self.context.clone()))
+    }
+
+    fn enabled(&self, level: LogLevel) -> bool {
+        FieldLogger::enabled(self, level)
+    }
+
+    fn log_at(&self, level: LogLevel, message: &str, location: &Location) -> Result<()> {
+        FieldLogger::log_at(self, level, message, location)
+    }
+
+    fn enabled_at(&self, level: LogLevel, location: &Location) -> bool {
+        FieldLogger::enabled_at(self, level, location)
+    }
+
+    fn with_field(&self, key: &str, value: serde_json::Value) -> Box<dyn Log + '_> {
+        Box::new(FieldLogger {
+            logger: self.logger,
+            context: self.context.clone().with_field(key, value),
+        })
+    }
+
+    fn begin_scope(&self, name: &str) -> Box<dyn Log + '_> {
+        Box::new(self.logger.begin_scope(name))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::batch::{BatchOptions, BatchSink};
+use crate::compression::Compression;
+use crate::http::HttpEndpoint;
+use crate::output::Record;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+
+pub const PUSH_PATH: &str = "/loki/api/v1/push";
+
+#[derive(Debug, Clone)]
+pub struct LokiConfig {
+    pub url: String,
+    pub labels: BTreeMap<String, String>,
+    pub label_fields: Vec<String>,
+    pub tenant: Option<String>,
+    pub batch: BatchOptions,
+}
+
+impl LokiConfig {
+    /// `url` is the Loki base address, e.g. `http://localhost:3100`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            labels: BTreeMap::new(),
+            label_fields: Vec::new(),
+            tenant: None,
+            batch: BatchOptions::default(),
+        }
+    }
+
+    // Static label attached to every stream
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.insert(label_name(name), value.to_string());
+        self
+    }
+
+    // Promotes a context field to a stream label; keep these low-cardinality
+    pub fn with_label_field(mut self, key: &str) -> Self {
+        self.label_fields.push(key.to_string());
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+
+    pub fn with_batch(mut self, batch: BatchOptions) -> Self {
+        self.batch = batch;
+        self
+    }
+}
+
+/// Pushes batches to Loki, one stream per distinct label set.
+///
+/// Every stream carries `level` and `target` labels alongside the static labels and
+/// any promoted fields.
+pub struct LokiSink {
+    endpoint: HttpEndpoint,
+    labels: BTreeMap<String, String>,
+    label_fields: Vec<String>,
+}
+
+impl LokiSink {
+    pub fn new(config: &LokiConfig, compression: Compression) -> io::Result<Self> {
+        let mut endpoint = HttpEndpoint::parse(&config.url)?
+            .with_path(PUSH_PATH)
+            .with_compression(compression);
+        if let Some(ref tenant) = config.tenant {
+            endpoint = endpoint.with_header("X-Scope-OrgID", tenant);
+        }
+
+        Ok(Self {
+            endpoint,
+            labels: config.labels.clone(),
+            label_fields: config.label_fields.clone(),
+        })
+    }
+
+    pub fn stream_labels(&self, record: &Record) -> BTreeMap<String, String> {
+        let context = &record.context;
+        let mut labels = self.labels.clone();
+        labels.insert("level".to_string(), record.level.as_str().to_lowercase());
+        labels.insert("target".to_string(), context.target.clone());
+
+        for key in &self.label_fields {
+            if let Some(value) = context.get_field(key) {
+                labels.insert(label_name(key), value.to_text());
+            }
+        }
+
+        labels
+    }
+
+    pub fn encode(&self, records: &[Record]) -> Value {
+        let mut streams: BTreeMap<BTreeMap<String, String>, Vec<Value>> = BTreeMap::new();
+        for record in records {
+            let timestamp = record
+                .context
+                .timestamp
+                .timestamp_nanos_opt()
+                .unwrap_or_default();
+            streams
+                .entry(self.stream_labels(record))
+                .or_default()
+                .push(json!([timestamp.to_string(), record.line]));
+        }
+
+        let streams: Vec<Value> = streams
+            .into_iter()
+            .map(|(labels, values)| json!({ "stream": labels, "values": values }))
+            .collect();
+        json!({ "streams": streams })
+    }
+}
+
+impl BatchSink for LokiSink {
+    fn send(&self, records: &[Record]) -> io::Result<()> {
+        let body = serde_json::to_vec(&self.encode(records)).map_err(io::Error::other)?;
+        self.endpoint
+            .post("application/json", &[], &body)?
+            .into_result()
+            .map(|_| ())
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.endpoint.probe()
+    }
+}
+
+// Loki label names follow the Prometheus rules: `[a-zA-Z_][a-zA-Z0-9_]*`
+fn label_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
@@ -1,42 +1,242 @@
+// What every level macro expands to: the `enabled_at` check before formatting, then
+// `log_at` with the call site, and the fields after `;` as chained `with_field` calls
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __logflow_try_log {
+    ($level:expr, $logger:expr, $fmt:literal $(, $arg:expr)* ; $($key:ident = $value:expr),+ $(,)?) => {
+        if $crate::LOGGING_ENABLED {
+            let logger = &$logger;
+            let location = $crate::location!();
+            if logger.enabled_at($level, &location) {
+                logger
+                    $(.with_field(stringify!($key), $value))+
+                    .log_at($level, &format!($fmt $(, $arg)*), &location)
+            } else {
+                Ok(())
+            }
+        } else {
+            Ok(())
+        }
+    };
+    ($level:expr, $logger:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            let logger = &$logger;
+            let location = $crate::location!();
+            if logger.enabled_at($level, &location) {
+                logger.log_at($level, &format!($($arg)*), &location)
+            } else {
+                Ok(())
+            }
+        } else {
+            Ok(())
+        }
+    };
+}
+
+// `__logflow_try_log!` with errors reported to `diagnostics` instead of returned; the
+// `@global` form logs to `GLOBAL_LOGGER`, skipping the record if it is locked
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __logflow_log {
+    (@global $level:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
+                $crate::__logflow_log!($level, *logger, $($arg)*);
+            }
+        }
+    };
+    ($level:expr, $logger:expr, $($arg:tt)*) => {
+        if let Err(err) = $crate::__logflow_try_log!($level, $logger, $($arg)*) {
+            $crate::diagnostics::report(&err);
+        }
+    };
+}
+
+/// Logs a `format!` message at Trace, skipping the formatting entirely when the
+/// logger wouldn't write the record; the other level macros work the same way.
+///
+/// ```
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// logflow::trace!(logger, "state: {:?}", vec![1, 2, 3]); // filtered, never formatted
+/// logflow::info!(logger, "{} users online", 42);
+/// ```
+///
+/// Fields can follow the message after a `;`, as with chained `with_field` calls:
+///
+/// ```
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// logflow::info!(logger, "user logged in"; user_id = 42, method = "oauth");
+/// logflow::warn!(logger, "retry {} of {}", 1, 3; backoff_ms = 250);
+/// ```
 #[macro_export]
 macro_rules! trace {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.trace(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Trace, $logger, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! debug {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.debug(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Debug, $logger, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! info {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.info(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Info, $logger, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! warn {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.warn(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Warn, $logger, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! error {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.error(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Error, $logger, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! fatal {
     ($logger:expr, $($arg:tt)*) => {
-        $logger.fatal(&format!($($arg)*)).unwrap_or(())
+        $crate::__logflow_log!($crate::LogLevel::Fatal, $logger, $($arg)*)
+    };
+}
+
+/// Like [`trace!`], but logs at most once per `interval` from this call site, for
+/// progress messages in tight loops; the other `*_every!` macros work the same way.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use std::time::Duration;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// for done in 0..10_000 {
+///     logflow::info_every!(logger, Duration::from_secs(5), "processed {} items", done);
+/// }
+/// ```
+#[macro_export]
+macro_rules! trace_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::trace!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! debug_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::debug!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! info_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::info!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! warn_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::warn!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! error_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::error!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! fatal_every {
+    ($logger:expr, $interval:expr, $($arg:tt)*) => {
+        if $crate::LOGGING_ENABLED {
+            static THROTTLE: $crate::ratelimit::Throttle = $crate::ratelimit::Throttle::new();
+            if THROTTLE.ready($interval) {
+                $crate::fatal!($logger, $($arg)*);
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! try_trace {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Trace, $logger, $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! try_debug {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Debug, $logger, $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! try_info {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Info, $logger, $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! try_warn {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Warn, $logger, $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! try_error {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Error, $logger, $($arg)*)
+    };
+}
+
+#[macro_export]
+macro_rules! try_fatal {
+    ($logger:expr, $($arg:tt)*) => {
+        $crate::__logflow_try_log!($crate::LogLevel::Fatal, $logger, $($arg)*)
     };
 }
 
@@ -58,54 +258,42 @@ macro_rules! log_field {
 #[macro_export]
 macro_rules! logflow_trace {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.trace(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Trace, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! logflow_debug {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.debug(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Debug, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! logflow_info {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.info(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Info, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! logflow_warn {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.warn(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Warn, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! logflow_error {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.error(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Error, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! logflow_fatal {
     ($($arg:tt)*) => {
-        if let Ok(logger) = $crate::GLOBAL_LOGGER.try_lock() {
-            logger.fatal(&format!($($arg)*)).unwrap_or(())
-        }
+        $crate::__logflow_log!(@global $crate::LogLevel::Fatal, $($arg)*)
     };
 }
 
@@ -125,5 +313,13 @@ pub fn with_global_logger<F, R>(f: F) -> Option<R>
 where
     F: FnOnce(&LogFlow) -> R,
 {
-    GLOBAL_LOGGER.try_lock().ok().map(|logger| f(&*logger))
+    GLOBAL_LOGGER.try_lock().ok().map(|logger| f(&logger))
+}
+
+/// The call site as a [`Location`](crate::Location), for [`LogFlow::log_at`](crate::LogFlow::log_at).
+#[macro_export]
+macro_rules! location {
+    () => {
+        $crate::Location::new(module_path!(), file!(), line!())
+    };
 }
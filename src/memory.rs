@@ -0,0 +1,213 @@
+use crate::level::LogLevel;
+use crate::output::{OutputType, OutputWriter, Record};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory capture of whole records, optionally bounded.
+///
+/// `with_capacity` turns it into a ring that keeps only the newest records, and
+/// `with_retention` drops records older than the given age on every write and read,
+/// so long-running processes don't have to clean up by hand.
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::prelude::*;
+/// use std::time::Duration;
+///
+/// let sink = MemorySink::new()
+///     .with_capacity(2)
+///     .with_retention(Duration::from_secs(60));
+/// let logger = LogFlow::new().with_output(sink.output()).build().unwrap();
+///
+/// logger.info("one").unwrap();
+/// logger.info("two").unwrap();
+/// logger.info("three").unwrap();
+/// let messages: Vec<String> = sink.records().into_iter().map(|r| r.message).collect();
+/// assert_eq!(messages, vec!["two", "three"]);
+/// ```
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    records: Arc<Mutex<VecDeque<Record>>>,
+    capacity: Option<usize>,
+    retention: Option<Duration>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity.max(1));
+        self
+    }
+
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub fn output(&self) -> OutputType {
+        OutputType::Custom(Arc::new(self.clone()))
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.expire();
+        self.lock()
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.records()
+            .into_iter()
+            .map(|record| record.line)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.expire();
+        self.lock().map(|records| records.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut records) = self.lock() {
+            records.clear();
+        }
+    }
+
+    /// Drops records older than `age`, returning how many were removed.
+    pub fn purge_older_than(&self, age: Duration) -> usize {
+        self.purge_before(cutoff(age))
+    }
+
+    pub fn purge_before(&self, cutoff: DateTime<Utc>) -> usize {
+        let Ok(mut records) = self.lock() else {
+            return 0;
+        };
+        let before = records.len();
+        records.retain(|record| record.context.timestamp >= cutoff);
+        before - records.len()
+    }
+
+    fn expire(&self) {
+        if let Some(retention) = self.retention {
+            self.purge_older_than(retention);
+        }
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, VecDeque<Record>>> {
+        self.records
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire memory sink lock"))
+    }
+}
+
+impl OutputWriter for MemorySink {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.expire();
+        let mut records = self.lock()?;
+        records.push_back(record.clone());
+        if let Some(capacity) = self.capacity {
+            while records.len() > capacity {
+                records.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn cutoff(age: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(age)
+        .ok()
+        .and_then(|age| Utc::now().checked_sub_signed(age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Which records [`LogFlow::recent`](crate::LogFlow::recent) returns from
+/// `OutputType::RingBuffer` outputs.
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::memory::RecordQuery;
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new()
+///     .with_output(OutputType::RingBuffer(1000))
+///     .build()
+///     .unwrap();
+/// logger.info("cache warm").unwrap();
+/// logger.warn("retrying upstream").unwrap();
+///
+/// let warnings = logger.recent(&RecordQuery::new().at_least(LogLevel::Warn).last(50));
+/// assert_eq!(warnings.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordQuery {
+    pub min_level: Option<LogLevel>,
+    pub target: Option<String>,
+    pub contains: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl RecordQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at_least(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    // The target and its submodules
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    pub fn containing(mut self, text: &str) -> Self {
+        self.contains = Some(text.to_string());
+        self
+    }
+
+    // Only the newest `limit` matches
+    pub fn last(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        let target = &record.context.target;
+        self.min_level.is_none_or(|level| record.level >= level)
+            && self.target.as_ref().is_none_or(|prefix| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|text| record.message.contains(text.as_str()))
+    }
+}
@@ -0,0 +1,81 @@
+use crate::level::LogLevel;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+type NotifyCallback = Arc<dyn Fn(LogLevel, &str) + Send + Sync>;
+
+#[derive(Clone)]
+pub enum NotificationKind {
+    Bell,
+    #[cfg(feature = "notify")]
+    Desktop,
+    Custom(NotifyCallback),
+}
+
+impl std::fmt::Debug for NotificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationKind::Bell => write!(f, "Bell"),
+            #[cfg(feature = "notify")]
+            NotificationKind::Desktop => write!(f, "Desktop"),
+            NotificationKind::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub min_level: LogLevel,
+    pub kind: NotificationKind,
+}
+
+impl Notification {
+    pub fn new(min_level: LogLevel, kind: NotificationKind) -> Self {
+        Self { min_level, kind }
+    }
+
+    pub fn bell(min_level: LogLevel) -> Self {
+        Self::new(min_level, NotificationKind::Bell)
+    }
+
+    #[cfg(feature = "notify")]
+    pub fn desktop(min_level: LogLevel) -> Self {
+        Self::new(min_level, NotificationKind::Desktop)
+    }
+
+    pub fn custom<F>(min_level: LogLevel, callback: F) -> Self
+    where
+        F: Fn(LogLevel, &str) + Send + Sync + 'static,
+    {
+        Self::new(min_level, NotificationKind::Custom(Arc::new(callback)))
+    }
+
+    pub fn notify(&self, level: LogLevel, message: &str) {
+        if level < self.min_level {
+            return;
+        }
+
+        match &self.kind {
+            // The bell goes to stderr so it never ends up in file or JSON sinks
+            NotificationKind::Bell => {
+                let mut stderr = io::stderr();
+                let _ = stderr.write_all(b"\x07");
+                let _ = stderr.flush();
+            }
+            #[cfg(feature = "notify")]
+            NotificationKind::Desktop => {
+                let _ = notify_rust::Notification::new()
+                    .summary(&format!("LogFlow {}", level.as_str()))
+                    .body(message)
+                    .show();
+            }
+            NotificationKind::Custom(callback) => callback(level, message),
+        }
+    }
+}
+
+pub fn dispatch(notifications: &[Notification], level: LogLevel, message: &str) {
+    for notification in notifications {
+        notification.notify(level, message);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::context::LogContext;
+use opentelemetry::trace::TraceContextExt;
+
+// Adds the active OpenTelemetry span's `trace_id` and `span_id`, leaving ids set
+// explicitly on the record alone
+pub(crate) fn attach_trace_ids(mut context: LogContext) -> LogContext {
+    let current = opentelemetry::Context::current();
+    let span = current.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return context;
+    }
+
+    context
+        .fields
+        .entry("trace_id".to_string())
+        .or_insert_with(|| span_context.trace_id().to_string().into());
+    context
+        .fields
+        .entry("span_id".to_string())
+        .or_insert_with(|| span_context.span_id().to_string().into());
+    context
+}
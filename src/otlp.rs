@@ -0,0 +1,418 @@
+use crate::batch::{BatchOptions, BatchSink};
+use crate::compression::Compression;
+use crate::context::FieldValue;
+use crate::http::HttpEndpoint;
+use crate::level::LogLevel;
+use crate::output::Record;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+
+pub const LOGS_PATH: &str = "/v1/logs";
+
+/// Encoding of OTLP/HTTP request bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OtlpEncoding {
+    #[default]
+    Protobuf,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    pub url: String,
+    pub encoding: OtlpEncoding,
+    pub service_name: Option<String>,
+    pub resource: BTreeMap<String, String>,
+    pub headers: Vec<(String, String)>,
+    pub batch: BatchOptions,
+}
+
+impl OtlpConfig {
+    /// `url` is the collector's OTLP/HTTP address, e.g. `http://localhost:4318`;
+    /// `/v1/logs` is added unless the URL has a path.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            encoding: OtlpEncoding::default(),
+            service_name: None,
+            resource: BTreeMap::new(),
+            headers: Vec::new(),
+            batch: BatchOptions::default(),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: OtlpEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    // `service.name` of the resource; the logger's app name by default
+    pub fn with_service_name(mut self, name: &str) -> Self {
+        self.service_name = Some(name.to_string());
+        self
+    }
+
+    // Resource attribute sent with every batch, e.g. `deployment.environment`
+    pub fn with_resource(mut self, key: &str, value: &str) -> Self {
+        self.resource.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    // Extra request header, e.g. for collector authentication
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_batch(mut self, batch: BatchOptions) -> Self {
+        self.batch = batch;
+        self
+    }
+}
+
+/// Exports batches as OpenTelemetry log records over OTLP/HTTP.
+///
+/// Each record becomes a LogRecord with its level as severity, the message as body and
+/// fields as attributes, next to `target`, `scope` and the `code.*` location. `trace_id`
+/// and `span_id` fields, as attached by the `opentelemetry` feature, become the
+/// record's trace context instead. gRPC is not supported; collectors accept OTLP/HTTP
+/// on port 4318 by default.
+pub struct OtlpSink {
+    endpoint: HttpEndpoint,
+    encoding: OtlpEncoding,
+    resource: BTreeMap<String, String>,
+}
+
+impl OtlpSink {
+    pub fn new(
+        config: &OtlpConfig,
+        compression: Compression,
+        app_name: Option<&str>,
+    ) -> io::Result<Self> {
+        let mut endpoint = HttpEndpoint::parse(&config.url)?.with_compression(compression);
+        if endpoint.path == "/" {
+            endpoint = endpoint.with_path(LOGS_PATH);
+        }
+        for (name, value) in &config.headers {
+            endpoint = endpoint.with_header(name, value);
+        }
+
+        let service_name = config
+            .service_name
+            .as_deref()
+            .or(app_name)
+            .unwrap_or("unknown_service");
+        let mut resource = BTreeMap::from([
+            ("service.name".to_string(), service_name.to_string()),
+            ("telemetry.sdk.name".to_string(), "logflow".to_string()),
+            ("telemetry.sdk.language".to_string(), "rust".to_string()),
+            (
+                "telemetry.sdk.version".to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+        ]);
+        resource.extend(config.resource.clone());
+
+        Ok(Self {
+            endpoint,
+            encoding: config.encoding,
+            resource,
+        })
+    }
+
+    /// The `ExportLogsServiceRequest` for `records` in OTLP/JSON.
+    pub fn encode_json(&self, records: &[Record]) -> Value {
+        let resource: Vec<Value> = self
+            .resource
+            .iter()
+            .map(|(key, value)| json_key_value(key, &Value::from(value.as_str())))
+            .collect();
+        let log_records: Vec<Value> = records
+            .iter()
+            .map(|record| {
+                let log = LogRecord::from(record);
+                let mut json = json!({
+                    "timeUnixNano": log.time.to_string(),
+                    "observedTimeUnixNano": log.time.to_string(),
+                    "severityNumber": log.severity,
+                    "severityText": log.severity_text,
+                    "body": { "stringValue": record.message },
+                    "attributes": log
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| json_key_value(key, value))
+                        .collect::<Vec<_>>(),
+                });
+                if let Some(trace_id) = log.trace_id {
+                    json["traceId"] = hex(&trace_id).into();
+                }
+                if let Some(span_id) = log.span_id {
+                    json["spanId"] = hex(&span_id).into();
+                }
+                json
+            })
+            .collect();
+
+        json!({
+            "resourceLogs": [{
+                "resource": { "attributes": resource },
+                "scopeLogs": [{
+                    "scope": { "name": "logflow", "version": env!("CARGO_PKG_VERSION") },
+                    "logRecords": log_records,
+                }],
+            }],
+        })
+    }
+
+    /// The `ExportLogsServiceRequest` for `records` in OTLP protobuf.
+    pub fn encode_protobuf(&self, records: &[Record]) -> Vec<u8> {
+        let mut request = Proto::default();
+        request.message(1, |resource_logs| {
+            resource_logs.message(1, |resource| {
+                for (key, value) in &self.resource {
+                    resource.message(1, |kv| {
+                        proto_key_value(kv, key, &Value::from(value.as_str()))
+                    });
+                }
+            });
+            resource_logs.message(2, |scope_logs| {
+                scope_logs.message(1, |scope| {
+                    scope.string(1, "logflow");
+                    scope.string(2, env!("CARGO_PKG_VERSION"));
+                });
+                for record in records {
+                    let log = LogRecord::from(record);
+                    scope_logs.message(2, |proto| {
+                        proto.fixed64(1, log.time);
+                        proto.varint_field(2, log.severity);
+                        proto.string(3, log.severity_text);
+                        proto.message(5, |body| body.string(1, &record.message));
+                        for (key, value) in &log.attributes {
+                            proto.message(6, |kv| proto_key_value(kv, key, value));
+                        }
+                        if let Some(trace_id) = log.trace_id {
+                            proto.bytes(9, &trace_id);
+                        }
+                        if let Some(span_id) = log.span_id {
+                            proto.bytes(10, &span_id);
+                        }
+                        proto.fixed64(11, log.time);
+                    });
+                }
+            });
+        });
+        request.0
+    }
+}
+
+impl BatchSink for OtlpSink {
+    fn send(&self, records: &[Record]) -> io::Result<()> {
+        let (content_type, body) = match self.encoding {
+            OtlpEncoding::Protobuf => ("application/x-protobuf", self.encode_protobuf(records)),
+            OtlpEncoding::Json => (
+                "application/json",
+                serde_json::to_vec(&self.encode_json(records)).map_err(io::Error::other)?,
+            ),
+        };
+        self.endpoint
+            .post(content_type, &[], &body)?
+            .into_result()
+            .map(|_| ())
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.endpoint.probe()
+    }
+}
+
+// The parts of a LogRecord shared by both encodings
+struct LogRecord {
+    time: u64,
+    severity: u64,
+    severity_text: &'static str,
+    attributes: Vec<(String, Value)>,
+    trace_id: Option<Vec<u8>>,
+    span_id: Option<Vec<u8>>,
+}
+
+impl From<&Record> for LogRecord {
+    fn from(record: &Record) -> Self {
+        let context = &record.context;
+        let id = |key: &str, len: usize| {
+            context
+                .get_field(key)
+                .and_then(FieldValue::as_str)
+                .and_then(|id| unhex(id).filter(|bytes| bytes.len() == len))
+        };
+        let trace_id = id("trace_id", 16);
+        let span_id = id("span_id", 8);
+
+        let mut attributes = vec![("target".to_string(), Value::from(context.target.as_str()))];
+        if let Some(ref scope) = context.scope {
+            attributes.push(("scope".to_string(), Value::from(scope.as_str())));
+        }
+        if let Some(ref module) = context.module {
+            attributes.push(("code.namespace".to_string(), Value::from(module.as_str())));
+        }
+        if let Some(ref file) = context.file {
+            attributes.push(("code.filepath".to_string(), Value::from(file.as_str())));
+        }
+        if let Some(line) = context.line {
+            attributes.push(("code.lineno".to_string(), Value::from(line)));
+        }
+        let mut fields: Vec<_> = context
+            .fields
+            .iter()
+            .filter(|(key, _)| {
+                !(key.as_str() == "trace_id" && trace_id.is_some()
+                    || key.as_str() == "span_id" && span_id.is_some())
+            })
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        attributes.extend(fields.into_iter().map(|(k, v)| (k.clone(), v.to_json())));
+
+        Self {
+            time: context
+                .timestamp
+                .timestamp_nanos_opt()
+                .unwrap_or_default()
+                .max(0) as u64,
+            severity: severity_number(record.level),
+            severity_text: record.level.as_str(),
+            attributes,
+            trace_id,
+            span_id,
+        }
+    }
+}
+
+/// OpenTelemetry severity number for `level`; custom levels take that of the
+/// built-in level below them.
+pub fn severity_number(level: LogLevel) -> u64 {
+    match level.base_level() {
+        LogLevel::Trace => 1,
+        LogLevel::Debug => 5,
+        LogLevel::Info => 9,
+        LogLevel::Warn => 13,
+        LogLevel::Error => 17,
+        _ => 21,
+    }
+}
+
+fn json_key_value(key: &str, value: &Value) -> Value {
+    json!({ "key": key, "value": json_any_value(value) })
+}
+
+fn json_any_value(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({}),
+        Value::Bool(flag) => json!({ "boolValue": flag }),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => json!({ "intValue": int.to_string() }),
+            None => json!({ "doubleValue": number.as_f64() }),
+        },
+        Value::String(text) => json!({ "stringValue": text }),
+        Value::Array(values) => json!({
+            "arrayValue": { "values": values.iter().map(json_any_value).collect::<Vec<_>>() }
+        }),
+        Value::Object(map) => json!({
+            "kvlistValue": {
+                "values": map
+                    .iter()
+                    .map(|(key, value)| json_key_value(key, value))
+                    .collect::<Vec<_>>()
+            }
+        }),
+    }
+}
+
+fn proto_key_value(proto: &mut Proto, key: &str, value: &Value) {
+    proto.string(1, key);
+    proto.message(2, |any| proto_any_value(any, value));
+}
+
+// AnyValue; null leaves every field unset
+fn proto_any_value(proto: &mut Proto, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::Bool(flag) => proto.varint_field(2, *flag as u64),
+        Value::Number(number) => match number.as_i64() {
+            Some(int) => proto.varint_field(3, int as u64),
+            None => proto.double(4, number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => proto.string(1, text),
+        Value::Array(values) => proto.message(5, |array| {
+            for value in values {
+                array.message(1, |any| proto_any_value(any, value));
+            }
+        }),
+        Value::Object(map) => proto.message(6, |list| {
+            for (key, value) in map {
+                list.message(1, |kv| proto_key_value(kv, key, value));
+            }
+        }),
+    }
+}
+
+// Just enough of the protobuf wire format for the OTLP messages
+#[derive(Default)]
+struct Proto(Vec<u8>);
+
+impl Proto {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u64, wire_type: u64) {
+        self.varint(field << 3 | wire_type);
+    }
+
+    fn varint_field(&mut self, field: u64, value: u64) {
+        self.tag(field, 0);
+        self.varint(value);
+    }
+
+    fn fixed64(&mut self, field: u64, value: u64) {
+        self.tag(field, 1);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn double(&mut self, field: u64, value: f64) {
+        self.fixed64(field, value.to_bits());
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) {
+        self.tag(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, field: u64, text: &str) {
+        self.bytes(field, text.as_bytes());
+    }
+
+    fn message(&mut self, field: u64, build: impl FnOnce(&mut Proto)) {
+        let mut message = Proto::default();
+        build(&mut message);
+        self.bytes(field, &message.0);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -1,15 +1,81 @@
+use crate::ansi;
+use crate::config::LogConfig;
+use crate::context::LogContext;
+use crate::gelf::{GelfConfig, GelfWriter};
+use crate::level::LogLevel;
+use crate::memory::{MemorySink, RecordQuery};
+use crate::spool::SpoolWriter;
+use crate::syslog::{SyslogFormat, SyslogTransport, SyslogWriter};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs::OpenOptions;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub enum OutputType {
     Stdout,
     Stderr,
     File(PathBuf),
+    RollingFile(PathBuf, Rotation),
+    Syslog(SyslogTransport, SyslogFormat),
+    Gelf(GelfConfig),
+    #[cfg(all(unix, feature = "journald"))]
+    Journald,
+    #[cfg(feature = "loki")]
+    Loki(crate::loki::LokiConfig),
+    #[cfg(feature = "elasticsearch")]
+    Elasticsearch(crate::elasticsearch::ElasticsearchConfig),
+    #[cfg(feature = "otlp")]
+    Otlp(crate::otlp::OtlpConfig),
+    #[cfg(feature = "parquet")]
+    Parquet(crate::parquet::ParquetConfig),
     Buffer(Arc<Mutex<Vec<u8>>>),
+    // Keeps the newest records in memory for `recent`
+    RingBuffer(usize),
     Custom(Arc<dyn OutputWriter>),
+    Router(Router),
+}
+
+impl OutputType {
+    // Whether everything written here reaches a terminal, for `ColorMode::Auto`
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            OutputType::Stdout => io::stdout().is_terminal(),
+            OutputType::Stderr => io::stderr().is_terminal(),
+            OutputType::Router(router) => {
+                !router.routes.is_empty()
+                    && router.routes.iter().all(|route| route.output.is_terminal())
+            }
+            _ => false,
+        }
+    }
+
+    // Outputs that ship over the network, which `LogConfig::with_spool` puts behind a spool
+    fn network_outputs(&self) -> usize {
+        match self {
+            OutputType::Syslog(..) | OutputType::Gelf(_) => 1,
+            #[cfg(feature = "loki")]
+            OutputType::Loki(_) => 1,
+            #[cfg(feature = "elasticsearch")]
+            OutputType::Elasticsearch(_) => 1,
+            #[cfg(feature = "otlp")]
+            OutputType::Otlp(_) => 1,
+            OutputType::Router(router) => router
+                .routes
+                .iter()
+                .map(|route| route.output.network_outputs())
+                .sum(),
+            _ => 0,
+        }
+    }
 }
 
 impl std::fmt::Debug for OutputType {
@@ -18,58 +84,820 @@ impl std::fmt::Debug for OutputType {
             OutputType::Stdout => write!(f, "Stdout"),
             OutputType::Stderr => write!(f, "Stderr"),
             OutputType::File(path) => write!(f, "File({:?})", path),
+            OutputType::RollingFile(path, rotation) => {
+                write!(f, "RollingFile({:?}, {:?})", path, rotation)
+            }
+            OutputType::Syslog(transport, format) => {
+                write!(f, "Syslog({:?}, {:?})", transport, format)
+            }
+            OutputType::Gelf(config) => write!(f, "Gelf({})", config.address),
+            #[cfg(all(unix, feature = "journald"))]
+            OutputType::Journald => write!(f, "Journald"),
+            #[cfg(feature = "loki")]
+            OutputType::Loki(config) => write!(f, "Loki({})", config.url),
+            #[cfg(feature = "elasticsearch")]
+            OutputType::Elasticsearch(config) => {
+                write!(f, "Elasticsearch({}, {})", config.url, config.index)
+            }
+            #[cfg(feature = "otlp")]
+            OutputType::Otlp(config) => write!(f, "Otlp({})", config.url),
+            #[cfg(feature = "parquet")]
+            OutputType::Parquet(config) => write!(f, "Parquet({})", config.dir.display()),
             OutputType::Buffer(_) => write!(f, "Buffer"),
+            OutputType::RingBuffer(capacity) => write!(f, "RingBuffer({})", capacity),
             OutputType::Custom(_) => write!(f, "Custom"),
+            OutputType::Router(router) => write!(f, "{:?}", router),
+        }
+    }
+}
+
+/// Time-based rollover policy for file outputs.
+///
+/// Rotated files are named after the configured path with a UTC date suffix,
+/// e.g. `app.log.2024-01-31` for daily or `app.log.2024-01-31-14` for hourly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    #[default]
+    Never,
+    Daily,
+    Hourly,
+    Minutely,
+}
+
+impl Rotation {
+    pub(crate) fn suffix(&self, now: DateTime<Utc>) -> Option<String> {
+        let format = match self {
+            Rotation::Never => return None,
+            Rotation::Daily => "%Y-%m-%d",
+            Rotation::Hourly => "%Y-%m-%d-%H",
+            Rotation::Minutely => "%Y-%m-%d-%H-%M",
+        };
+        Some(now.format(format).to_string())
+    }
+
+    /// File a rolling output writes to at `now`, e.g. `app.log.2024-05-01` for daily rotation.
+    pub fn path_for(&self, base: &Path, now: DateTime<Utc>) -> PathBuf {
+        match self.suffix(now) {
+            Some(suffix) => {
+                let mut name = base.as_os_str().to_owned();
+                name.push(".");
+                name.push(suffix);
+                PathBuf::from(name)
+            }
+            None => base.to_path_buf(),
+        }
+    }
+}
+
+/// Output that sends each record to every route whose level range contains it.
+///
+/// Routes may overlap, so a level can go to several outputs; levels no route covers
+/// are dropped.
+///
+/// ```no_run
+/// use logflow::prelude::*;
+///
+/// let router = Router::new()
+///     .route(..LogLevel::Warn, OutputType::Stdout)
+///     .route(LogLevel::Warn.., OutputType::Stderr)
+///     .route(LogLevel::Fatal.., OutputType::File("fatal.log".into()));
+/// let logger = LogFlow::new().with_output(router.output()).build().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+type LevelRange = (Bound<LogLevel>, Bound<LogLevel>);
+
+#[derive(Debug, Clone)]
+struct Route {
+    // Kept as given, so custom levels between the built-in ones fall where they belong
+    levels: LevelRange,
+    output: OutputType,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The common CLI convention: warnings and errors on stderr, the rest on stdout
+    pub fn cli() -> Self {
+        Self::new()
+            .route(..LogLevel::Warn, OutputType::Stdout)
+            .route(LogLevel::Warn.., OutputType::Stderr)
+    }
+
+    pub fn route<R: RangeBounds<LogLevel>>(mut self, levels: R, output: OutputType) -> Self {
+        let levels = (levels.start_bound().cloned(), levels.end_bound().cloned());
+        self.routes.push(Route { levels, output });
+        self
+    }
+
+    pub fn output(self) -> OutputType {
+        OutputType::Router(self)
+    }
+}
+
+/// What the stdout output does once the reader closes the pipe (e.g. `| head`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BrokenPipePolicy {
+    /// Keep returning the `BrokenPipe` error from every write.
+    #[default]
+    Error,
+    /// Silently drop all further output.
+    Suppress,
+    /// Send all further output to stderr instead.
+    Stderr,
+}
+
+/// Flush file outputs once no record has been written for `after`, optionally
+/// followed by an fsync, so `tail -f` and crash recovery see records promptly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdleFlush {
+    pub after: Duration,
+    pub fsync: bool,
+}
+
+impl IdleFlush {
+    pub fn new(after: Duration) -> Self {
+        Self {
+            after,
+            fsync: false,
+        }
+    }
+
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+}
+
+/// Buffer file writes in memory and flush at most every `flush_interval`, instead of
+/// after each record.
+///
+/// Buffered data is also flushed in the background once writes pause for
+/// `flush_interval`, unless an [`IdleFlush`] is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileBuffer {
+    pub capacity: usize,
+    pub flush_interval: Duration,
+}
+
+impl Default for FileBuffer {
+    fn default() -> Self {
+        Self {
+            capacity: 64 * 1024,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl FileBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OutputId(pub usize);
+
+impl std::fmt::Display for OutputId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output#{}", self.0)
+    }
+}
+
+/// String-backed output for examples and doctests.
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::output::DocBuffer;
+/// use logflow::prelude::*;
+///
+/// let buffer = DocBuffer::new();
+/// let logger = LogFlow::new()
+///     .compact()
+///     .with_output(buffer.output())
+///     .build()
+///     .unwrap();
+///
+/// logger.info("hello").unwrap();
+/// assert_eq!(buffer.to_string(), "I hello\n");
+/// assert_eq!(buffer.lines(), vec!["I hello"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DocBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl DocBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output(&self) -> OutputType {
+        OutputType::Buffer(Arc::clone(&self.buffer))
+    }
+
+    pub fn contents(&self) -> String {
+        self.buffer
+            .lock()
+            .map(|buffer| String::from_utf8_lossy(&buffer).into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.contents().lines().map(str::to_string).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer
+            .lock()
+            .map(|buffer| buffer.is_empty())
+            .unwrap_or(true)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.clear();
+        }
+    }
+}
+
+impl std::fmt::Display for DocBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.contents())
+    }
+}
+
+/// A rendered record together with the data it was rendered from; `line` is still
+/// empty while [`Processor`](crate::pipeline::Processor)s run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub level: LogLevel,
+    pub message: String,
+    pub context: LogContext,
+    pub line: String,
+}
+
+impl Record {
+    pub fn new(level: LogLevel, message: &str, context: LogContext, line: String) -> Self {
+        Self {
+            level,
+            message: message.to_string(),
+            context,
+            line,
         }
     }
+
+    // Record for bytes written without going through a logger
+    pub fn raw(data: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(data);
+        let text = text.trim_end_matches('\n');
+        let context = LogContext::new(std::module_path!().to_string());
+        Self::new(LogLevel::Info, text, context, text.to_string())
+    }
 }
 
 pub trait OutputWriter: Send + Sync {
     fn write(&self, data: &[u8]) -> io::Result<()>;
     fn flush(&self) -> io::Result<()>;
+
+    // True for writers that override `write_record` and need each record intact
+    fn is_structured(&self) -> bool {
+        false
+    }
+
+    // Writers that need more than the rendered line (severity, fields) override this
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.write(record.line.as_bytes())?;
+        self.write(b"\n")
+    }
+
+    // Called after each logged record; writers with their own buffering policy may
+    // hold the data back, while `flush` always writes it out
+    fn flush_buffered(&self) -> io::Result<()> {
+        self.flush()
+    }
+
+    // Writes out everything still pending, even if that means waiting on the network;
+    // called right before the process exits, so later writes may be lost
+    fn close(&self) -> io::Result<()> {
+        self.flush()
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    // One result per destination, for writers that fan out to several
+    fn health_checks(&self) -> Vec<io::Result<()>> {
+        vec![self.health_check()]
+    }
 }
 
 pub struct Output {
     writer: Box<dyn OutputWriter>,
+    extra: Vec<(LogLevel, Box<dyn OutputWriter>)>,
+    // Ring buffer outputs, kept queryable
+    rings: Vec<MemorySink>,
+    direct: bool,
+    // Status line pinned below the records, only when the primary output is a terminal
+    terminal: bool,
+    status: Option<String>,
 }
 
 impl Output {
     pub fn new(output_type: OutputType) -> io::Result<Self> {
-        let writer: Box<dyn OutputWriter> = match output_type {
-            OutputType::Stdout => Box::new(StdoutWriter),
-            OutputType::Stderr => Box::new(StderrWriter),
-            OutputType::File(path) => Box::new(FileWriter::new(path)?),
-            OutputType::Buffer(buffer) => Box::new(BufferWriter { buffer }),
-            OutputType::Custom(writer) => Box::new(CustomWriterWrapper { writer }),
+        Self::open(output_type, &LogConfig::default())
+    }
+
+    pub fn from_config(config: &LogConfig) -> io::Result<Self> {
+        Self::open(config.output.clone(), config)
+    }
+
+    fn open(output_type: OutputType, config: &LogConfig) -> io::Result<Self> {
+        if config.spool.is_some() {
+            let network = output_type.network_outputs()
+                + config
+                    .extra_outputs
+                    .iter()
+                    .map(|(output, _)| output.network_outputs())
+                    .sum::<usize>();
+            if network > 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("A spool serves one network output, {} configured", network),
+                ));
+            }
+        }
+
+        let mut rings = Vec::new();
+        let mut open = |output: OutputType| match output {
+            OutputType::RingBuffer(capacity) if crate::LOGGING_ENABLED => {
+                let ring = MemorySink::new().with_capacity(capacity);
+                rings.push(ring.clone());
+                Ok(Box::new(ring) as Box<dyn OutputWriter>)
+            }
+            output => open_writer(output, config),
+        };
+
+        let extra = config
+            .extra_outputs
+            .iter()
+            .map(|(output, level)| {
+                let writer = open(output.clone())?;
+                Ok((*level, plain_unless_terminal(writer, output, config)))
+            })
+            .collect::<io::Result<_>>()?;
+        let terminal = matches!(output_type, OutputType::Stdout | OutputType::Stderr)
+            && output_type.is_terminal();
+        let writer = open(output_type)?;
+
+        Ok(Self {
+            writer,
+            extra,
+            rings,
+            direct: false,
+            terminal,
+            status: None,
+        })
+    }
+
+    /// Pins `status` as the last line of a terminal, below the records scrolling above
+    /// it; outputs that aren't terminals ignore it.
+    pub fn set_status(&mut self, status: &str) -> io::Result<()> {
+        if !self.terminal {
+            return Ok(());
+        }
+        let status = status.replace(['\n', '\r'], " ");
+        // One column short of the width, so the terminal never wraps it
+        let status = match ansi::terminal_width() {
+            Some(width) => ansi::truncate(&status, width.saturating_sub(1)).into_owned(),
+            None => status,
         };
+        self.hide_status()?;
+        self.status = Some(status);
+        self.show_status()
+    }
+
+    pub fn clear_status(&mut self) -> io::Result<()> {
+        self.hide_status()?;
+        self.status = None;
+        self.writer.flush()
+    }
 
-        Ok(Self { writer })
+    fn hide_status(&self) -> io::Result<()> {
+        match self.status {
+            Some(_) => self.writer.write(b"\r\x1b[2K"),
+            None => Ok(()),
+        }
     }
 
+    fn show_status(&self) -> io::Result<()> {
+        match self.status {
+            Some(ref status) => {
+                self.writer.write(status.as_bytes())?;
+                self.writer.flush()
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Matching records from the ring buffer outputs, oldest first
+    pub fn recent(&self, query: &RecordQuery) -> Vec<Record> {
+        let mut records: Vec<Record> = self
+            .rings
+            .iter()
+            .flat_map(|ring| ring.records())
+            .filter(|record| query.matches(record))
+            .collect();
+        if self.rings.len() > 1 {
+            records.sort_by_key(|record| record.context.timestamp);
+            records.dedup_by(|a, b| !a.context.id.is_empty() && a.context.id == b.context.id);
+        }
+        if let Some(limit) = query.limit {
+            records.drain(..records.len().saturating_sub(limit));
+        }
+        records
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.direct
+    }
+
+    pub fn set_direct(&mut self, direct: bool) {
+        self.direct = direct;
+    }
+
+    pub fn has_extra_outputs(&self) -> bool {
+        !self.extra.is_empty()
+    }
+
+    // Raw bytes only go to the primary output; extra outputs need records to filter on
     pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
-        self.writer.write(data)
+        self.hide_status()?;
+        self.writer.write(data)?;
+        self.show_status()
     }
 
     pub fn flush(&mut self) -> io::Result<()> {
-        self.writer.flush()
+        self.writer.flush()?;
+        for (_, writer) in &self.extra {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The first failing health check, naming the output it came from.
+    pub fn health_check(&self) -> io::Result<()> {
+        for (id, result) in self.health_checks() {
+            result.map_err(|err| io::Error::new(err.kind(), format!("{}: {}", id, err)))?;
+        }
+        Ok(())
+    }
+
+    /// One health check per writer: the primary output (each route, for a level
+    /// router), then the extra outputs, numbered in that order.
+    pub fn health_checks(&self) -> Vec<(OutputId, io::Result<()>)> {
+        self.writer
+            .health_checks()
+            .into_iter()
+            .chain(
+                self.extra
+                    .iter()
+                    .flat_map(|(_, writer)| writer.health_checks()),
+            )
+            .enumerate()
+            .map(|(id, result)| (OutputId(id), result))
+            .collect()
+    }
+
+    pub fn is_structured(&self) -> bool {
+        self.writer.is_structured()
     }
 
     pub fn write_line(&mut self, line: &str) -> io::Result<()> {
-        self.write(line.as_bytes())?;
-        self.write(b"\n")?;
-        self.flush()
+        self.write(format!("{}\n", line).as_bytes())?;
+        self.flush_buffered()
+    }
+
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        self.write_record_to(record, true)
+    }
+
+    pub fn write_records(&mut self, records: &[Record]) -> io::Result<()> {
+        self.hide_status()?;
+        for record in records {
+            self.deliver(record, true)?;
+        }
+        self.show_status()?;
+        self.flush_buffered()
+    }
+
+    // `primary` is false for records below the logger's own threshold, which only
+    // extra outputs with a lower level may still take
+    pub fn write_record_to(&mut self, record: &Record, primary: bool) -> io::Result<()> {
+        self.hide_status()?;
+        self.deliver(record, primary)?;
+        self.show_status()?;
+        self.flush_buffered()
+    }
+
+    pub fn write_records_to(&mut self, records: &[(Record, bool)]) -> io::Result<()> {
+        self.hide_status()?;
+        for (record, primary) in records {
+            self.deliver(record, *primary)?;
+        }
+        self.show_status()?;
+        self.flush_buffered()
+    }
+
+    fn flush_buffered(&mut self) -> io::Result<()> {
+        self.writer.flush_buffered()?;
+        for (_, writer) in &self.extra {
+            writer.flush_buffered()?;
+        }
+        Ok(())
+    }
+
+    pub fn close(&mut self) -> io::Result<()> {
+        self.clear_status()?;
+        self.writer.close()?;
+        for (_, writer) in &self.extra {
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    fn deliver(&self, record: &Record, primary: bool) -> io::Result<()> {
+        if primary {
+            self.writer.write_record(record)?;
+        }
+        for (level, writer) in &self.extra {
+            if record.level >= *level {
+                writer.write_record(record)?;
+            }
+        }
+        Ok(())
     }
 }
 
-struct StdoutWriter;
+fn open_writer(output_type: OutputType, config: &LogConfig) -> io::Result<Box<dyn OutputWriter>> {
+    // Nothing is opened when logging is compiled out, so builds never fail on I/O
+    if !crate::LOGGING_ENABLED {
+        return Ok(Box::new(NullWriter));
+    }
+
+    config.compression.ensure_available()?;
+
+    let writer: Box<dyn OutputWriter> = match output_type {
+        OutputType::Stdout => Box::new(StdoutWriter::new(config.broken_pipe)),
+        OutputType::Stderr => Box::new(StderrWriter),
+        OutputType::File(path) => Box::new(FileWriter::new(path, config)?),
+        OutputType::RollingFile(path, rotation) => {
+            Box::new(FileWriter::rolling(path, rotation, config)?)
+        }
+        OutputType::Syslog(transport, format) => match &config.spool {
+            Some(spool) => {
+                let writer = SyslogWriter::deferred(
+                    transport,
+                    format,
+                    config.syslog_facility,
+                    config.app_name.as_deref(),
+                )
+                .with_compression(config.compression);
+                Box::new(SpoolWriter::new(Arc::new(writer), spool)?)
+            }
+            None => Box::new(
+                SyslogWriter::deferred(
+                    transport,
+                    format,
+                    config.syslog_facility,
+                    config.app_name.as_deref(),
+                )
+                .with_compression(config.compression)
+                .connected()?,
+            ),
+        },
+        OutputType::Gelf(gelf) => {
+            let writer = GelfWriter::connect(&gelf, config.compression)?;
+            match &config.spool {
+                Some(spool) => Box::new(SpoolWriter::new(Arc::new(writer), spool)?),
+                None => Box::new(writer),
+            }
+        }
+        #[cfg(all(unix, feature = "journald"))]
+        OutputType::Journald => Box::new(crate::journald::JournaldWriter::connect(
+            config.syslog_facility,
+            config.app_name.as_deref(),
+        )?),
+        #[cfg(feature = "loki")]
+        OutputType::Loki(loki) => batched(
+            crate::loki::LokiSink::new(&loki, config.compression)?,
+            loki.batch,
+            config,
+        )?,
+        #[cfg(feature = "elasticsearch")]
+        OutputType::Elasticsearch(elasticsearch) => batched(
+            crate::elasticsearch::ElasticsearchSink::new(&elasticsearch, config.compression)?,
+            elasticsearch.batch,
+            config,
+        )?,
+        #[cfg(feature = "otlp")]
+        OutputType::Otlp(otlp) => batched(
+            crate::otlp::OtlpSink::new(&otlp, config.compression, config.app_name.as_deref())?,
+            otlp.batch,
+            config,
+        )?,
+        #[cfg(feature = "parquet")]
+        OutputType::Parquet(parquet) => Box::new(crate::parquet::ParquetWriter::new(parquet)?),
+        OutputType::Buffer(buffer) => Box::new(BufferWriter { buffer }),
+        // Only queryable as the primary or an extra output, not behind a router
+        OutputType::RingBuffer(capacity) => Box::new(MemorySink::new().with_capacity(capacity)),
+        OutputType::Custom(writer) => Box::new(CustomWriterWrapper { writer }),
+        OutputType::Router(router) => Box::new(RouterWriter::open(router, config)?),
+    };
+
+    Ok(writer)
+}
+
+// Behind a spool the sink is driven by the spool thread, which only moves past
+// records the destination accepted
+#[cfg(any(feature = "loki", feature = "elasticsearch", feature = "otlp"))]
+fn batched<S: crate::batch::BatchSink>(
+    sink: S,
+    options: crate::batch::BatchOptions,
+    config: &LogConfig,
+) -> io::Result<Box<dyn OutputWriter>> {
+    Ok(match &config.spool {
+        Some(spool) => Box::new(SpoolWriter::for_sink(sink, options.max_records, spool)?),
+        None => Box::new(crate::batch::BatchWriter::new(sink, options)?),
+    })
+}
+
+struct RouterWriter {
+    routes: Vec<(LevelRange, Box<dyn OutputWriter>)>,
+}
+
+impl RouterWriter {
+    fn open(router: Router, config: &LogConfig) -> io::Result<Self> {
+        let routes = router
+            .routes
+            .into_iter()
+            .map(|route| {
+                let writer = open_writer(route.output.clone(), config)?;
+                let writer = plain_unless_terminal(writer, &route.output, config);
+                Ok((route.levels, writer))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { routes })
+    }
+}
+
+impl OutputWriter for RouterWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        for (levels, writer) in &self.routes {
+            if levels.contains(&record.level) {
+                writer.write_record(record)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        for (_, writer) in &self.routes {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush_buffered(&self) -> io::Result<()> {
+        for (_, writer) in &self.routes {
+            writer.flush_buffered()?;
+        }
+        Ok(())
+    }
+
+    fn close(&self) -> io::Result<()> {
+        for (_, writer) in &self.routes {
+            writer.close()?;
+        }
+        Ok(())
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        for (_, writer) in &self.routes {
+            writer.health_check()?;
+        }
+        Ok(())
+    }
+
+    fn health_checks(&self) -> Vec<io::Result<()>> {
+        self.routes
+            .iter()
+            .flat_map(|(_, writer)| writer.health_checks())
+            .collect()
+    }
+}
+
+struct NullWriter;
+
+impl OutputWriter for NullWriter {
+    fn write(&self, _data: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        let _ = self.clear_status();
+    }
+}
+
+struct StdoutWriter {
+    policy: BrokenPipePolicy,
+    closed: AtomicBool,
+}
+
+impl StdoutWriter {
+    fn new(policy: BrokenPipePolicy) -> Self {
+        Self {
+            policy,
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    // Once stdout is gone, stop touching it so each call doesn't hit EPIPE again
+    fn handle(
+        &self,
+        result: io::Result<()>,
+        fallback: impl Fn() -> io::Result<()>,
+    ) -> io::Result<()> {
+        match result {
+            Err(err)
+                if err.kind() == io::ErrorKind::BrokenPipe
+                    && self.policy != BrokenPipePolicy::Error =>
+            {
+                self.closed.store(true, Ordering::Relaxed);
+                fallback()
+            }
+            result => result,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
 
 impl OutputWriter for StdoutWriter {
     fn write(&self, data: &[u8]) -> io::Result<()> {
-        io::stdout().write_all(data)
+        let fallback = || match self.policy {
+            BrokenPipePolicy::Stderr => io::stderr().write_all(data),
+            _ => Ok(()),
+        };
+
+        if self.is_closed() {
+            return fallback();
+        }
+        self.handle(io::stdout().write_all(data), fallback)
     }
 
     fn flush(&self) -> io::Result<()> {
-        io::stdout().flush()
+        let fallback = || match self.policy {
+            BrokenPipePolicy::Stderr => io::stderr().flush(),
+            _ => Ok(()),
+        };
+
+        if self.is_closed() {
+            return fallback();
+        }
+        self.handle(io::stdout().flush(), fallback)
     }
 }
 
@@ -86,43 +914,224 @@ impl OutputWriter for StderrWriter {
 }
 
 struct FileWriter {
-    file: Arc<Mutex<std::fs::File>>,
+    base: PathBuf,
+    rotation: Rotation,
+    buffer: Option<FileBuffer>,
+    state: Arc<Mutex<FileState>>,
+    idle: Option<IdleFlusher>,
+    min_free_space: u64,
+}
+
+struct FileState {
+    path: PathBuf,
+    file: BufWriter<std::fs::File>,
+    dirty: bool,
+    last_write: Instant,
+    last_flush: Instant,
 }
 
 impl FileWriter {
-    fn new(path: PathBuf) -> io::Result<Self> {
+    fn new(path: PathBuf, config: &LogConfig) -> io::Result<Self> {
+        Self::rolling(path, Rotation::Never, config)
+    }
+
+    fn rolling(base: PathBuf, rotation: Rotation, config: &LogConfig) -> io::Result<Self> {
+        let buffer = config.file_buffer;
+        let path = rotation.path_for(&base, Utc::now());
+        let file = Self::open(&path, buffer)?;
+        let state = Arc::new(Mutex::new(FileState {
+            path,
+            file,
+            dirty: false,
+            last_write: Instant::now(),
+            last_flush: Instant::now(),
+        }));
+
+        // Buffered data must not sit in memory forever once writes stop
+        let idle_flush = config
+            .idle_flush
+            .or_else(|| buffer.map(|buffer| IdleFlush::new(buffer.flush_interval)));
+        let idle = match idle_flush {
+            Some(idle_flush) => Some(IdleFlusher::start(Arc::clone(&state), idle_flush)?),
+            None => None,
+        };
+
+        Ok(Self {
+            base,
+            rotation,
+            buffer,
+            state,
+            idle,
+            min_free_space: config.min_free_space,
+        })
+    }
+
+    // Without a buffer the writer has zero capacity and passes every write straight through
+    fn open(path: &Path, buffer: Option<FileBuffer>) -> io::Result<BufWriter<std::fs::File>> {
         let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let capacity = buffer.map_or(0, |buffer| buffer.capacity);
+        Ok(BufWriter::with_capacity(capacity, file))
+    }
+
+    fn lock_state(&self) -> io::Result<std::sync::MutexGuard<'_, FileState>> {
+        self.state
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire file lock"))
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        // Stop the idle thread first so it never races the final flush
+        drop(self.idle.take());
+        if let Ok(mut state) = self.state.lock() {
+            let _ = state.file.flush();
+        }
+    }
+}
+
+// Background thread that flushes a file once writes have paused for `after`
+struct IdleFlusher {
+    stop: mpsc::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl IdleFlusher {
+    fn start(state: Arc<Mutex<FileState>>, options: IdleFlush) -> io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("logflow-idle-flush".to_string())
+            .spawn(move || {
+                let mut wait = options.after;
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(wait) {
+                    let Ok(mut state) = state.lock() else {
+                        return;
+                    };
+                    wait = options.after;
+                    if !state.dirty {
+                        continue;
+                    }
+
+                    let idle = state.last_write.elapsed();
+                    if idle < options.after {
+                        wait = options.after - idle;
+                        continue;
+                    }
+
+                    state.dirty = false;
+                    state.last_flush = Instant::now();
+                    let mut result = state.file.flush();
+                    if options.fsync {
+                        result = result.and_then(|()| state.file.get_ref().sync_data());
+                    }
+                    if let Err(err) = result {
+                        crate::diagnostics::report(&crate::logger::LogFlowError::Output(err));
+                    }
+                }
+            })?;
 
         Ok(Self {
-            file: Arc::new(Mutex::new(file)),
+            stop,
+            thread: Some(thread),
         })
     }
 }
 
+impl Drop for IdleFlusher {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 impl OutputWriter for FileWriter {
     fn write(&self, data: &[u8]) -> io::Result<()> {
-        if let Ok(mut file) = self.file.lock() {
-            file.write_all(data)
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to acquire file lock",
-            ))
+        let mut state = self.lock_state()?;
+
+        // Switch files once the current period has rolled over
+        let path = self.rotation.path_for(&self.base, Utc::now());
+        if path != state.path {
+            state.file.flush()?;
+            state.file = Self::open(&path, self.buffer)?;
+            state.path = path;
         }
+
+        state.file.write_all(data)?;
+        state.dirty = true;
+        state.last_write = Instant::now();
+        Ok(())
     }
 
     fn flush(&self) -> io::Result<()> {
-        if let Ok(mut file) = self.file.lock() {
-            file.flush()
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to acquire file lock",
-            ))
+        let mut state = self.lock_state()?;
+        state.last_flush = Instant::now();
+        state.file.flush()
+    }
+
+    fn flush_buffered(&self) -> io::Result<()> {
+        let mut state = self.lock_state()?;
+        if let Some(buffer) = self.buffer {
+            if state.last_flush.elapsed() < buffer.flush_interval {
+                return Ok(());
+            }
         }
+        state.last_flush = Instant::now();
+        state.file.flush()
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        let mut state = self.lock_state()?;
+
+        // Catches files deleted or made read-only after opening
+        let metadata = std::fs::metadata(&state.path)?;
+        if metadata.permissions().readonly() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is read-only", state.path.display()),
+            ));
+        }
+
+        #[cfg(unix)]
+        if self.min_free_space > 0 {
+            let available = available_space(&state.path)?;
+            if available < self.min_free_space {
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    format!(
+                        "{} bytes free for {}, below the {} byte minimum",
+                        available,
+                        state.path.display(),
+                        self.min_free_space
+                    ),
+                ));
+            }
+        }
+
+        state.file.write_all(&[])?;
+        state.file.flush()
     }
 }
 
+// Space available to unprivileged writers on the file system holding `path`
+#[cfg(unix)]
+fn available_space(path: &Path) -> io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read once statvfs filled it in
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    // Both are narrower than u64 on some platforms
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
 struct BufferWriter {
     buffer: Arc<Mutex<Vec<u8>>>,
 }
@@ -133,16 +1142,121 @@ impl OutputWriter for BufferWriter {
             buffer.extend_from_slice(data);
             Ok(())
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to acquire buffer lock",
-            ))
+            Err(io::Error::other("Failed to acquire buffer lock"))
         }
     }
 
     fn flush(&self) -> io::Result<()> {
         Ok(())
     }
+
+    fn health_check(&self) -> io::Result<()> {
+        if self.buffer.is_poisoned() {
+            Err(io::Error::other("Buffer lock is poisoned"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Removes ANSI escape sequences (colors, cursor movement and OSC hyperlinks) from
+/// `text`, borrowing it when there are none.
+///
+/// ```
+/// use logflow::output::strip_ansi;
+///
+/// assert_eq!(strip_ansi("\x1b[32mINF\x1b[0m ready"), "INF ready");
+/// ```
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let plain = ansi::pieces(text)
+        .filter_map(|piece| match piece {
+            ansi::Piece::Char(c) => Some(c),
+            ansi::Piece::Escape(_) => None,
+        })
+        .collect();
+    Cow::Owned(plain)
+}
+
+/// Writer that strips ANSI escape sequences before passing data on, so a file or
+/// network copy of colored output stays plain.
+///
+/// Extra outputs and router routes that aren't terminals get this automatically while
+/// colors are on; wrap a custom output in it to do the same.
+pub struct StripAnsi {
+    inner: Box<dyn OutputWriter>,
+}
+
+impl StripAnsi {
+    pub fn new<W: OutputWriter + 'static>(inner: W) -> Self {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+
+    fn boxed(inner: Box<dyn OutputWriter>) -> Self {
+        Self { inner }
+    }
+}
+
+impl OutputWriter for StripAnsi {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        match std::str::from_utf8(data) {
+            Ok(text) => self.inner.write(strip_ansi(text).as_bytes()),
+            Err(_) => self.inner.write(data),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn is_structured(&self) -> bool {
+        self.inner.is_structured()
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        match strip_ansi(&record.line) {
+            Cow::Borrowed(_) => self.inner.write_record(record),
+            Cow::Owned(line) => {
+                let mut record = record.clone();
+                record.line = line;
+                self.inner.write_record(&record)
+            }
+        }
+    }
+
+    fn flush_buffered(&self) -> io::Result<()> {
+        self.inner.flush_buffered()
+    }
+
+    fn close(&self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.inner.health_check()
+    }
+
+    fn health_checks(&self) -> Vec<io::Result<()>> {
+        self.inner.health_checks()
+    }
+}
+
+// Copies of colored output headed somewhere other than a terminal are stripped
+fn plain_unless_terminal(
+    writer: Box<dyn OutputWriter>,
+    output: &OutputType,
+    config: &LogConfig,
+) -> Box<dyn OutputWriter> {
+    if config.colors_enabled() && !output.is_terminal() {
+        Box::new(StripAnsi::boxed(writer))
+    } else {
+        writer
+    }
 }
 
 struct CustomWriterWrapper {
@@ -157,4 +1271,28 @@ impl OutputWriter for CustomWriterWrapper {
     fn flush(&self) -> io::Result<()> {
         self.writer.flush()
     }
+
+    fn is_structured(&self) -> bool {
+        self.writer.is_structured()
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.writer.write_record(record)
+    }
+
+    fn flush_buffered(&self) -> io::Result<()> {
+        self.writer.flush_buffered()
+    }
+
+    fn close(&self) -> io::Result<()> {
+        self.writer.close()
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        self.writer.health_check()
+    }
+
+    fn health_checks(&self) -> Vec<io::Result<()>> {
+        self.writer.health_checks()
+    }
 }
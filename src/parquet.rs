@@ -0,0 +1,270 @@
+use crate::output::{OutputWriter, Record, Rotation};
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use arrow_array::builder::{StringBuilder, TimestampMicrosecondBuilder, UInt32Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Utc};
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, Clone)]
+pub struct ParquetConfig {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub rotation: Rotation,
+    pub batch_rows: usize,
+}
+
+impl ParquetConfig {
+    /// Files go to `dir` as `<prefix>-<opened at>-<id>.parquet`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: "logflow".to_string(),
+            rotation: Rotation::Hourly,
+            batch_rows: 8192,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    // A new file is started whenever the period rolls over; `Never` keeps one file
+    // until the logger is flushed or closed
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    // Records per row group, held in memory until written
+    pub fn with_batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+}
+
+/// Column layout of the files [`ParquetWriter`] writes.
+///
+/// `fields` holds the record's context fields as a JSON object, which DuckDB and
+/// DataFusion can query with their JSON functions.
+pub fn schema() -> SchemaRef {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", timestamp, false),
+        Field::new("level", DataType::Utf8, false),
+        Field::new("message", DataType::Utf8, false),
+        Field::new("target", DataType::Utf8, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("parent_id", DataType::Utf8, true),
+        Field::new("scope", DataType::Utf8, true),
+        Field::new("module", DataType::Utf8, true),
+        Field::new("file", DataType::Utf8, true),
+        Field::new("line", DataType::UInt32, true),
+        Field::new("fields", DataType::Utf8, false),
+    ]))
+}
+
+/// Writes records as Parquet files for querying with DuckDB, DataFusion and the like.
+///
+/// Records collect in columns and go out as a row group every `batch_rows` records.
+/// A file is finished when its rotation period ends or the logger is flushed or
+/// closed; until then it is named `*.parquet.partial`, so globs over `*.parquet`
+/// only ever see complete files.
+pub struct ParquetWriter {
+    config: ParquetConfig,
+    schema: SchemaRef,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    columns: Columns,
+    file: Option<OpenFile>,
+}
+
+struct OpenFile {
+    writer: ArrowWriter<File>,
+    partial: PathBuf,
+    period: Option<String>,
+}
+
+#[derive(Default)]
+struct Columns {
+    rows: usize,
+    timestamp: TimestampMicrosecondBuilder,
+    level: StringBuilder,
+    message: StringBuilder,
+    target: StringBuilder,
+    id: StringBuilder,
+    parent_id: StringBuilder,
+    scope: StringBuilder,
+    module: StringBuilder,
+    file: StringBuilder,
+    line: UInt32Builder,
+    fields: StringBuilder,
+}
+
+impl Columns {
+    fn push(&mut self, record: &Record) {
+        let context = &record.context;
+        self.rows += 1;
+        self.timestamp
+            .append_value(context.timestamp.timestamp_micros());
+        self.level.append_value(record.level.as_str());
+        self.message.append_value(&record.message);
+        self.target.append_value(&context.target);
+        self.id.append_value(&context.id);
+        self.parent_id.append_option(context.parent_id.as_deref());
+        self.scope.append_option(context.scope.as_deref());
+        self.module.append_option(context.module.as_deref());
+        self.file.append_option(context.file.as_deref());
+        self.line.append_option(context.line);
+        self.fields
+            .append_value(serde_json::to_string(&context.fields).unwrap_or_default());
+    }
+
+    // Empties the builders into a batch
+    fn finish(&mut self, schema: &SchemaRef) -> io::Result<RecordBatch> {
+        self.rows = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.timestamp.finish().with_timezone("UTC")),
+            Arc::new(self.level.finish()),
+            Arc::new(self.message.finish()),
+            Arc::new(self.target.finish()),
+            Arc::new(self.id.finish()),
+            Arc::new(self.parent_id.finish()),
+            Arc::new(self.scope.finish()),
+            Arc::new(self.module.finish()),
+            Arc::new(self.file.finish()),
+            Arc::new(self.line.finish()),
+            Arc::new(self.fields.finish()),
+        ];
+        RecordBatch::try_new(Arc::clone(schema), columns).map_err(io::Error::other)
+    }
+}
+
+impl ParquetWriter {
+    pub fn new(config: ParquetConfig) -> io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        Ok(Self {
+            config,
+            schema: schema(),
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    fn lock_state(&self) -> io::Result<MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire parquet lock"))
+    }
+
+    fn open(&self, now: DateTime<Utc>) -> io::Result<OpenFile> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let name = format!(
+            "{}-{}-{}.parquet.partial",
+            self.config.prefix,
+            now.format("%Y-%m-%dT%H-%M-%S"),
+            &id[..8]
+        );
+        let partial = self.config.dir.join(name);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = ArrowWriter::try_new(
+            File::create(&partial)?,
+            Arc::clone(&self.schema),
+            Some(properties),
+        )
+        .map_err(io::Error::other)?;
+        Ok(OpenFile {
+            writer,
+            partial,
+            period: self.config.rotation.suffix(now),
+        })
+    }
+
+    // Writes the collected rows as a row group, opening a file if none is open
+    fn write_batch(&self, state: &mut State) -> io::Result<()> {
+        if state.columns.rows == 0 {
+            return Ok(());
+        }
+        let batch = state.columns.finish(&self.schema)?;
+        let file = match state.file.as_mut() {
+            Some(file) => file,
+            None => state.file.insert(self.open(Utc::now())?),
+        };
+        file.writer.write(&batch).map_err(io::Error::other)?;
+        file.writer.flush().map_err(io::Error::other)
+    }
+
+    // Writes the footer and drops the `.partial` suffix
+    fn finish_file(&self, state: &mut State) -> io::Result<()> {
+        self.write_batch(state)?;
+        if let Some(file) = state.file.take() {
+            file.writer.close().map_err(io::Error::other)?;
+            std::fs::rename(&file.partial, file.partial.with_extension(""))?;
+        }
+        Ok(())
+    }
+}
+
+impl OutputWriter for ParquetWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        let mut state = self.lock_state()?;
+
+        // Rows of the old period go into the old file before it is finished
+        let now = Utc::now();
+        let period = self.config.rotation.suffix(now);
+        if state
+            .file
+            .as_ref()
+            .is_some_and(|file| file.period != period)
+        {
+            self.finish_file(&mut state)?;
+        }
+        if state.file.is_none() {
+            state.file = Some(self.open(now)?);
+        }
+
+        state.columns.push(record);
+        if state.columns.rows >= self.config.batch_rows {
+            self.write_batch(&mut state)?;
+        }
+        Ok(())
+    }
+
+    // Rows wait in memory for a full row group
+    fn flush_buffered(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut state = self.lock_state()?;
+        self.finish_file(&mut state)
+    }
+}
+
+impl Drop for ParquetWriter {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Err(err) = self.finish_file(&mut state) {
+                crate::diagnostics::report(&crate::logger::LogFlowError::Output(err));
+            }
+        }
+    }
+}
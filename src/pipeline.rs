@@ -0,0 +1,69 @@
+use crate::output::Record;
+use std::sync::RwLock;
+
+/// Step every record logged by the application goes through after level filtering and
+/// before formatting, e.g. to drop, enrich, redact or re-level it.
+///
+/// Processors run in the order they were added, each seeing the changes of the ones
+/// before it. Implemented for closures:
+///
+/// ```
+/// use logflow::output::Record;
+/// use logflow::prelude::*;
+///
+/// let logger = LogFlow::new()
+///     // Redact
+///     .with_processor(|record: &mut Record| {
+///         record.message = record.message.replace("hunter2", "***");
+///         true
+///     })
+///     // Filter
+///     .with_processor(|record: &mut Record| !record.message.starts_with("healthcheck"))
+///     .build()
+///     .unwrap();
+///
+/// logger.info("login with password hunter2").unwrap();
+/// ```
+pub trait Processor: Send + Sync {
+    /// Returns false to drop the record.
+    fn process(&self, record: &mut Record) -> bool;
+}
+
+impl<F> Processor for F
+where
+    F: Fn(&mut Record) -> bool + Send + Sync,
+{
+    fn process(&self, record: &mut Record) -> bool {
+        self(record)
+    }
+}
+
+impl std::fmt::Debug for dyn Processor + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Processor")
+    }
+}
+
+type Hook = Box<dyn Fn(&Record) + Send + Sync>;
+
+// Callbacks registered with `on_record`, called for every record written
+#[derive(Default)]
+pub(crate) struct RecordHooks {
+    hooks: RwLock<Vec<Hook>>,
+}
+
+impl RecordHooks {
+    pub(crate) fn add(&self, hook: Hook) {
+        if let Ok(mut hooks) = self.hooks.write() {
+            hooks.push(hook);
+        }
+    }
+
+    pub(crate) fn notify(&self, record: &Record) {
+        if let Ok(hooks) = self.hooks.read() {
+            for hook in hooks.iter() {
+                hook(record);
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::logger::LogFlow;
+use std::thread::{Scope, ScopedJoinHandle};
+
+pub trait ContextPropagatingSpawn<'scope, 'env> {
+    fn spawn_with_context<F, T>(
+        &'scope self,
+        logger: &'env LogFlow,
+        f: F,
+    ) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope;
+}
+
+impl<'scope, 'env> ContextPropagatingSpawn<'scope, 'env> for Scope<'scope, 'env> {
+    fn spawn_with_context<F, T>(
+        &'scope self,
+        logger: &'env LogFlow,
+        f: F,
+    ) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let snapshot = logger.snapshot_context();
+        self.spawn(move || logger.in_context(snapshot, f))
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub mod rayon {
+    use crate::logger::LogFlow;
+
+    pub fn spawn<'scope, F>(scope: &rayon::Scope<'scope>, logger: &'scope LogFlow, f: F)
+    where
+        F: FnOnce(&rayon::Scope<'scope>) + Send + 'scope,
+    {
+        let snapshot = logger.snapshot_context();
+        scope.spawn(move |scope| logger.in_context(snapshot, || f(scope)));
+    }
+
+    pub fn install<F, R>(pool: &rayon::ThreadPool, logger: &LogFlow, f: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        let snapshot = logger.snapshot_context();
+        pool.install(move || logger.in_context(snapshot, f))
+    }
+}
@@ -0,0 +1,185 @@
+use crate::output::Record;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Buckets kept before idle ones are pruned, bounding memory with per-message keys
+const MAX_KEYS: usize = 1024;
+
+/// What records share a token bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitKey {
+    #[default]
+    Global,
+    Target,
+    Subtitle,
+    Message,
+}
+
+/// Token-bucket cap on records per second, to survive log storms from retry loops.
+///
+/// Records over the limit are dropped; the next record let through for the same key
+/// is preceded by a "Suppressed N similar messages" notice.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::ratelimit::{RateLimit, RateLimitKey};
+///
+/// let logger = LogFlow::new()
+///     .with_rate_limit(
+///         RateLimit::per_second(10.0)
+///             .with_burst(50)
+///             .keyed_by(RateLimitKey::Message),
+///     )
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+    pub key: RateLimitKey,
+}
+
+impl RateLimit {
+    pub fn per_second(per_second: f64) -> Self {
+        Self {
+            per_second: per_second.max(0.0),
+            burst: per_second.ceil().max(1.0) as u32,
+            key: RateLimitKey::Global,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn keyed_by(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    fn key_for(&self, record: &Record) -> String {
+        match self.key {
+            RateLimitKey::Global => String::new(),
+            RateLimitKey::Target => record.context.target.clone(),
+            RateLimitKey::Subtitle => record.context.subtitle.clone().unwrap_or_default(),
+            RateLimitKey::Message => record.message.clone(),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    suppressed: u64,
+}
+
+pub enum RateDecision {
+    Allow,
+    // Let through after `suppressed` records for `key` were dropped
+    Resume { key: String, suppressed: u64 },
+    Suppress,
+}
+
+/// Token buckets for a [`RateLimit`], one per key.
+pub struct RateLimiter {
+    limit: Option<RateLimit>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, record: &Record) -> RateDecision {
+        let Some(limit) = self.limit else {
+            return RateDecision::Allow;
+        };
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return RateDecision::Allow;
+        };
+
+        let now = Instant::now();
+        let burst = f64::from(limit.burst);
+        if buckets.len() >= MAX_KEYS {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.suppressed > 0 || bucket.tokens + elapsed * limit.per_second < burst
+            });
+        }
+
+        let key = limit.key_for(record);
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: burst,
+            refilled: now,
+            suppressed: 0,
+        });
+
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(burst);
+        bucket.refilled = now;
+
+        if bucket.tokens < 1.0 {
+            bucket.suppressed += 1;
+            return RateDecision::Suppress;
+        }
+        bucket.tokens -= 1.0;
+
+        match std::mem::take(&mut bucket.suppressed) {
+            0 => RateDecision::Allow,
+            suppressed => RateDecision::Resume { key, suppressed },
+        }
+    }
+
+    pub fn limit(&self) -> Option<&RateLimit> {
+        self.limit.as_ref()
+    }
+}
+
+/// Once-per-interval gate for a single call site, behind `info_every!` and friends.
+pub struct Throttle {
+    last: Mutex<Option<Instant>>,
+}
+
+impl Throttle {
+    pub const fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    // Whether `interval` has passed since the last time this returned true
+    pub fn ready(&self, interval: Duration) -> bool {
+        let Ok(mut last) = self.last.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+        match *last {
+            Some(at) if now.duration_since(at) < interval => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn suppressed_notice(key: &str, suppressed: u64) -> String {
+    if key.is_empty() {
+        format!("Suppressed {} messages over the rate limit", suppressed)
+    } else {
+        format!("Suppressed {} similar messages ({})", suppressed, key)
+    }
+}
@@ -0,0 +1,28 @@
+use std::cell::Cell;
+
+thread_local! {
+    // xorshift64* state, seeded per thread from the OS random source behind uuid
+    static STATE: Cell<u64> = Cell::new(uuid::Uuid::new_v4().as_u64_pair().0 | 1);
+}
+
+fn next() -> u64 {
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
+
+// True for roughly `rate` of calls; cheap enough to run before every record
+pub(crate) fn keep(rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    ((next() >> 11) as f64 / (1u64 << 53) as f64) < rate
+}
@@ -0,0 +1,59 @@
+use crate::level::LogLevel;
+use serde_json::{json, Value};
+
+/// Version of the JSON output format, written to every record as `schema_version`.
+///
+/// Bumped whenever a field is removed, renamed or changes type; adding an optional
+/// field keeps the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema (draft 2020-12) describing one record of the `Json` formatter.
+///
+/// Describes the default key names. With [`JsonFields::Flatten`](crate::JsonFields::Flatten)
+/// fields become extra top-level keys, which this schema's `additionalProperties: false`
+/// rejects, and [`LogConfig::with_json_key`](crate::LogConfig::with_json_key) renames keys.
+///
+/// ```
+/// let schema = logflow::schema::json_schema();
+/// assert_eq!(
+///     schema["properties"]["schema_version"]["const"],
+///     logflow::schema::SCHEMA_VERSION
+/// );
+/// ```
+pub fn json_schema() -> Value {
+    let levels: Vec<&str> = LogLevel::all()
+        .iter()
+        .copied()
+        .chain(LogLevel::custom_levels())
+        .map(|level| level.as_str())
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "LogFlow record",
+        "type": "object",
+        "required": ["schema_version", "timestamp", "level", "message", "target", "id", "nesting_level"],
+        "additionalProperties": false,
+        "properties": {
+            "schema_version": { "const": SCHEMA_VERSION },
+            "timestamp": {
+                "oneOf": [
+                    { "type": "string", "format": "date-time" },
+                    { "type": "integer" }
+                ]
+            },
+            "level": { "enum": levels },
+            "message": { "type": "string" },
+            "target": { "type": "string" },
+            "id": { "type": "string" },
+            "nesting_level": { "type": "integer", "minimum": 0 },
+            "subtitle": { "type": "string" },
+            "module": { "type": "string" },
+            "file": { "type": "string" },
+            "line": { "type": "integer", "minimum": 0 },
+            "parent_id": { "type": "string" },
+            "backtrace": { "type": "array", "items": { "type": "string" } },
+            "fields": { "type": "object" }
+        }
+    })
+}
@@ -0,0 +1,135 @@
+use crate::context::FieldValue;
+use crate::logger::LogScope;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "async")]
+use crate::async_logger::AsyncLogScope;
+
+pub const HTTP_METHOD: &str = "http.request.method";
+pub const HTTP_PATH: &str = "url.path";
+pub const HTTP_STATUS: &str = "http.response.status_code";
+pub const DB_SYSTEM: &str = "db.system";
+pub const DB_QUERY: &str = "db.query.text";
+pub const DB_ROWS: &str = "db.response.returned_rows";
+pub const JOB_NAME: &str = "job.name";
+pub const JOB_ID: &str = "job.id";
+pub const JOB_ATTEMPT: &str = "job.attempt";
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Scopes a typed handle can wrap: [`LogScope`] and `AsyncLogScope`.
+pub trait ScopeHandle: sealed::Sealed {
+    fn set_field(&mut self, key: &str, value: FieldValue);
+}
+
+impl sealed::Sealed for LogScope<'_> {}
+
+impl ScopeHandle for LogScope<'_> {
+    fn set_field(&mut self, key: &str, value: FieldValue) {
+        LogScope::set_field(self, key, value);
+    }
+}
+
+#[cfg(feature = "async")]
+impl sealed::Sealed for AsyncLogScope<'_> {}
+
+#[cfg(feature = "async")]
+impl ScopeHandle for AsyncLogScope<'_> {
+    fn set_field(&mut self, key: &str, value: FieldValue) {
+        AsyncLogScope::set_field(self, key, value);
+    }
+}
+
+macro_rules! typed_scope {
+    ($name:ident) => {
+        impl<S> Deref for $name<S> {
+            type Target = S;
+
+            fn deref(&self) -> &S {
+                &self.scope
+            }
+        }
+
+        impl<S> DerefMut for $name<S> {
+            fn deref_mut(&mut self) -> &mut S {
+                &mut self.scope
+            }
+        }
+
+        impl<S> $name<S> {
+            pub fn into_inner(self) -> S {
+                self.scope
+            }
+        }
+    };
+}
+
+/// Typed scope handle that records request fields under canonical names.
+///
+/// Like the other typed handles it wraps an ordinary scope and derefs to it, so all
+/// logging methods stay available:
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::scopes::HttpRequestScope;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// let mut request = HttpRequestScope::new(logger.begin_scope("request"), "GET", "/users");
+/// request.set_status(200);
+/// request.info("request finished").unwrap();
+/// ```
+pub struct HttpRequestScope<S> {
+    scope: S,
+}
+
+impl<S: ScopeHandle> HttpRequestScope<S> {
+    pub fn new(mut scope: S, method: &str, path: &str) -> Self {
+        scope.set_field(HTTP_METHOD, FieldValue::from(method));
+        scope.set_field(HTTP_PATH, FieldValue::from(path));
+        Self { scope }
+    }
+
+    pub fn set_status(&mut self, status: u16) {
+        self.scope.set_field(HTTP_STATUS, FieldValue::from(status));
+    }
+}
+
+typed_scope!(HttpRequestScope);
+
+pub struct DbQueryScope<S> {
+    scope: S,
+}
+
+impl<S: ScopeHandle> DbQueryScope<S> {
+    pub fn new(mut scope: S, system: &str, query: &str) -> Self {
+        scope.set_field(DB_SYSTEM, FieldValue::from(system));
+        scope.set_field(DB_QUERY, FieldValue::from(query));
+        Self { scope }
+    }
+
+    pub fn set_rows(&mut self, rows: u64) {
+        self.scope.set_field(DB_ROWS, FieldValue::from(rows));
+    }
+}
+
+typed_scope!(DbQueryScope);
+
+pub struct JobScope<S> {
+    scope: S,
+}
+
+impl<S: ScopeHandle> JobScope<S> {
+    pub fn new(mut scope: S, name: &str, id: &str) -> Self {
+        scope.set_field(JOB_NAME, FieldValue::from(name));
+        scope.set_field(JOB_ID, FieldValue::from(id));
+        Self { scope }
+    }
+
+    pub fn set_attempt(&mut self, attempt: u32) {
+        self.scope.set_field(JOB_ATTEMPT, FieldValue::from(attempt));
+    }
+}
+
+typed_scope!(JobScope);
@@ -0,0 +1,318 @@
+use crate::batch::BatchSink;
+use crate::output::{OutputWriter, Record};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+const MIN_RETRY: Duration = Duration::from_millis(100);
+const MAX_RETRY: Duration = Duration::from_secs(30);
+
+/// Write-ahead queue in front of a network writer or batch sink.
+///
+/// Records are appended to a local spool file and shipped to the target by a
+/// background thread. The shipped position is persisted next to the spool
+/// (`<path>.offset`), so records not yet acknowledged survive outages and restarts.
+/// Delivery is at-least-once: a crash between shipping and acknowledging resends a record.
+pub struct SpoolWriter {
+    shared: Arc<Spool>,
+    wake: Sender<()>,
+    shipper: Option<JoinHandle<()>>,
+}
+
+struct Spool {
+    path: PathBuf,
+    offset_path: PathBuf,
+    file: Mutex<File>,
+    target: Target,
+    stop: AtomicBool,
+}
+
+enum Target {
+    // A record counts as delivered once `write_record` returns
+    Writer(Arc<dyn OutputWriter>),
+    // Up to `max_records` per request; delivered once the sink accepts them
+    Batch(Arc<dyn BatchSink>, usize),
+}
+
+impl SpoolWriter {
+    pub fn new<P: AsRef<Path>>(inner: Arc<dyn OutputWriter>, path: P) -> io::Result<Self> {
+        Self::open(Target::Writer(inner), path.as_ref())
+    }
+
+    // Ships straight to `sink` instead of through a `BatchWriter`, so the offset only
+    // moves past records the destination accepted
+    pub fn for_sink<S: BatchSink, P: AsRef<Path>>(
+        sink: S,
+        max_records: usize,
+        path: P,
+    ) -> io::Result<Self> {
+        Self::open(
+            Target::Batch(Arc::new(sink), max_records.max(1)),
+            path.as_ref(),
+        )
+    }
+
+    fn open(target: Target, path: &Path) -> io::Result<Self> {
+        let path = path.to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let mut offset_path = path.clone().into_os_string();
+        offset_path.push(".offset");
+
+        let shared = Arc::new(Spool {
+            path,
+            offset_path: PathBuf::from(offset_path),
+            file: Mutex::new(file),
+            target,
+            stop: AtomicBool::new(false),
+        });
+
+        let (wake, receiver) = mpsc::channel();
+        let shipper = {
+            let shared = Arc::clone(&shared);
+            std::thread::Builder::new()
+                .name("logflow-spool".to_string())
+                .spawn(move || shared.run(receiver))?
+        };
+
+        Ok(Self {
+            shared,
+            wake,
+            shipper: Some(shipper),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.shared.path
+    }
+
+    // Number of bytes appended but not yet acknowledged by the target
+    pub fn pending_bytes(&self) -> io::Result<u64> {
+        let len = std::fs::metadata(&self.shared.path)?.len();
+        Ok(len.saturating_sub(self.shared.read_offset()))
+    }
+
+    fn wake(&self) {
+        let _ = self.wake.send(());
+    }
+
+    fn append(&self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record).map_err(io::Error::other)?;
+        line.push(b'\n');
+
+        let mut file = self.shared.lock_file()?;
+        file.write_all(&line)?;
+        drop(file);
+
+        self.wake();
+        Ok(())
+    }
+}
+
+impl OutputWriter for SpoolWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.append(&Record::raw(data))
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.append(record)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.shared.lock_file()?.flush()
+    }
+
+    fn health_check(&self) -> io::Result<()> {
+        std::fs::metadata(&self.shared.path)?;
+        match &self.shared.target {
+            Target::Writer(writer) => writer.health_check(),
+            Target::Batch(sink, _) => sink.health_check(),
+        }
+    }
+}
+
+impl Drop for SpoolWriter {
+    fn drop(&mut self) {
+        // One last shipping attempt; anything still undelivered stays on disk
+        self.shared.stop.store(true, Ordering::SeqCst);
+        self.wake();
+        if let Some(shipper) = self.shipper.take() {
+            let _ = shipper.join();
+        }
+    }
+}
+
+impl Spool {
+    fn lock_file(&self) -> io::Result<std::sync::MutexGuard<'_, File>> {
+        self.file
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire spool lock"))
+    }
+
+    fn run(&self, wake: mpsc::Receiver<()>) {
+        let mut retry = MIN_RETRY;
+        loop {
+            let wait = match self.ship() {
+                Ok(()) => {
+                    retry = MIN_RETRY;
+                    IDLE_INTERVAL
+                }
+                Err(_) => {
+                    let wait = retry;
+                    retry = (retry * 2).min(MAX_RETRY);
+                    wait
+                }
+            };
+
+            if self.stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            if let Err(RecvTimeoutError::Disconnected) = wake.recv_timeout(wait) {
+                break;
+            }
+        }
+    }
+
+    fn ship(&self) -> io::Result<()> {
+        let len = std::fs::metadata(&self.path)?.len();
+        let mut offset = self.read_offset();
+        if offset > len {
+            // The spool was truncated before the offset reset reached disk
+            offset = 0;
+        }
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        reader.seek(SeekFrom::Start(offset))?;
+
+        let result = match &self.target {
+            Target::Writer(writer) => self.ship_records(&mut reader, &mut offset, writer),
+            Target::Batch(sink, max_records) => {
+                self.ship_batches(&mut reader, &mut offset, &**sink, *max_records)
+            }
+        };
+        // Whatever was acknowledged before a failure is compacted away regardless
+        self.compact(offset)?;
+        result
+    }
+
+    fn ship_records(
+        &self,
+        reader: &mut BufReader<File>,
+        offset: &mut u64,
+        writer: &Arc<dyn OutputWriter>,
+    ) -> io::Result<()> {
+        while let Some((record, read)) = next_record(reader)? {
+            if let Some(record) = record {
+                writer.write_record(&record)?;
+            }
+            *offset += read;
+            self.write_offset(*offset)?;
+        }
+        writer.flush()
+    }
+
+    fn ship_batches(
+        &self,
+        reader: &mut BufReader<File>,
+        offset: &mut u64,
+        sink: &dyn BatchSink,
+        max_records: usize,
+    ) -> io::Result<()> {
+        loop {
+            // Each record with the spool bytes up to and including its line
+            let mut records = Vec::new();
+            let mut ends = Vec::new();
+            let mut end = *offset;
+            while records.len() < max_records {
+                let Some((record, read)) = next_record(reader)? else {
+                    break;
+                };
+                end += read;
+                if let Some(record) = record {
+                    records.push(record);
+                    ends.push(end);
+                }
+            }
+
+            if records.is_empty() {
+                // Only unreadable lines, if any; nothing to deliver
+                if end > *offset {
+                    *offset = end;
+                    self.write_offset(end)?;
+                }
+                return Ok(());
+            }
+
+            let full = records.len() == max_records;
+            let retry = sink.send_partial(&records)?;
+            match retry.iter().min() {
+                // Records before the first one to retry are delivered; the rest are
+                // sent again, so a partial batch may repeat some of them
+                Some(&first) => {
+                    if first > 0 {
+                        *offset = ends[first - 1];
+                        self.write_offset(*offset)?;
+                    }
+                    return Err(io::Error::other(format!(
+                        "{} spooled records rejected, retrying",
+                        retry.len()
+                    )));
+                }
+                None => {
+                    *offset = end;
+                    self.write_offset(end)?;
+                }
+            }
+
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+    // Once everything is acknowledged the spool is emptied so it doesn't grow without bound
+    fn compact(&self, offset: u64) -> io::Result<()> {
+        let file = self.lock_file()?;
+        if offset > 0 && file.metadata()?.len() == offset {
+            file.set_len(0)?;
+            self.write_offset(0)?;
+        }
+        Ok(())
+    }
+
+    fn read_offset(&self) -> u64 {
+        std::fs::read_to_string(&self.offset_path)
+            .ok()
+            .and_then(|offset| offset.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn write_offset(&self, offset: u64) -> io::Result<()> {
+        let mut temp = self.offset_path.clone().into_os_string();
+        temp.push(".tmp");
+        std::fs::write(&temp, offset.to_string())?;
+        std::fs::rename(&temp, &self.offset_path)
+    }
+}
+
+// The next complete line and its length in bytes; `None` for an unreadable record.
+// A partially appended line is left for the next pass.
+fn next_record(reader: &mut BufReader<File>) -> io::Result<Option<(Option<Record>, u64)>> {
+    let mut line = String::new();
+    let read = reader.read_line(&mut line)?;
+    if read == 0 || !line.ends_with('\n') {
+        return Ok(None);
+    }
+    let record = serde_json::from_str::<Record>(line.trim_end()).ok();
+    Ok(Some((record, read as u64)))
+}
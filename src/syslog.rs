@@ -0,0 +1,371 @@
+use crate::compression::{Compression, Encoder};
+use crate::level::LogLevel;
+use crate::output::{OutputWriter, Record};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SyslogFormat {
+    #[default]
+    Rfc5424,
+    Rfc3164,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyslogTransport {
+    Udp(String),
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl SyslogTransport {
+    pub fn local_udp() -> Self {
+        SyslogTransport::Udp("127.0.0.1:514".to_string())
+    }
+
+    #[cfg(unix)]
+    pub fn dev_log() -> Self {
+        SyslogTransport::Unix(PathBuf::from("/dev/log"))
+    }
+}
+
+pub fn severity(level: LogLevel) -> u8 {
+    match level.base_level() {
+        LogLevel::Trace | LogLevel::Debug => 7,
+        LogLevel::Info => 6,
+        LogLevel::Warn => 4,
+        LogLevel::Error => 3,
+        _ => 2,
+    }
+}
+
+// Bounds connecting and writing over TCP, so a hung collector can't stall logging
+const TIMEOUT: Duration = Duration::from_secs(5);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+enum Connection {
+    Udp(UdpSocket),
+    Tcp(Encoder<TcpStream>),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+impl Connection {
+    // Compression only applies to TCP; datagrams have to stay self-contained
+    fn open(transport: &SyslogTransport, compression: Compression) -> io::Result<Self> {
+        match transport {
+            SyslogTransport::Udp(addr) => {
+                let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "No syslog address")
+                })?;
+                let local = if addr.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(addr)?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogTransport::Tcp(addr) => {
+                let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "No syslog address")
+                })?;
+                let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Ok(Connection::Tcp(compression.stream(stream)?))
+            }
+            #[cfg(unix)]
+            SyslogTransport::Unix(path) => {
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                Ok(Connection::Unix(socket))
+            }
+        }
+    }
+
+    // Errors the OS has recorded on the socket, e.g. ICMP port unreachable for UDP or
+    // a reset TCP connection
+    fn check(&self) -> io::Result<()> {
+        let error = match self {
+            Connection::Udp(socket) => socket.take_error()?,
+            Connection::Tcp(stream) => {
+                stream.get_ref().peer_addr()?;
+                stream.get_ref().take_error()?
+            }
+            #[cfg(unix)]
+            Connection::Unix(socket) => socket.take_error()?,
+        };
+        error.map_or(Ok(()), Err)
+    }
+
+    fn send(&mut self, format: SyslogFormat, message: &str) -> io::Result<()> {
+        match self {
+            Connection::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            // RFC 6587 framing: octet counting for 5424, LF-terminated for legacy 3164
+            Connection::Tcp(stream) => {
+                match format {
+                    SyslogFormat::Rfc5424 => write!(stream, "{} {}", message.len(), message)?,
+                    SyslogFormat::Rfc3164 => writeln!(stream, "{}", message)?,
+                }
+                stream.flush()
+            }
+            #[cfg(unix)]
+            Connection::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Connection::Tcp(stream) = self {
+            let _ = stream.try_finish();
+        }
+    }
+}
+
+// The connection, and when a new one may be tried after connecting failed
+struct Link {
+    connection: Option<Connection>,
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl Link {
+    fn new(connection: Option<Connection>) -> Self {
+        Self {
+            connection,
+            retry_at: None,
+            backoff: INITIAL_BACKOFF,
+        }
+    }
+
+    // Failed connects back off exponentially, so records logged while the collector
+    // is down fail fast instead of each waiting on a connect
+    fn reconnect(
+        &mut self,
+        transport: &SyslogTransport,
+        compression: Compression,
+    ) -> io::Result<&mut Connection> {
+        if self
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() < retry_at)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Syslog collector unreachable, waiting to reconnect",
+            ));
+        }
+        self.connect(transport, compression)
+    }
+
+    fn connect(
+        &mut self,
+        transport: &SyslogTransport,
+        compression: Compression,
+    ) -> io::Result<&mut Connection> {
+        match Connection::open(transport, compression) {
+            Ok(connection) => {
+                self.retry_at = None;
+                self.backoff = INITIAL_BACKOFF;
+                Ok(self.connection.insert(connection))
+            }
+            Err(err) => {
+                self.connection = None;
+                self.retry_at = Some(Instant::now() + self.backoff);
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                Err(err)
+            }
+        }
+    }
+}
+
+pub struct SyslogWriter {
+    transport: SyslogTransport,
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: String,
+    hostname: String,
+    pid: u32,
+    compression: Compression,
+    link: Mutex<Link>,
+}
+
+impl SyslogWriter {
+    pub fn connect(
+        transport: SyslogTransport,
+        format: SyslogFormat,
+        facility: Facility,
+        app_name: Option<&str>,
+    ) -> io::Result<Self> {
+        Self::deferred(transport, format, facility, app_name).connected()
+    }
+
+    // Connects on first send, so an unreachable collector doesn't fail the build
+    pub fn deferred(
+        transport: SyslogTransport,
+        format: SyslogFormat,
+        facility: Facility,
+        app_name: Option<&str>,
+    ) -> Self {
+        Self {
+            transport,
+            format,
+            facility,
+            app_name: app_name.map_or_else(default_app_name, str::to_string),
+            hostname: hostname(),
+            pid: std::process::id(),
+            compression: Compression::None,
+            link: Mutex::new(Link::new(None)),
+        }
+    }
+
+    // Takes effect on the next (re)connect
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn connected(self) -> io::Result<Self> {
+        let connection = Connection::open(&self.transport, self.compression)?;
+        *self.lock_link()? = Link::new(Some(connection));
+        Ok(self)
+    }
+
+    fn lock_link(&self) -> io::Result<std::sync::MutexGuard<'_, Link>> {
+        self.link
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire syslog lock"))
+    }
+
+    fn send(&self, level: LogLevel, text: &str, timestamp: DateTime<Utc>) -> io::Result<()> {
+        let message = self.format_message(level, text, timestamp);
+
+        let mut link = self.lock_link()?;
+        if let Some(connection) = link.connection.as_mut() {
+            if connection.send(self.format, &message).is_ok() {
+                return Ok(());
+            }
+            link.connection = None;
+        }
+
+        // (Re)connect once, e.g. after the collector restarted
+        let connection = link.reconnect(&self.transport, self.compression)?;
+        connection.send(self.format, &message)
+    }
+
+    pub fn format_message(&self, level: LogLevel, message: &str, now: DateTime<Utc>) -> String {
+        let priority = (self.facility as u8) * 8 + severity(level);
+
+        match self.format {
+            SyslogFormat::Rfc5424 => format!(
+                "<{}>1 {} {} {} {} - - {}",
+                priority,
+                now.to_rfc3339_opts(SecondsFormat::Millis, true),
+                self.hostname,
+                self.app_name,
+                self.pid,
+                message
+            ),
+            SyslogFormat::Rfc3164 => format!(
+                "<{}>{} {} {}[{}]: {}",
+                priority,
+                now.format("%b %e %H:%M:%S"),
+                self.hostname,
+                self.app_name,
+                self.pid,
+                message
+            ),
+        }
+    }
+}
+
+impl OutputWriter for SyslogWriter {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        let text = String::from_utf8_lossy(data);
+        self.send(LogLevel::Info, text.trim_end_matches('\n'), Utc::now())
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.send(record.level, &record.line, record.context.timestamp)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.lock_link()?.connection.as_mut() {
+            Some(Connection::Tcp(stream)) => stream.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    // Connects if there is no healthy connection yet, so an unreachable collector fails
+    // the check instead of the first record
+    fn health_check(&self) -> io::Result<()> {
+        let mut link = self.lock_link()?;
+        if link
+            .connection
+            .as_ref()
+            .is_some_and(|open| open.check().is_ok())
+        {
+            return Ok(());
+        }
+        link.connect(&self.transport, self.compression).map(|_| ())
+    }
+}
+
+pub(crate) fn default_app_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "logflow".to_string())
+}
+
+pub(crate) fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
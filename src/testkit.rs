@@ -0,0 +1,320 @@
+use crate::batch::BatchSink;
+use crate::output::{OutputType, OutputWriter, Record};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub use crate::facade::CaptureLogger as TestLogger;
+
+/// Message condition for [`TestLogger::assert_logged`], shown in failure messages.
+pub struct Matcher {
+    description: String,
+    test: Box<dyn Fn(&str) -> bool + Send + Sync>,
+}
+
+impl Matcher {
+    pub fn new<F>(description: &str, test: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            description: description.to_string(),
+            test: Box::new(test),
+        }
+    }
+
+    pub fn matches(&self, message: &str) -> bool {
+        (self.test)(message)
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+pub fn contains(text: &str) -> Matcher {
+    let text = text.to_string();
+    Matcher::new(&format!("containing {:?}", text), move |message| {
+        message.contains(text.as_str())
+    })
+}
+
+pub fn equals(text: &str) -> Matcher {
+    let text = text.to_string();
+    Matcher::new(&format!("equal to {:?}", text), move |message| {
+        message == text
+    })
+}
+
+pub fn starts_with(text: &str) -> Matcher {
+    let text = text.to_string();
+    Matcher::new(&format!("starting with {:?}", text), move |message| {
+        message.starts_with(text.as_str())
+    })
+}
+
+pub fn any() -> Matcher {
+    Matcher::new("of any text", |_| true)
+}
+
+/// Failures and latency injected into a fake sink, shared by all its clones.
+#[derive(Default)]
+struct Faults {
+    fail_next: AtomicUsize,
+    failing: AtomicBool,
+    latency: Mutex<Duration>,
+    attempts: AtomicUsize,
+}
+
+impl Faults {
+    // Sleeps for the configured latency, then decides whether this call fails
+    fn attempt(&self) -> io::Result<()> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+
+        let latency = self
+            .latency
+            .lock()
+            .map(|latency| *latency)
+            .unwrap_or_default();
+        if !latency.is_zero() {
+            std::thread::sleep(latency);
+        }
+
+        let scheduled = self
+            .fail_next
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if scheduled || self.failing.load(Ordering::SeqCst) {
+            return Err(io::Error::other("injected failure"));
+        }
+        Ok(())
+    }
+}
+
+macro_rules! fault_controls {
+    () => {
+        /// Makes the next `count` writes fail.
+        pub fn fail_next(&self, count: usize) {
+            self.faults.fail_next.store(count, Ordering::SeqCst);
+        }
+
+        /// Makes every write fail until called again with `false`.
+        pub fn set_failing(&self, failing: bool) {
+            self.faults.failing.store(failing, Ordering::SeqCst);
+        }
+
+        /// Delays every write, e.g. to fill a non-blocking writer's queue.
+        pub fn set_latency(&self, latency: Duration) {
+            if let Ok(mut current) = self.faults.latency.lock() {
+                *current = latency;
+            }
+        }
+
+        /// Writes attempted so far, failed ones included.
+        pub fn attempts(&self) -> usize {
+            self.faults.attempts.load(Ordering::SeqCst)
+        }
+    };
+}
+
+/// Output that records what it receives and fails or stalls on command.
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::prelude::*;
+/// use logflow::testkit::FakeSink;
+///
+/// let sink = FakeSink::new();
+/// let logger = LogFlow::new().with_output(sink.output()).build().unwrap();
+///
+/// sink.fail_next(1);
+/// assert!(logger.info("lost").is_err());
+/// logger.info("kept").unwrap();
+/// assert_eq!(sink.messages(), vec!["kept"]);
+/// assert_eq!(sink.attempts(), 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeSink {
+    records: Arc<Mutex<Vec<Record>>>,
+    faults: Arc<Faults>,
+    flushes: Arc<AtomicUsize>,
+    closes: Arc<AtomicUsize>,
+}
+
+impl FakeSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output(&self) -> OutputType {
+        OutputType::Custom(Arc::new(self.clone()))
+    }
+
+    fault_controls!();
+
+    pub fn records(&self) -> Vec<Record> {
+        self.records
+            .lock()
+            .map(|records| records.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn messages(&self) -> Vec<String> {
+        self.records()
+            .into_iter()
+            .map(|record| record.message)
+            .collect()
+    }
+
+    pub fn flushes(&self) -> usize {
+        self.flushes.load(Ordering::SeqCst)
+    }
+
+    // Times the sink was closed, e.g. by `fatal_exit`
+    pub fn closes(&self) -> usize {
+        self.closes.load(Ordering::SeqCst)
+    }
+}
+
+impl OutputWriter for FakeSink {
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.write_record(&Record::raw(data))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.flushes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_structured(&self) -> bool {
+        true
+    }
+
+    fn write_record(&self, record: &Record) -> io::Result<()> {
+        self.faults.attempt()?;
+        self.records
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire fake sink lock"))?
+            .push(record.clone());
+        Ok(())
+    }
+
+    fn close(&self) -> io::Result<()> {
+        self.closes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// [`BatchSink`] stand-in for network outputs, for testing batching and retries
+/// through a [`BatchWriter`](crate::batch::BatchWriter).
+///
+/// ```
+/// # if !logflow::LOGGING_ENABLED { return; }
+/// use logflow::batch::{BatchOptions, BatchWriter};
+/// use logflow::prelude::*;
+/// use logflow::testkit::FakeBatchSink;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let sink = FakeBatchSink::new();
+/// sink.fail_next(2);
+/// let options = BatchOptions::default()
+///     .with_max_delay(Duration::from_millis(10))
+///     .with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+/// let writer = BatchWriter::new(sink.clone(), options).unwrap();
+/// let logger = LogFlow::new()
+///     .with_output(OutputType::Custom(Arc::new(writer)))
+///     .build()
+///     .unwrap();
+///
+/// logger.info("retried").unwrap();
+/// drop(logger);
+/// assert_eq!(sink.batches().len(), 1);
+/// assert_eq!(sink.attempts(), 3);
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeBatchSink {
+    batches: Arc<Mutex<Vec<Vec<Record>>>>,
+    faults: Arc<Faults>,
+}
+
+impl FakeBatchSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fault_controls!();
+
+    // Batches delivered successfully, in order
+    pub fn batches(&self) -> Vec<Vec<Record>> {
+        self.batches
+            .lock()
+            .map(|batches| batches.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.batches().into_iter().flatten().collect()
+    }
+}
+
+impl BatchSink for FakeBatchSink {
+    fn send(&self, records: &[Record]) -> io::Result<()> {
+        self.faults.attempt()?;
+        self.batches
+            .lock()
+            .map_err(|_| io::Error::other("Failed to acquire fake sink lock"))?
+            .push(records.to_vec());
+        Ok(())
+    }
+}
+
+/// Scratch directory for file output tests, removed on drop.
+pub struct TempLogDir {
+    path: PathBuf,
+}
+
+impl TempLogDir {
+    pub fn new() -> io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "logflow-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    // Names of the files currently in the directory, sorted
+    pub fn files(&self) -> io::Result<Vec<String>> {
+        let mut names = std::fs::read_dir(&self.path)?
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()?;
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn read(&self, name: &str) -> io::Result<String> {
+        std::fs::read_to_string(self.file(name))
+    }
+}
+
+impl Drop for TempLogDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::context::{FieldValue, LogContext};
+use crate::level::LogLevel;
+use crate::logger::LogFlow;
+use indexmap::IndexMap;
+use std::fmt;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// `tracing_subscriber` layer that writes tracing events through a [`LogFlow`] logger.
+///
+/// Spans become nested scopes: events are indented under the spans they occur in and
+/// carry the fields of every enclosing span, with the closest span winning.
+///
+/// ```
+/// use logflow::prelude::*;
+/// use logflow::LogFlowLayer;
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let logger = LogFlow::new().build().unwrap();
+/// let subscriber = tracing_subscriber::registry().with(LogFlowLayer::new(logger));
+///
+/// tracing::subscriber::with_default(subscriber, || {
+///     let span = tracing::info_span!("request", method = "GET");
+///     let _enter = span.enter();
+///     tracing::info!(status = 200, "handled");
+/// });
+/// ```
+pub struct LogFlowLayer {
+    logger: LogFlow,
+}
+
+impl LogFlowLayer {
+    pub fn new(logger: LogFlow) -> Self {
+        Self { logger }
+    }
+
+    pub fn logger(&self) -> &LogFlow {
+        &self.logger
+    }
+}
+
+pub fn level_from_tracing(level: &Level) -> LogLevel {
+    match *level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    }
+}
+
+// Context of a span, stored in its registry extensions
+struct SpanContext(LogContext);
+
+struct FieldVisitor<'a> {
+    fields: &'a mut IndexMap<String, FieldValue>,
+    message: Option<&'a mut Option<String>>,
+}
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: FieldValue) {
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if let (Some(message), "message") = (self.message.as_mut(), field.name()) {
+            **message = Some(format!("{:?}", value));
+            return;
+        }
+        self.insert(field, format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if let (Some(message), "message") = (self.message.as_mut(), field.name()) {
+            **message = Some(value.to_string());
+            return;
+        }
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+}
+
+impl<S> Layer<S> for LogFlowLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let metadata = span.metadata();
+        let target = metadata.target().to_string();
+
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanContext>()
+                .map(|c| c.0.clone())
+        });
+        let mut context = match parent {
+            Some(parent) => self.logger.config().child_context(&parent, target),
+            None => self.logger.scoped_context(target),
+        }
+        .with_scope(metadata.name());
+
+        attrs.record(&mut FieldVisitor {
+            fields: &mut context.fields,
+            message: None,
+        });
+        span.extensions_mut().insert(SpanContext(context));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanContext(context)) = extensions.get_mut::<SpanContext>() {
+            values.record(&mut FieldVisitor {
+                fields: &mut context.fields,
+                message: None,
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let target = metadata.target().to_string();
+
+        let mut context = None;
+        let mut fields = IndexMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(SpanContext(span_context)) = span.extensions().get::<SpanContext>() {
+                    fields.extend(span_context.fields.clone());
+                    context = Some(span_context.clone());
+                }
+            }
+        }
+        let mut context = match context {
+            // Like records logged through a scope, events share their span's indentation
+            Some(span_context) => self
+                .logger
+                .config()
+                .child_context(&span_context, target)
+                .with_level(span_context.level),
+            None => self.logger.scoped_context(target),
+        };
+
+        let mut message = None;
+        event.record(&mut FieldVisitor {
+            fields: &mut fields,
+            message: Some(&mut message),
+        });
+        context.fields.extend(fields);
+
+        if let Some(module) = metadata.module_path() {
+            context = context.with_module(module);
+        }
+        if let (Some(file), Some(line)) = (metadata.file(), metadata.line()) {
+            context = context.with_file_line(file, line);
+        }
+
+        let message = message.unwrap_or_else(|| metadata.name().to_string());
+        let _ = self.logger.log_with_context(
+            level_from_tracing(metadata.level()),
+            &message,
+            Some(context),
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::config::LogConfig;
+use crate::diagnostics;
+use crate::logger::{lock_output, LogFlowError};
+use crate::output::{Output, Record};
+use std::io;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// Records paired with whether the primary output takes them
+pub(crate) type Routed = Vec<(Record, bool)>;
+
+enum Command {
+    Write(Routed),
+    Flush(mpsc::Sender<()>),
+    Shutdown,
+}
+
+// Logger side of the channel to the writer thread
+#[derive(Clone)]
+pub(crate) struct Worker {
+    sender: SyncSender<Command>,
+}
+
+impl Worker {
+    pub(crate) fn spawn(
+        output: Arc<Mutex<Output>>,
+        config: LogConfig,
+        capacity: usize,
+    ) -> io::Result<(Self, WorkerGuard)> {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let thread = std::thread::Builder::new()
+            .name("logflow-writer".to_string())
+            .spawn(move || run(&receiver, &output, &config))?;
+
+        let guard = WorkerGuard {
+            sender: sender.clone(),
+            thread: Some(thread),
+        };
+        Ok((Self { sender }, guard))
+    }
+
+    // Blocks while the queue is full; hands the records back once the guard has
+    // stopped the thread, so the caller can write them itself
+    pub(crate) fn send(&self, records: Routed) -> Result<(), Routed> {
+        self.sender.send(Command::Write(records)).map_err(
+            |mpsc::SendError(command)| match command {
+                Command::Write(records) => records,
+                _ => Vec::new(),
+            },
+        )
+    }
+
+    // Waits until everything queued so far is written; false once the thread is gone
+    pub(crate) fn flush(&self) -> bool {
+        let (done, wait) = mpsc::channel();
+        self.sender.send(Command::Flush(done)).is_ok() && wait.recv().is_ok()
+    }
+}
+
+/// Keeps the writer thread of a non-blocking [`LogFlow`](crate::LogFlow) alive.
+///
+/// Dropping the guard writes out everything still queued and stops the thread; the
+/// logger then goes back to writing synchronously. Hold it until the end of `main`.
+#[must_use = "dropping the guard stops the writer thread immediately"]
+pub struct WorkerGuard {
+    sender: SyncSender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(receiver: &Receiver<Command>, output: &Mutex<Output>, config: &LogConfig) {
+    // After a shutdown, still write what other threads queued before the channel closes
+    let mut draining = false;
+    loop {
+        let command = if draining {
+            receiver.try_recv().ok()
+        } else {
+            receiver.recv().ok()
+        };
+        let Some(command) = command else {
+            return;
+        };
+
+        match command {
+            Command::Write(records) => {
+                let result = lock_output(output, config).write_records_to(&records);
+                if let Err(err) = result {
+                    diagnostics::report(&LogFlowError::Output(err));
+                }
+            }
+            Command::Flush(done) => {
+                if let Err(err) = lock_output(output, config).flush() {
+                    diagnostics::report(&LogFlowError::Output(err));
+                }
+                let _ = done.send(());
+            }
+            Command::Shutdown => draining = true,
+        }
+    }
+}